@@ -0,0 +1,56 @@
+#ifndef FINAL_PROJ_H
+#define FINAL_PROJ_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define CHESS_OK 0
+
+#define CHESS_ERROR_NULL -1
+
+#define CHESS_ERROR_UTF8 -2
+
+#define CHESS_ERROR_FEN -3
+
+#define CHESS_ERROR_ILLEGAL_MOVE -4
+
+#define CHESS_ERROR_PANIC -5
+
+typedef enum ChessState {
+  CHESS_STATE_ONGOING,
+  CHESS_STATE_CHECK,
+  CHESS_STATE_CHECKMATE,
+  CHESS_STATE_STALEMATE,
+  CHESS_STATE_DRAW,
+} ChessState;
+
+typedef enum ChessColor {
+  CHESS_COLOR_WHITE,
+  CHESS_COLOR_BLACK,
+} ChessColor;
+
+typedef struct ChessGame ChessGame;
+
+struct ChessGame *chess_game_new(void);
+
+struct ChessGame *chess_game_new_variant(const char *variant);
+
+void chess_game_free(struct ChessGame *game);
+
+int32_t chess_game_load_fen(struct ChessGame *game, const char *text);
+
+size_t chess_game_fen(const struct ChessGame *game, char *buffer, size_t capacity);
+
+size_t chess_game_legal_move_count(const struct ChessGame *game);
+
+size_t chess_game_legal_moves(const struct ChessGame *game, char *buffer, size_t capacity);
+
+int32_t chess_game_make_move(struct ChessGame *game, const char *text);
+
+enum ChessState chess_game_state(const struct ChessGame *game);
+
+enum ChessColor chess_game_turn(const struct ChessGame *game);
+
+#endif  /* FINAL_PROJ_H */
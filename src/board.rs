@@ -1,83 +1,222 @@
-
 use crate::piece::Piece;
 use crate::piece::{PieceType, Color};
 
 pub const BOARD_SIZE: usize = 8;
+// largest board any variant can use, the grid is always allocated at this size
+pub const MAX_BOARD_SIZE: usize = 12;
+
+// how pieces are drawn, the letter sets are for terminals without chess glyph fonts
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GlyphSet {
+    Unicode, // ♔♕♖♗♘♙ / ♚♛♜♝♞♟
+    Ascii,   // KQRBNP / kqrbnp
+    German,  // KDTLSB
+    French,  // RDTFCP
+    Spanish, // RDTACP
+}
+
+impl GlyphSet {
+    pub fn from_name(name: &str) -> Option<GlyphSet> {
+        let names: [(&[&str], GlyphSet); 5] = [
+            (&["unicode"], GlyphSet::Unicode),
+            (&["ascii", "english"], GlyphSet::Ascii),
+            (&["german", "de"], GlyphSet::German),
+            (&["french", "fr"], GlyphSet::French),
+            (&["spanish", "es"], GlyphSet::Spanish),
+        ];
+        names.into_iter().find(|(aliases, _)| aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))).map(|(_, glyphs)| glyphs)
+    }
+
+    // letter sets use upper case for White and lower case for Black
+    pub fn symbol(&self, piece: &Piece) -> &'static str {
+        if *self == GlyphSet::Unicode {
+            return match (piece.color, piece.piece_type) {
+                (Color::White, PieceType::King) => "♔",
+                (Color::White, PieceType::Queen) => "♕",
+                (Color::White, PieceType::Rook) => "♖",
+                (Color::White, PieceType::Bishop) => "♗",
+                (Color::White, PieceType::Knight) => "♘",
+                (Color::White, PieceType::Pawn) => "♙",
+                (Color::Black, PieceType::King) => "♚",
+                (Color::Black, PieceType::Queen) => "♛",
+                (Color::Black, PieceType::Rook) => "♜",
+                (Color::Black, PieceType::Bishop) => "♝",
+                (Color::Black, PieceType::Knight) => "♞",
+                (Color::Black, PieceType::Pawn) => "♟",
+                // there are no chess glyphs for the fairy pieces so they use letters
+                (Color::White, PieceType::Archbishop) => "A",
+                (Color::White, PieceType::Chancellor) => "C",
+                (Color::White, PieceType::Amazon) => "Z",
+                (Color::Black, PieceType::Archbishop) => "a",
+                (Color::Black, PieceType::Chancellor) => "c",
+                (Color::Black, PieceType::Amazon) => "z",
+            };
+        }
+        // king, queen, rook, bishop, knight, pawn in each language, the fairy pieces keep their English letters
+        let letters = match (self, piece.color) {
+            (GlyphSet::German, Color::White) => ["K", "D", "T", "L", "S", "B"],
+            (GlyphSet::German, Color::Black) => ["k", "d", "t", "l", "s", "b"],
+            (GlyphSet::French, Color::White) => ["R", "D", "T", "F", "C", "P"],
+            (GlyphSet::French, Color::Black) => ["r", "d", "t", "f", "c", "p"],
+            (GlyphSet::Spanish, Color::White) => ["R", "D", "T", "A", "C", "P"],
+            (GlyphSet::Spanish, Color::Black) => ["r", "d", "t", "a", "c", "p"],
+            (_, Color::White) => ["K", "Q", "R", "B", "N", "P"],
+            (_, Color::Black) => ["k", "q", "r", "b", "n", "p"],
+        };
+        match (piece.piece_type, piece.color) {
+            (PieceType::King, _) => letters[0],
+            (PieceType::Queen, _) => letters[1],
+            (PieceType::Rook, _) => letters[2],
+            (PieceType::Bishop, _) => letters[3],
+            (PieceType::Knight, _) => letters[4],
+            (PieceType::Pawn, _) => letters[5],
+            (PieceType::Archbishop, Color::White) => "A",
+            (PieceType::Chancellor, Color::White) => "C",
+            (PieceType::Amazon, Color::White) => "Z",
+            (PieceType::Archbishop, Color::Black) => "a",
+            (PieceType::Chancellor, Color::Black) => "c",
+            (PieceType::Amazon, Color::Black) => "z",
+        }
+    }
+}
 
+// which side is drawn at the bottom of the board
 #[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Orientation {
+    White,
+    Black,
+    SideToMove,
+}
+
+impl Orientation {
+    pub fn black_at_bottom(&self, to_move: Color) -> bool {
+        match self {
+            Orientation::White => false,
+            Orientation::Black => true,
+            Orientation::SideToMove => to_move == Color::Black,
+        }
+    }
+    // turns the board around from however it is currently shown
+    pub fn flipped(&self, to_move: Color) -> Orientation {
+        if self.black_at_bottom(to_move) { Orientation::White } else { Orientation::Black }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Cell {
     Empty,
     Occupied(Piece),
 }
+// grid[0] is rank 1 (White's back rank) and grid[x][0] is the a-file
+// only the first `rows` x `cols` cells are part of the board
 #[derive(Clone)]
 pub struct Board {
-    pub grid: [[Cell; BOARD_SIZE]; BOARD_SIZE],
+    pub grid: [[Cell; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+    pub rows: usize,
+    pub cols: usize,
+    // how far the pawns start from their own edge, e.g. 1 for the 2nd rank
+    pub pawn_row: usize,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
 }
 
 impl Board {
     pub fn new() -> Self {
-        let mut grid = [[Cell::Empty; BOARD_SIZE]; BOARD_SIZE];
-        grid[0] = [
-            Cell::Occupied(Piece { piece_type: PieceType::Rook, color: Color::White, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Knight, color: Color::White, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Bishop, color: Color::White, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Queen, color: Color::White, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::King, color: Color::White, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Bishop, color: Color::White, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Knight, color: Color::White, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Rook, color: Color::White, has_moved: false }),
-        ];
-        grid[1] = [Cell::Occupied(Piece { piece_type: PieceType::Pawn, color: Color::White, has_moved: false }); BOARD_SIZE];
-        grid[6] = [Cell::Occupied(Piece { piece_type: PieceType::Pawn, color: Color::Black, has_moved: false }); BOARD_SIZE];
-        grid[7] = [
-            Cell::Occupied(Piece { piece_type: PieceType::Rook, color: Color::Black, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Knight, color: Color::Black, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Bishop, color: Color::Black, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Queen, color: Color::Black, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::King, color: Color::Black, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Bishop, color: Color::Black, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Knight, color: Color::Black, has_moved: false }),
-            Cell::Occupied(Piece { piece_type: PieceType::Rook, color: Color::Black, has_moved: false }),
+        Board::with_setup(BOARD_SIZE, BOARD_SIZE, &[
+            Some(PieceType::Rook), Some(PieceType::Knight), Some(PieceType::Bishop), Some(PieceType::Queen),
+            Some(PieceType::King), Some(PieceType::Bishop), Some(PieceType::Knight), Some(PieceType::Rook),
+        ], 1)
+    }
+    // 10x8 Capablanca chess with the archbishop and chancellor next to the bishops
+    pub fn capablanca() -> Self {
+        Board::with_setup(8, 10, &[
+            Some(PieceType::Rook), Some(PieceType::Knight), Some(PieceType::Archbishop), Some(PieceType::Bishop), Some(PieceType::Queen),
+            Some(PieceType::King), Some(PieceType::Bishop), Some(PieceType::Chancellor), Some(PieceType::Knight), Some(PieceType::Rook),
+        ], 1)
+    }
+    // 10x10 Grand chess: rooks alone in the corners, the other pieces on the 2nd rank and pawns on the 3rd
+    pub fn grand() -> Self {
+        let mut board = Board::with_setup(10, 10, &[], 2);
+        let second_rank = [
+            None, Some(PieceType::Knight), Some(PieceType::Bishop), Some(PieceType::Queen), Some(PieceType::King),
+            Some(PieceType::Chancellor), Some(PieceType::Archbishop), Some(PieceType::Bishop), Some(PieceType::Knight), None,
         ];
-        Self { grid }
+        for (col, piece_type) in second_rank.iter().enumerate() {
+            if let Some(piece_type) = *piece_type {
+                board.place_mirrored((1, col), piece_type);
+            }
+        }
+        board.place_mirrored((0, 0), PieceType::Rook);
+        board.place_mirrored((0, 9), PieceType::Rook);
+        board
+    }
+    // a board with no pieces on it
+    pub fn empty(rows: usize, cols: usize) -> Self {
+        assert!(rows <= MAX_BOARD_SIZE && cols <= MAX_BOARD_SIZE, "board is larger than MAX_BOARD_SIZE");
+        Self { grid: [[Cell::Empty; MAX_BOARD_SIZE]; MAX_BOARD_SIZE], rows, cols, pawn_row: 1 }
+    }
+    // mirrored setup with the given back rank for both players and a full row of pawns in front of it
+    // used to prototype variants, a None leaves the square empty
+    pub fn with_setup(rows: usize, cols: usize, back_rank: &[Option<PieceType>], pawn_row: usize) -> Self {
+        let mut board = Board::empty(rows, cols);
+        board.pawn_row = pawn_row;
+        for (col, piece_type) in back_rank.iter().enumerate().take(cols) {
+            if let Some(piece_type) = *piece_type {
+                board.place_mirrored((0, col), piece_type);
+            }
+        }
+        for col in 0..cols {
+            board.place_mirrored((pawn_row, col), PieceType::Pawn);
+        }
+        board
+    }
+    // puts a White piece on 'position' and the matching Black piece on the same file at the other end
+    fn place_mirrored(&mut self, position: (usize, usize), piece_type: PieceType) {
+        self.set_piece_at(position, Piece { piece_type, color: Color::White, has_moved: false });
+        self.set_piece_at((self.rows - 1 - position.0, position.1), Piece { piece_type, color: Color::Black, has_moved: false });
     }
     
-    pub fn print_board(board: &Board) {
-        println!("  a b c d e f g h");
-        for (i, row) in board.grid.iter().enumerate() {
-            print!("{} ", 8 - i); // Row label
-            for cell in row {
-                let piece_symbol = match cell {
+    // prints the board with White at the bottom, or Black at the bottom when flipped
+    // highlighted squares are marked with • when empty and have their piece put in brackets otherwise
+    #[cfg(feature = "std")]
+    pub fn print_board(board: &Board, glyphs: GlyphSet, flipped: bool, highlights: &[(usize, usize)]) {
+        let mut cols: Vec<usize> = (0..board.cols).collect();
+        let mut rows: Vec<usize> = (0..board.rows).rev().collect();
+        if flipped {
+            cols.reverse();
+            rows.reverse();
+        }
+        let files: Vec<String> = cols.iter().map(|&col| ((b'a' + col as u8) as char).to_string()).collect();
+        let files = files.join(" ");
+        println!("   {}", files);
+        for &i in &rows {
+            print!("{:>2}", i + 1); // Row label
+            let mut bracket_open = false;
+            for &col in &cols {
+                let highlighted = highlights.contains(&(i, col));
+                let piece_symbol = match &board.grid[i][col] {
+                    Cell::Empty if highlighted => "•",
                     Cell::Empty => "·", // empty cells
-                    Cell::Occupied(piece) => &piece_to_ascii(piece),
+                    Cell::Occupied(piece) => glyphs.symbol(piece),
                 };
-                print!("{} ", piece_symbol); // Print piece with space
+                let capture = highlighted && board.grid[i][col] != Cell::Empty;
+                // the space before each piece turns into a bracket around a piece that can be captured
+                print!("{}{}", if capture { "[" } else if bracket_open { "]" } else { " " }, piece_symbol);
+                bracket_open = capture;
             }
-            println!(" {}", 8 - i); // Row label at the end
+            println!("{} {}", if bracket_open { "]" } else { " " }, i + 1); // Row label at the end
         }
-        println!("  a b c d e f g h");
-    
-
-    pub fn piece_to_ascii(piece: &Piece) -> &str {
-        match (piece.color, piece.piece_type) {
-            (Color::White, PieceType::King) => "♔",
-            (Color::White, PieceType::Queen) => "♕",
-            (Color::White, PieceType::Rook) => "♖",
-            (Color::White, PieceType::Bishop) => "♗",
-            (Color::White, PieceType::Knight) => "♘",
-            (Color::White, PieceType::Pawn) => "♙",
-            (Color::Black, PieceType::King) => "♚",
-            (Color::Black, PieceType::Queen) => "♛",
-            (Color::Black, PieceType::Rook) => "♜",
-            (Color::Black, PieceType::Bishop) => "♝",
-            (Color::Black, PieceType::Knight) => "♞",
-            (Color::Black, PieceType::Pawn) => "♟︎",
-            _ => " ", // Default case
-        } 
-    }  } 
+        println!("   {}", files);
+    }
 
     pub fn is_within_bounds(&self, position: (isize, isize)) -> bool {
-        position.0 >= 0 && position.0 < BOARD_SIZE as isize && position.1 >= 0 && position.1 < BOARD_SIZE as isize
+        position.0 >= 0 && position.0 < self.rows as isize && position.1 >= 0 && position.1 < self.cols as isize
     }
 
     pub fn get_piece_at(&self, position: (usize, usize)) -> Option<Piece> {
@@ -111,7 +250,7 @@ impl Board {
         self.grid[to.0][to.1] = piece;
     }
     pub fn move_piece(&mut self, from: (usize, usize), to: (usize, usize)) {
-        let mut piece_to_move = match self.grid[from.0][from.1] {
+        let piece_to_move = match self.grid[from.0][from.1] {
             Cell::Occupied(piece) => piece,
             Cell::Empty => return,
         };
@@ -134,3 +273,74 @@ impl Board {
 }
 
 
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::{Board, GlyphSet, Orientation};
+    use crate::fen;
+    use crate::game::{Game, Variant};
+    use crate::piece::{Color, Piece, PieceType};
+
+    #[test]
+    fn variants_start_from_their_own_setup() {
+        let capablanca = Game::with_variant(Variant::Capablanca);
+        assert_eq!(fen::to_fen(&capablanca), "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1");
+        let grand = Game::with_variant(Variant::Grand);
+        assert_eq!(fen::to_fen(&grand), "r8r/1nbqkcabn1/pppppppppp/10/10/10/10/PPPPPPPPPP/1NBQKCABN1/R8R w - - 0 1");
+        assert_eq!((grand.board.rows, grand.board.cols, grand.board.pawn_row), (10, 10, 2));
+        // the known move counts from the start, fairy pieces and the longer pawn rows included
+        assert_eq!(capablanca.legal_moves().len(), 28);
+        assert_eq!(grand.legal_moves().len(), 65);
+    }
+
+    #[test]
+    fn fairy_pieces_move_like_both_their_parts() {
+        let count = |piece_type: PieceType, rows: usize, cols: usize, at: (usize, usize)| {
+            let board = Board::empty(rows, cols);
+            Piece { piece_type, color: Color::White, has_moved: true }.valid_moves(at, &board).len()
+        };
+        // in the middle: 13 bishop and 8 knight moves, 14 rook and 8 knight moves, 27 queen and 8 knight moves
+        assert_eq!(count(PieceType::Archbishop, 8, 8, (3, 3)), 21);
+        assert_eq!(count(PieceType::Chancellor, 8, 8, (3, 3)), 22);
+        assert_eq!(count(PieceType::Amazon, 8, 8, (3, 3)), 35);
+        // in the corner of the wider board the rook part reaches all ten files
+        assert_eq!(count(PieceType::Chancellor, 8, 10, (0, 0)), 7 + 9 + 2);
+        assert_eq!(count(PieceType::Archbishop, 8, 10, (0, 0)), 7 + 2);
+        // a knight's jump is not blocked, the slides are
+        let mut board = Board::empty(8, 8);
+        for square in [(1, 0), (1, 1), (0, 1)] {
+            board.set_piece_at(square, Piece { piece_type: PieceType::Pawn, color: Color::White, has_moved: false });
+        }
+        let moves = Piece { piece_type: PieceType::Chancellor, color: Color::White, has_moved: true }.valid_moves((0, 0), &board);
+        assert_eq!(moves.as_slice(), &[(2, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn glyph_sets_name_the_pieces() {
+        let piece = |piece_type: PieceType, color: Color| Piece { piece_type, color, has_moved: false };
+        assert_eq!(GlyphSet::from_name("DE"), Some(GlyphSet::German));
+        assert_eq!(GlyphSet::from_name("english"), Some(GlyphSet::Ascii));
+        assert_eq!(GlyphSet::from_name("klingon"), None);
+        assert_eq!(GlyphSet::Unicode.symbol(&piece(PieceType::Knight, Color::White)), "♘");
+        assert_eq!(GlyphSet::Unicode.symbol(&piece(PieceType::Pawn, Color::Black)), "♟");
+        assert_eq!(GlyphSet::Ascii.symbol(&piece(PieceType::Queen, Color::Black)), "q");
+        assert_eq!(GlyphSet::German.symbol(&piece(PieceType::Knight, Color::White)), "S");
+        assert_eq!(GlyphSet::French.symbol(&piece(PieceType::Bishop, Color::Black)), "f");
+        assert_eq!(GlyphSet::Spanish.symbol(&piece(PieceType::King, Color::White)), "R");
+        // fairy pieces keep their letters in every set
+        assert_eq!(GlyphSet::Unicode.symbol(&piece(PieceType::Chancellor, Color::Black)), "c");
+        assert_eq!(GlyphSet::German.symbol(&piece(PieceType::Archbishop, Color::White)), "A");
+    }
+
+    #[test]
+    fn orientation_follows_the_setting_or_the_side_to_move() {
+        assert!(!Orientation::White.black_at_bottom(Color::Black));
+        assert!(Orientation::Black.black_at_bottom(Color::White));
+        assert!(Orientation::SideToMove.black_at_bottom(Color::Black));
+        assert!(!Orientation::SideToMove.black_at_bottom(Color::White));
+        // flipping turns whatever is shown around
+        assert_eq!(Orientation::White.flipped(Color::White), Orientation::Black);
+        assert_eq!(Orientation::SideToMove.flipped(Color::Black), Orientation::White);
+        assert_eq!(Orientation::SideToMove.flipped(Color::White), Orientation::Black);
+    }
+}
@@ -1,13 +1,19 @@
 use crate::piece::Piece;
 use crate::piece::PieceType;
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String};
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Move {
-    from: (usize, usize),
-    to: (usize, usize),
-    piece_moved: Piece,
-    piece_captured: Option<Piece>,
-    is_castling_move: bool,
-    is_en_passant_move: bool,
-    promoted_to: Option<PieceType>, // None if not a promotion
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::square"))]
+    pub from: (usize, usize),
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::square"))]
+    pub to: (usize, usize),
+    pub piece_moved: Piece,
+    pub piece_captured: Option<Piece>,
+    pub is_castling_move: bool,
+    pub is_en_passant_move: bool,
+    pub promoted_to: Option<PieceType>, // None if not a promotion
 }
 
 impl Move {
@@ -24,4 +30,35 @@ impl Move {
         }
     }
 
-}
\ No newline at end of file
+    // coordinate notation such as e2e4, with the promotion piece added at the end (e7e8q)
+    #[cfg(feature = "alloc")]
+    pub fn notation(&self) -> String {
+        let mut notation = format!("{}{}", square_name(self.from), square_name(self.to));
+        if let Some(piece_type) = self.promoted_to {
+            notation.push(promotion_letter(piece_type));
+        }
+        notation
+    }
+
+}
+
+// name of a grid position, (1, 4) is e2
+#[cfg(feature = "alloc")]
+pub fn square_name(position: (usize, usize)) -> String {
+    format!("{}{}", (b'a' + position.1 as u8) as char, position.0 + 1)
+}
+
+#[cfg(feature = "alloc")]
+fn promotion_letter(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::Queen => 'q',
+        PieceType::Rook => 'r',
+        PieceType::Bishop => 'b',
+        PieceType::Knight => 'n',
+        PieceType::King => 'k',
+        PieceType::Pawn => 'p',
+        PieceType::Archbishop => 'a',
+        PieceType::Chancellor => 'c',
+        PieceType::Amazon => 'z',
+    }
+}
@@ -0,0 +1,633 @@
+use crate::fen;
+use crate::game::{Game, GameState, Variant};
+use crate::notation;
+use crate::pgn::{self, PgnGame};
+use crate::piece::Color;
+use crate::uci_engine::{EngineConfig, UciEngine};
+use std::fs::{self, File};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// time an engine may go past its clock before it loses on time, for process and pipe overhead
+const TIME_MARGIN: Duration = Duration::from_millis(100);
+// a fixed depth search taking longer than this counts as a hung engine
+const DEPTH_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Clone, Copy, Debug)]
+pub enum TimeControl {
+    // a clock for the whole game plus an increment per move
+    Clock { base: Duration, increment: Duration },
+    MoveTime(Duration),
+    Depth(u32),
+}
+
+impl TimeControl {
+    // seconds for the game with an optional increment, e.g. 10+0.1 or 60
+    pub fn parse_clock(text: &str) -> Result<TimeControl, String> {
+        let seconds = |text: &str| {
+            text.parse::<f64>().ok()
+                .filter(|seconds| *seconds >= 0.0)
+                .map(Duration::from_secs_f64)
+                .ok_or(format!("'{}' is not a time control like 10+0.1", text))
+        };
+        let (base, increment) = text.split_once('+').unwrap_or((text, "0"));
+        Ok(TimeControl::Clock { base: seconds(base)?, increment: seconds(increment)? })
+    }
+
+    // the PGN TimeControl tag
+    fn tag(&self) -> String {
+        match self {
+            TimeControl::Clock { base, increment } if increment.is_zero() => format!("{}", base.as_secs_f64()),
+            TimeControl::Clock { base, increment } => format!("{}+{}", base.as_secs_f64(), increment.as_secs_f64()),
+            TimeControl::MoveTime(time) => format!("{}/move", time.as_secs_f64()),
+            TimeControl::Depth(_) => String::from("-"),
+        }
+    }
+}
+
+// when a game is ended early on the engines' own scores, a count of 0 turns a rule off
+// scores are what each engine reported for its own move, from its own point of view
+#[derive(Clone, Copy, Debug)]
+pub struct Adjudication {
+    // a draw once both engines have stayed within draw_score of 0 for draw_count moves each,
+    // counted from move draw_move_number on
+    pub draw_move_number: u32,
+    pub draw_count: u32,
+    pub draw_score: i32,
+    // a loss for an engine that scored resign_score or worse for resign_count moves in a row
+    pub resign_count: u32,
+    pub resign_score: i32,
+    // a draw at this move number whatever the scores, 0 for no limit
+    pub max_moves: u32,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Adjudication { draw_move_number: 40, draw_count: 8, draw_score: 10, resign_count: 4, resign_score: 1000, max_moves: 0 }
+    }
+}
+
+// sequential probability ratio test: H0 says the first engine is elo0 stronger, H1 that it is elo1
+// stronger, and the match stops once the results favour one of them at the alpha/beta error rates
+#[derive(Clone, Copy, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    // words like `elo0=0 elo1=5 alpha=0.05 beta=0.05`, anything left out keeps these defaults
+    pub fn parse(words: &[&str]) -> Result<Sprt, String> {
+        let mut sprt = Sprt { elo0: 0.0, elo1: 5.0, alpha: 0.05, beta: 0.05 };
+        for word in words {
+            let (name, value) = word.split_once('=').ok_or(format!("'{}' should be NAME=VALUE", word))?;
+            let value: f64 = value.parse().map_err(|_| format!("'{}' is not a number", value))?;
+            match name {
+                "elo0" => sprt.elo0 = value,
+                "elo1" => sprt.elo1 = value,
+                "alpha" => sprt.alpha = value,
+                "beta" => sprt.beta = value,
+                _ => return Err(format!("unknown SPRT setting '{}', expected elo0, elo1, alpha or beta", name)),
+            }
+        }
+        if sprt.elo1 <= sprt.elo0 || !(0.0..0.5).contains(&sprt.alpha) || !(0.0..0.5).contains(&sprt.beta) || sprt.alpha == 0.0 || sprt.beta == 0.0 {
+            return Err(String::from("SPRT needs elo0 < elo1 and alpha, beta between 0 and 0.5"));
+        }
+        Ok(sprt)
+    }
+
+    // the log-likelihood ratio below which H0 is accepted and above which H1 is
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    // log-likelihood ratio of H1 against H0, using the normal approximation to the
+    // win/draw/loss distribution that fishtest's GSPRT uses
+    pub fn llr(&self, score: &Score) -> f64 {
+        let variance = score.variance();
+        if score.games() == 0 || variance <= 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (expected_score(self.elo0), expected_score(self.elo1));
+        (s1 - s0) * (2.0 * score.mean() - s0 - s1) * score.games() as f64 / (2.0 * variance)
+    }
+
+    // Some(true) once H1 is accepted, Some(false) once H0 is
+    pub fn decision(&self, score: &Score) -> Option<bool> {
+        let (lower, upper) = self.bounds();
+        let llr = self.llr(score);
+        if llr >= upper {
+            Some(true)
+        } else if llr <= lower {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+// expected score against an opponent 'elo' points weaker
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    // adding 0 turns the -0 of an even score into 0
+    -400.0 * (1.0 / score - 1.0).log10() + 0.0
+}
+
+// results from the first engine's point of view
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Score {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl Score {
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    // points per game
+    pub fn mean(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    // variance of the points from a single game
+    fn variance(&self) -> f64 {
+        let mean = self.mean();
+        let games = self.games().max(1) as f64;
+        (self.wins as f64 * (1.0 - mean).powi(2) + self.draws as f64 * (0.5 - mean).powi(2) + self.losses as f64 * mean.powi(2)) / games
+    }
+
+    // the Elo difference the results suggest and the margin of its 95% confidence interval,
+    // None while one side has all the points since the difference is then unbounded
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let mean = self.mean();
+        if self.games() == 0 || mean <= 0.0 || mean >= 1.0 {
+            return None;
+        }
+        let deviation = (self.variance() / self.games() as f64).sqrt();
+        let low = (mean - 1.96 * deviation).max(1e-6);
+        let high = (mean + 1.96 * deviation).min(1.0 - 1e-6);
+        Some((elo_from_score(mean), (elo_from_score(high) - elo_from_score(low)) / 2.0))
+    }
+}
+
+// a starting position for a pair of games, moves in UCI notation played from the FEN or the start position
+#[derive(Clone, Debug)]
+pub struct Opening {
+    pub fen: Option<String>,
+    pub moves: Vec<String>,
+}
+
+impl Opening {
+    fn start(&self) -> Result<Game, String> {
+        match &self.fen {
+            Some(text) => fen::parse_for(Variant::Standard, text),
+            None => Ok(Game::default()),
+        }
+    }
+
+    // the game with the opening moves played
+    fn record(&self) -> Result<Record, String> {
+        let mut record = Record { game: self.start()?, uci_moves: Vec::new(), san_moves: Vec::new() };
+        for text in &self.moves {
+            if !record.play(text) {
+                return Err(format!("the opening move {} is not legal", text));
+            }
+        }
+        Ok(record)
+    }
+}
+
+// a .pgn file gives the first 'plies' moves of each game, anything else is read as one FEN or EPD per line
+pub fn load_openings(path: &str, plies: usize) -> Result<Vec<Opening>, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    let mut openings = Vec::new();
+    if path.to_ascii_lowercase().ends_with(".pgn") {
+        for (number, game) in pgn::parse(&text).iter().enumerate() {
+            let played = game.replay().map_err(|error| format!("{}: game {}: {}", path, number + 1, error))?;
+            let moves = played.iter().take(plies).map(|(_, mv)| mv.notation()).collect();
+            openings.push(Opening { fen: game.tag("FEN").map(str::to_string), moves });
+        }
+    } else {
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // EPD operations and move counters after the first four fields are left out
+            let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+            let game = fen::parse(&fields.join(" ")).map_err(|error| format!("{}:{}: {}", path, number + 1, error))?;
+            openings.push(Opening { fen: Some(fen::to_fen(&game)), moves: Vec::new() });
+        }
+    }
+    if openings.is_empty() {
+        return Err(format!("{}: no openings", path));
+    }
+    Ok(openings)
+}
+
+pub struct MatchConfig {
+    pub engines: [EngineConfig; 2],
+    // the most games to play, an SPRT may end the match sooner
+    pub games: usize,
+    // games played at the same time, each with its own pair of engine processes
+    pub concurrency: usize,
+    // used in turn, each one twice so both engines get each side of it
+    pub openings: Vec<Opening>,
+    pub time_control: TimeControl,
+    pub adjudication: Adjudication,
+    // PGN file the games are appended to as they finish
+    pub pgn: Option<String>,
+    pub sprt: Option<Sprt>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Outcome {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl Outcome {
+    fn loss_for(color: Color) -> Outcome {
+        if color == Color::White { Outcome::BlackWins } else { Outcome::WhiteWins }
+    }
+
+    fn result(&self) -> &'static str {
+        match self {
+            Outcome::WhiteWins => "1-0",
+            Outcome::BlackWins => "0-1",
+            Outcome::Draw => "1/2-1/2",
+        }
+    }
+}
+
+// how a game ended, 'termination' is one of the PGN Termination tag values
+struct GameEnd {
+    outcome: Outcome,
+    termination: &'static str,
+    reason: String,
+}
+
+impl GameEnd {
+    fn new(outcome: Outcome, termination: &'static str, reason: impl Into<String>) -> GameEnd {
+        GameEnd { outcome, termination, reason: reason.into() }
+    }
+}
+
+struct PlayedGame {
+    pgn: PgnGame,
+    end: GameEnd,
+    // an engine failed, so the pair is restarted before the next game
+    engine_failed: bool,
+}
+
+// what the workers share: the results so far and where the games are written
+struct Progress {
+    score: Score,
+    pgn: Option<File>,
+    error: Option<String>,
+}
+
+// plays the match and prints the running score, Elo estimate and SPRT state after every game
+pub fn run(config: &MatchConfig) -> Result<Score, String> {
+    let pgn = match &config.pgn {
+        Some(path) => Some(File::options().create(true).append(true).open(path).map_err(|error| format!("{}: {}", path, error))?),
+        None => None,
+    };
+    let progress = Mutex::new(Progress { score: Score::default(), pgn, error: None });
+    let next_game = AtomicUsize::new(0);
+    let finished = AtomicBool::new(false);
+    println!("{} vs {}, {} games", config.engines[0].name, config.engines[1].name, config.games);
+    thread::scope(|scope| {
+        for _ in 0..config.concurrency.clamp(1, config.games.max(1)) {
+            scope.spawn(|| worker(config, &next_game, &finished, &progress));
+        }
+    });
+    let progress = progress.into_inner().map_err(|_| String::from("a match worker panicked"))?;
+    if let Some(error) = progress.error {
+        return Err(error);
+    }
+    println!("Finished: {}", summary(config, &progress.score));
+    Ok(progress.score)
+}
+
+// takes games off the shared counter until there are none left or the match is decided
+fn worker(config: &MatchConfig, next_game: &AtomicUsize, finished: &AtomicBool, progress: &Mutex<Progress>) {
+    let mut engines: Option<[UciEngine; 2]> = None;
+    while !finished.load(Ordering::Relaxed) {
+        let index = next_game.fetch_add(1, Ordering::Relaxed);
+        if index >= config.games {
+            break;
+        }
+        if engines.is_none() {
+            match start_engines(config) {
+                Ok(pair) => engines = Some(pair),
+                Err(error) => {
+                    progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).error.get_or_insert(error);
+                    finished.store(true, Ordering::Relaxed);
+                    break;
+                }
+            }
+        }
+        let Some([first, second]) = engines.as_mut() else { break };
+        // the first engine takes White in even games, each opening is played once with either colour
+        let first_is_white = index.is_multiple_of(2);
+        let opening = &config.openings[(index / 2) % config.openings.len()];
+        let (white, black) = if first_is_white { (first, second) } else { (second, first) };
+        let event = format!("{} vs {}", config.engines[0].name, config.engines[1].name);
+        let played = match play_game(white, black, opening, config.time_control, config.adjudication, &event, index + 1) {
+            Ok(played) => played,
+            Err(error) => {
+                progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).error.get_or_insert(format!("game {}: {}", index + 1, error));
+                finished.store(true, Ordering::Relaxed);
+                break;
+            }
+        };
+        if played.engine_failed {
+            engines = None;
+        }
+        let mut progress = progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match (played.end.outcome, first_is_white) {
+            (Outcome::Draw, _) => progress.score.draws += 1,
+            (Outcome::WhiteWins, true) | (Outcome::BlackWins, false) => progress.score.wins += 1,
+            _ => progress.score.losses += 1,
+        }
+        if let Some(file) = progress.pgn.as_mut() {
+            if let Err(error) = file.write_all(played.pgn.to_pgn().as_bytes()) {
+                eprintln!("could not write the game: {}", error);
+            }
+        }
+        println!(
+            "Game {} ({} vs {}): {} {{{}}}",
+            index + 1, played.pgn.tag("White").unwrap_or("?"), played.pgn.tag("Black").unwrap_or("?"), played.pgn.result, played.end.reason
+        );
+        println!("{}", summary(config, &progress.score));
+        if config.sprt.is_some_and(|sprt| sprt.decision(&progress.score).is_some()) {
+            finished.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+// plays a single game from the start position between two engines started for it
+pub fn play_engines(white: &EngineConfig, black: &EngineConfig, time_control: TimeControl, adjudication: Adjudication, event: &str, round: usize) -> Result<PgnGame, String> {
+    let (mut white, mut black) = (UciEngine::start(white)?, UciEngine::start(black)?);
+    let opening = Opening { fen: None, moves: Vec::new() };
+    Ok(play_game(&mut white, &mut black, &opening, time_control, adjudication, event, round)?.pgn)
+}
+
+fn start_engines(config: &MatchConfig) -> Result<[UciEngine; 2], String> {
+    Ok([UciEngine::start(&config.engines[0])?, UciEngine::start(&config.engines[1])?])
+}
+
+// one line with the score, Elo estimate and, when running one, the state of the SPRT
+fn summary(config: &MatchConfig, score: &Score) -> String {
+    let mut text = format!(
+        "Score of {} vs {}: {} - {} - {} [{:.3}] {}",
+        config.engines[0].name, config.engines[1].name, score.wins, score.losses, score.draws, score.mean(), score.games()
+    );
+    match score.elo() {
+        Some((elo, margin)) => text.push_str(&format!(", Elo {:+.1} +/- {:.1}", elo, margin)),
+        None => text.push_str(", Elo unknown"),
+    }
+    if let Some(sprt) = config.sprt {
+        let (lower, upper) = sprt.bounds();
+        text.push_str(&format!(", LLR {:.2} ({:.2}, {:.2}) [{}, {}]", sprt.llr(score), lower, upper, sprt.elo0, sprt.elo1));
+        match sprt.decision(score) {
+            Some(true) => text.push_str(" H1 accepted"),
+            Some(false) => text.push_str(" H0 accepted"),
+            None => {}
+        }
+    }
+    text
+}
+
+// plays one game from the opening, ending it by the rules, by adjudication, or when an engine
+// fails, runs out of time or plays an illegal move
+// an opening that can't be played is an error, since every game from it would be wrong
+fn play_game(white: &mut UciEngine, black: &mut UciEngine, opening: &Opening, time_control: TimeControl, adjudication: Adjudication, event: &str, round: usize) -> Result<PlayedGame, String> {
+    let mut record = opening.record()?;
+    let start = opening.start()?;
+    let mut engine_failed = false;
+
+    let names = [white.name.clone(), black.name.clone()];
+    for engine in [&mut *white, &mut *black] {
+        if let Err(error) = engine.new_game() {
+            engine_failed = true;
+            eprintln!("{}", error);
+        }
+    }
+    let mut clocks = match time_control {
+        TimeControl::Clock { base, .. } => [base; 2],
+        _ => [Duration::ZERO; 2],
+    };
+    // moves in a row each side has been losing by resign_score, and plies in a row near 0
+    let mut losing = [0; 2];
+    let mut level_plies = 0;
+    let end = loop {
+        if let Some(end) = record.rules_result() {
+            break end;
+        }
+        let move_number = record.uci_moves.len() as u32 / 2 + 1;
+        if adjudication.max_moves > 0 && move_number > adjudication.max_moves {
+            break GameEnd::new(Outcome::Draw, "adjudication", "move limit");
+        }
+        let side = record.game.current_player;
+        let index = if side == Color::White { 0 } else { 1 };
+        let moves = record.uci_moves.join(" ");
+        let position = match (&opening.fen, moves.is_empty()) {
+            (Some(fen), true) => format!("position fen {}", fen),
+            (Some(fen), false) => format!("position fen {} moves {}", fen, moves),
+            (None, true) => String::from("position startpos"),
+            (None, false) => format!("position startpos moves {}", moves),
+        };
+        let (go, timeout) = match time_control {
+            TimeControl::Clock { increment, .. } => {
+                let (wtime, btime, inc) = (clocks[0].as_millis(), clocks[1].as_millis(), increment.as_millis());
+                (format!("go wtime {} btime {} winc {} binc {}", wtime, btime, inc, inc), clocks[index] + TIME_MARGIN)
+            }
+            TimeControl::MoveTime(time) => (format!("go movetime {}", time.as_millis()), time * 2 + Duration::from_secs(1)),
+            TimeControl::Depth(depth) => (format!("go depth {}", depth), DEPTH_TIMEOUT),
+        };
+        let engine = if side == Color::White { &mut *white } else { &mut *black };
+        let answer = match engine.go(&position, &go, timeout) {
+            Ok(answer) => answer,
+            Err(error) => {
+                engine_failed = true;
+                let termination = if matches!(time_control, TimeControl::Clock { .. }) { "time forfeit" } else { "abandoned" };
+                break GameEnd::new(Outcome::loss_for(side), termination, error);
+            }
+        };
+        if let TimeControl::Clock { increment, .. } = time_control {
+            if answer.elapsed > clocks[index] + TIME_MARGIN {
+                break GameEnd::new(Outcome::loss_for(side), "time forfeit", format!("{} loses on time", names[index]));
+            }
+            clocks[index] = clocks[index].saturating_sub(answer.elapsed) + increment;
+        }
+        if !record.play(&answer.mv) {
+            break GameEnd::new(Outcome::loss_for(side), "rules infraction", format!("{} plays the illegal move {}", names[index], answer.mv));
+        }
+
+        if let Some(score) = answer.score {
+            losing[index] = if score <= -adjudication.resign_score { losing[index] + 1 } else { 0 };
+            level_plies = if move_number >= adjudication.draw_move_number && score.abs() <= adjudication.draw_score { level_plies + 1 } else { 0 };
+        }
+        if adjudication.resign_count > 0 && losing[index] >= adjudication.resign_count {
+            break GameEnd::new(Outcome::loss_for(side), "adjudication", format!("{} is lost by its own score", names[index]));
+        }
+        if adjudication.draw_count > 0 && level_plies >= adjudication.draw_count * 2 {
+            break GameEnd::new(Outcome::Draw, "adjudication", "both engines see a draw");
+        }
+    };
+
+    let mut tags = vec![
+        (String::from("Event"), event.to_string()),
+        (String::from("Site"), String::from("?")),
+        (String::from("Date"), today()),
+        (String::from("Round"), round.to_string()),
+        (String::from("White"), names[0].clone()),
+        (String::from("Black"), names[1].clone()),
+        (String::from("Result"), end.outcome.result().to_string()),
+    ];
+    if opening.fen.is_some() {
+        tags.push((String::from("SetUp"), String::from("1")));
+        tags.push((String::from("FEN"), fen::to_fen(&start)));
+    }
+    tags.push((String::from("TimeControl"), time_control.tag()));
+    tags.push((String::from("Termination"), end.termination.to_string()));
+    let pgn = PgnGame { tags, moves: record.san_moves, result: end.outcome.result().to_string() };
+    Ok(PlayedGame { pgn, end, engine_failed })
+}
+
+// a game in progress with its moves written out for the engines and the PGN
+struct Record {
+    game: Game,
+    uci_moves: Vec<String>,
+    san_moves: Vec<String>,
+}
+
+impl Record {
+    // plays a move given in UCI notation, false if it isn't legal
+    fn play(&mut self, text: &str) -> bool {
+        let Some(mv) = notation::parse_uci_move(&self.game, text) else { return false };
+        self.san_moves.push(notation::san(&self.game, &mv));
+        self.uci_moves.push(mv.notation());
+        self.game.make_move(&mv);
+        true
+    }
+
+    // the game over by the rules of chess: mate, stalemate, threefold repetition, the fifty-move
+    // rule, or too little material left for either side to mate, as Game works them out
+    fn rules_result(&self) -> Option<GameEnd> {
+        match self.game.state {
+            GameState::Checkmate => Some(GameEnd::new(Outcome::loss_for(self.game.current_player), "normal", "checkmate")),
+            GameState::Stalemate => Some(GameEnd::new(Outcome::Draw, "normal", "stalemate")),
+            GameState::Draw => Some(GameEnd::new(Outcome::Draw, "normal", self.game.draw_reason().unwrap_or("draw"))),
+            GameState::Ongoing | GameState::Check => None,
+        }
+    }
+}
+
+// the date for the PGN Date tag, in UTC, from the days since 1970 (Howard Hinnant's civil_from_days)
+fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs() / 86_400).unwrap_or(0) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(wins: u32, losses: u32, draws: u32) -> Score {
+        Score { wins, losses, draws }
+    }
+
+    #[test]
+    fn sprt_weighs_the_results() {
+        let sprt = Sprt::parse(&["elo0=0", "elo1=5"]).unwrap();
+        let (lower, upper) = sprt.bounds();
+        assert!((lower - (0.05f64 / 0.95).ln()).abs() < 1e-9 && (upper - 19f64.ln()).abs() < 1e-9);
+        // a mean of 0.55 with a variance of 0.1225 per game
+        assert!((sprt.llr(&score(60, 40, 100)) - 0.5451).abs() < 1e-4);
+        assert_eq!(sprt.decision(&score(60, 40, 100)), None);
+        assert_eq!(sprt.decision(&score(600, 400, 1000)), Some(true));
+        assert_eq!(sprt.decision(&score(400, 600, 1000)), Some(false));
+        assert_eq!(sprt.llr(&Score::default()), 0.0);
+        assert!(Sprt::parse(&["elo0=5", "elo1=0"]).is_err());
+    }
+
+    #[test]
+    fn elo_comes_from_the_mean_score() {
+        let (elo, margin) = score(60, 40, 100).elo().unwrap();
+        assert!((elo - 34.86).abs() < 0.01);
+        assert!(margin > 0.0 && margin < 100.0);
+        assert_eq!(score(5, 5, 0).elo().map(|(elo, _)| elo), Some(0.0));
+        assert!(score(10, 0, 0).elo().is_none());
+        assert!(Score::default().elo().is_none());
+    }
+
+    fn record(moves: &[&str]) -> Record {
+        let mut record = Opening { fen: None, moves: Vec::new() }.record().unwrap();
+        for text in moves {
+            assert!(record.play(text), "{}", text);
+        }
+        record
+    }
+
+    #[test]
+    fn the_rules_end_the_game() {
+        let mate = record(&["f2f3", "e7e5", "g2g4", "d8h4"]).rules_result().unwrap();
+        assert_eq!((mate.outcome, mate.reason.as_str()), (Outcome::BlackWins, "checkmate"));
+        assert_eq!(record(&["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1"]).rules_result().map(|end| end.outcome), None);
+        let repetition = record(&["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8"]).rules_result().unwrap();
+        assert_eq!((repetition.outcome, repetition.reason.as_str()), (Outcome::Draw, "threefold repetition"));
+
+        let opening = Opening { fen: Some(String::from("4k3/8/8/8/8/8/8/R3K3 w - - 99 80")), moves: vec![String::from("a1a2")] };
+        let fifty = opening.record().unwrap().rules_result().unwrap();
+        assert_eq!((fifty.outcome, fifty.reason.as_str()), (Outcome::Draw, "fifty-move rule"));
+        // an illegal opening move is an error rather than being skipped
+        assert!(Opening { fen: None, moves: vec![String::from("e2e5")] }.record().is_err());
+        assert!(Opening { fen: Some(String::from("not a fen")), moves: Vec::new() }.record().is_err());
+    }
+
+    #[test]
+    fn openings_load_from_pgn_and_epd() {
+        let directory = std::env::temp_dir();
+        let pgn_path = directory.join(format!("openings_test_{}.pgn", std::process::id()));
+        let epd_path = directory.join(format!("openings_test_{}.epd", std::process::id()));
+        let (pgn_path, epd_path) = (pgn_path.to_str().unwrap(), epd_path.to_str().unwrap());
+        fs::write(pgn_path, "[Event \"?\"]\n\n1. e4 e5 2. Nf3 Nc6 *\n\n[Event \"?\"]\n\n1. d4 d5 *\n").unwrap();
+        fs::write(epd_path, "# a comment\nrnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 id \"e4\";\n\n4k3/8/8/8/8/8/8/4K2R w K -\n").unwrap();
+
+        let openings = load_openings(pgn_path, 3).unwrap();
+        let moves: Vec<Vec<String>> = openings.iter().map(|opening| opening.moves.clone()).collect();
+        assert_eq!(moves, [vec!["e2e4", "e7e5", "g1f3"], vec!["d2d4", "d7d5"]]);
+        assert!(openings.iter().all(|opening| opening.fen.is_none()));
+
+        let openings = load_openings(epd_path, 3).unwrap();
+        let fens: Vec<&str> = openings.iter().filter_map(|opening| opening.fen.as_deref()).collect();
+        assert_eq!(fens, ["rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", "4k3/8/8/8/8/8/8/4K2R w K - 0 1"]);
+
+        fs::write(epd_path, "# nothing here\n").unwrap();
+        assert!(load_openings(epd_path, 3).is_err());
+        let _ = fs::remove_file(pgn_path);
+        let _ = fs::remove_file(epd_path);
+    }
+}
@@ -0,0 +1,457 @@
+use crate::board::{Board, MAX_BOARD_SIZE};
+use crate::piece::{Color, PieceType, PIECE_TYPE_COUNT};
+use std::fs;
+
+// how much each piece type counts towards the middlegame, the phase runs from
+// MAX_PHASE with all pieces on the board down to 0 with only kings and pawns
+const PHASE_WEIGHT: [i32; PIECE_TYPE_COUNT] = [0, 1, 1, 2, 4, 0, 3, 4, 6];
+const MAX_PHASE: i32 = 24;
+
+// piece-square tables are written for an 8x8 board from White's side, rank 8 first
+// larger boards are scaled onto them
+const PST_SIZE: usize = 8;
+
+// every weight the evaluation uses, with separate middlegame (mg) and endgame (eg) values
+// indexed by PieceType::index where there is one value per piece type
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvalParams {
+    pub material_mg: [i32; PIECE_TYPE_COUNT],
+    pub material_eg: [i32; PIECE_TYPE_COUNT],
+    // pawn, knight, bishop, rook, queen, king, the fairy pieces borrow the table of their main part
+    pub pst_mg: [[i32; 64]; 6],
+    pub pst_eg: [[i32; 64]; 6],
+    // per square a piece can move to
+    pub mobility_mg: [i32; PIECE_TYPE_COUNT],
+    pub mobility_eg: [i32; PIECE_TYPE_COUNT],
+    pub doubled_pawn_mg: i32,
+    pub doubled_pawn_eg: i32,
+    pub isolated_pawn_mg: i32,
+    pub isolated_pawn_eg: i32,
+    // by how far the pawn has advanced, scaled to 8 ranks
+    pub passed_pawn_mg: [i32; 8],
+    pub passed_pawn_eg: [i32; 8],
+    // per own pawn in front of the king and per square next to the king the opponent attacks
+    pub king_shield_mg: i32,
+    pub king_attack_mg: i32,
+    pub bishop_pair_mg: i32,
+    pub bishop_pair_eg: i32,
+    pub rook_open_file_mg: i32,
+    pub rook_open_file_eg: i32,
+    pub rook_semi_open_file_mg: i32,
+    pub rook_semi_open_file_eg: i32,
+}
+
+const PIECE_NAMES: [&str; PIECE_TYPE_COUNT] = ["pawn", "knight", "bishop", "rook", "queen", "king", "archbishop", "chancellor", "amazon"];
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        #[rustfmt::skip]
+        let pawn_mg = [
+             0,  0,  0,  0,  0,  0,  0,  0,
+            50, 50, 50, 50, 50, 50, 50, 50,
+            10, 10, 20, 30, 30, 20, 10, 10,
+             5,  5, 10, 25, 25, 10,  5,  5,
+             0,  0,  0, 20, 20,  0,  0,  0,
+             5, -5,-10,  0,  0,-10, -5,  5,
+             5, 10, 10,-20,-20, 10, 10,  5,
+             0,  0,  0,  0,  0,  0,  0,  0,
+        ];
+        #[rustfmt::skip]
+        let pawn_eg = [
+             0,  0,  0,  0,  0,  0,  0,  0,
+            80, 80, 80, 80, 80, 80, 80, 80,
+            50, 50, 50, 50, 50, 50, 50, 50,
+            30, 30, 30, 30, 30, 30, 30, 30,
+            15, 15, 15, 15, 15, 15, 15, 15,
+             5,  5,  5,  5,  5,  5,  5,  5,
+             0,  0,  0,  0,  0,  0,  0,  0,
+             0,  0,  0,  0,  0,  0,  0,  0,
+        ];
+        #[rustfmt::skip]
+        let knight = [
+            -50,-40,-30,-30,-30,-30,-40,-50,
+            -40,-20,  0,  0,  0,  0,-20,-40,
+            -30,  0, 10, 15, 15, 10,  0,-30,
+            -30,  5, 15, 20, 20, 15,  5,-30,
+            -30,  0, 15, 20, 20, 15,  0,-30,
+            -30,  5, 10, 15, 15, 10,  5,-30,
+            -40,-20,  0,  5,  5,  0,-20,-40,
+            -50,-40,-30,-30,-30,-30,-40,-50,
+        ];
+        #[rustfmt::skip]
+        let bishop = [
+            -20,-10,-10,-10,-10,-10,-10,-20,
+            -10,  0,  0,  0,  0,  0,  0,-10,
+            -10,  0,  5, 10, 10,  5,  0,-10,
+            -10,  5,  5, 10, 10,  5,  5,-10,
+            -10,  0, 10, 10, 10, 10,  0,-10,
+            -10, 10, 10, 10, 10, 10, 10,-10,
+            -10,  5,  0,  0,  0,  0,  5,-10,
+            -20,-10,-10,-10,-10,-10,-10,-20,
+        ];
+        #[rustfmt::skip]
+        let rook = [
+              0,  0,  0,  0,  0,  0,  0,  0,
+              5, 10, 10, 10, 10, 10, 10,  5,
+             -5,  0,  0,  0,  0,  0,  0, -5,
+             -5,  0,  0,  0,  0,  0,  0, -5,
+             -5,  0,  0,  0,  0,  0,  0, -5,
+             -5,  0,  0,  0,  0,  0,  0, -5,
+             -5,  0,  0,  0,  0,  0,  0, -5,
+              0,  0,  0,  5,  5,  0,  0,  0,
+        ];
+        #[rustfmt::skip]
+        let queen = [
+            -20,-10,-10, -5, -5,-10,-10,-20,
+            -10,  0,  0,  0,  0,  0,  0,-10,
+            -10,  0,  5,  5,  5,  5,  0,-10,
+             -5,  0,  5,  5,  5,  5,  0, -5,
+              0,  0,  5,  5,  5,  5,  0, -5,
+            -10,  5,  5,  5,  5,  5,  0,-10,
+            -10,  0,  5,  0,  0,  0,  0,-10,
+            -20,-10,-10, -5, -5,-10,-10,-20,
+        ];
+        #[rustfmt::skip]
+        let king_mg = [
+            -30,-40,-40,-50,-50,-40,-40,-30,
+            -30,-40,-40,-50,-50,-40,-40,-30,
+            -30,-40,-40,-50,-50,-40,-40,-30,
+            -30,-40,-40,-50,-50,-40,-40,-30,
+            -20,-30,-30,-40,-40,-30,-30,-20,
+            -10,-20,-20,-20,-20,-20,-20,-10,
+             20, 20,  0,  0,  0,  0, 20, 20,
+             20, 30, 10,  0,  0, 10, 30, 20,
+        ];
+        #[rustfmt::skip]
+        let king_eg = [
+            -50,-40,-30,-20,-20,-30,-40,-50,
+            -30,-20,-10,  0,  0,-10,-20,-30,
+            -30,-10, 20, 30, 30, 20,-10,-30,
+            -30,-10, 30, 40, 40, 30,-10,-30,
+            -30,-10, 30, 40, 40, 30,-10,-30,
+            -30,-10, 20, 30, 30, 20,-10,-30,
+            -30,-30,  0,  0,  0,  0,-30,-30,
+            -50,-30,-30,-30,-30,-30,-30,-50,
+        ];
+        EvalParams {
+            material_mg: [82, 337, 365, 477, 1025, 0, 850, 925, 1300],
+            material_eg: [94, 281, 297, 512, 936, 0, 800, 950, 1250],
+            pst_mg: [pawn_mg, knight, bishop, rook, queen, king_mg],
+            pst_eg: [pawn_eg, knight, bishop, rook, queen, king_eg],
+            mobility_mg: [0, 4, 5, 2, 1, 0, 3, 2, 1],
+            mobility_eg: [0, 4, 5, 4, 2, 0, 3, 3, 2],
+            doubled_pawn_mg: -10,
+            doubled_pawn_eg: -20,
+            isolated_pawn_mg: -10,
+            isolated_pawn_eg: -15,
+            passed_pawn_mg: [0, 5, 10, 15, 25, 40, 60, 0],
+            passed_pawn_eg: [0, 10, 20, 35, 60, 90, 130, 0],
+            king_shield_mg: 10,
+            king_attack_mg: -8,
+            bishop_pair_mg: 30,
+            bishop_pair_eg: 50,
+            rook_open_file_mg: 25,
+            rook_open_file_eg: 10,
+            rook_semi_open_file_mg: 10,
+            rook_semi_open_file_eg: 5,
+        }
+    }
+}
+
+impl EvalParams {
+    // every weight with a stable name, used for the parameter file and by the tuner
+    pub fn entries_mut(&mut self) -> Vec<(String, &mut i32)> {
+        let mut entries = Vec::new();
+        let EvalParams {
+            material_mg, material_eg, pst_mg, pst_eg, mobility_mg, mobility_eg,
+            doubled_pawn_mg, doubled_pawn_eg, isolated_pawn_mg, isolated_pawn_eg,
+            passed_pawn_mg, passed_pawn_eg, king_shield_mg, king_attack_mg,
+            bishop_pair_mg, bishop_pair_eg, rook_open_file_mg, rook_open_file_eg,
+            rook_semi_open_file_mg, rook_semi_open_file_eg,
+        } = self;
+        for (prefix, values) in [("material_mg", material_mg), ("material_eg", material_eg), ("mobility_mg", mobility_mg), ("mobility_eg", mobility_eg)] {
+            for (name, value) in PIECE_NAMES.iter().zip(values.iter_mut()) {
+                entries.push((format!("{}.{}", prefix, name), value));
+            }
+        }
+        for (prefix, tables) in [("pst_mg", pst_mg), ("pst_eg", pst_eg)] {
+            for (name, table) in PIECE_NAMES.iter().zip(tables.iter_mut()) {
+                for (square, value) in table.iter_mut().enumerate() {
+                    entries.push((format!("{}.{}.{}", prefix, name, square), value));
+                }
+            }
+        }
+        for (prefix, values) in [("passed_pawn_mg", passed_pawn_mg), ("passed_pawn_eg", passed_pawn_eg)] {
+            for (rank, value) in values.iter_mut().enumerate() {
+                entries.push((format!("{}.{}", prefix, rank), value));
+            }
+        }
+        for (name, value) in [
+            ("doubled_pawn_mg", doubled_pawn_mg), ("doubled_pawn_eg", doubled_pawn_eg),
+            ("isolated_pawn_mg", isolated_pawn_mg), ("isolated_pawn_eg", isolated_pawn_eg),
+            ("king_shield_mg", king_shield_mg), ("king_attack_mg", king_attack_mg),
+            ("bishop_pair_mg", bishop_pair_mg), ("bishop_pair_eg", bishop_pair_eg),
+            ("rook_open_file_mg", rook_open_file_mg), ("rook_open_file_eg", rook_open_file_eg),
+            ("rook_semi_open_file_mg", rook_semi_open_file_mg), ("rook_semi_open_file_eg", rook_semi_open_file_eg),
+        ] {
+            entries.push((name.to_string(), value));
+        }
+        entries
+    }
+
+    // reads `name = value` lines, '#' starts a comment and weights that aren't listed keep their default
+    pub fn load(path: &str) -> Result<EvalParams, String> {
+        let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        let mut params = EvalParams::default();
+        let mut entries = params.entries_mut();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = line.split_once('=').ok_or(format!("{}:{}: expected name = value", path, number + 1))?;
+            let value: i32 = value.trim().parse().map_err(|_| format!("{}:{}: '{}' is not a number", path, number + 1, value.trim()))?;
+            match entries.iter_mut().find(|(entry, _)| entry == name.trim()) {
+                Some((_, slot)) => **slot = value,
+                None => return Err(format!("{}:{}: unknown parameter '{}'", path, number + 1, name.trim())),
+            }
+        }
+        drop(entries);
+        Ok(params)
+    }
+
+    // the same format load reads, with every weight written out
+    pub fn to_text(&self) -> String {
+        let mut copy = self.clone();
+        copy.entries_mut().iter().map(|(name, value)| format!("{} = {}\n", name, value)).collect()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_text()).map_err(|error| format!("{}: {}", path, error))
+    }
+}
+
+// table entry for a piece, tables are from White's side with rank 8 first
+fn pst_index(board: &Board, position: (usize, usize), color: Color) -> usize {
+    let row = position.0 * PST_SIZE / board.rows;
+    let col = position.1 * PST_SIZE / board.cols;
+    let rank_from_top = match color {
+        Color::White => PST_SIZE - 1 - row,
+        Color::Black => row,
+    };
+    rank_from_top * PST_SIZE + col
+}
+
+// fairy pieces use the table of the piece they mostly move like
+fn pst_table(piece_type: PieceType) -> usize {
+    match piece_type {
+        PieceType::Archbishop => PieceType::Bishop.index(),
+        PieceType::Chancellor => PieceType::Rook.index(),
+        PieceType::Amazon => PieceType::Queen.index(),
+        other => other.index(),
+    }
+}
+
+// score of the board from White's side in centipawns, blending the middlegame and endgame
+// terms by how much material is left
+pub fn evaluate(board: &Board, params: &EvalParams) -> i32 {
+    let mut mg = [0; 2];
+    let mut eg = [0; 2];
+    let mut phase = 0;
+    let mut bishops = [0; 2];
+    let mut pawns_on_file = [[0; MAX_BOARD_SIZE]; 2];
+    let mut kings: [Option<(usize, usize)>; 2] = [None, None];
+    // squares each side attacks, used for king safety
+    let mut attacked = [[[false; MAX_BOARD_SIZE]; MAX_BOARD_SIZE]; 2];
+
+    for (us, color) in [Color::White, Color::Black].into_iter().enumerate() {
+        for (col, count) in pawns_on_file[us].iter_mut().enumerate().take(board.cols) {
+            *count = (0..board.rows).filter(|&row| is_pawn(board, (row, col), color)).count() as i32;
+        }
+    }
+
+    for row in 0..board.rows {
+        for col in 0..board.cols {
+            let piece = match board.get_piece_at((row, col)) {
+                Some(piece) => piece,
+                None => continue,
+            };
+            let us = side(piece.color);
+            let kind = piece.piece_type.index();
+            let square = pst_index(board, (row, col), piece.color);
+            mg[us] += params.material_mg[kind] + params.pst_mg[pst_table(piece.piece_type)][square];
+            eg[us] += params.material_eg[kind] + params.pst_eg[pst_table(piece.piece_type)][square];
+            phase += PHASE_WEIGHT[kind];
+
+            match piece.piece_type {
+                PieceType::Pawn => {
+                    let forward_row = match piece.color {
+                        Color::White => row + 1,
+                        Color::Black => row.wrapping_sub(1),
+                    };
+                    if forward_row < board.rows {
+                        for attacked_col in [col.wrapping_sub(1), col + 1] {
+                            if attacked_col < board.cols {
+                                attacked[us][forward_row][attacked_col] = true;
+                            }
+                        }
+                    }
+                    if is_passed(board, (row, col), piece.color) {
+                        let advanced = match piece.color {
+                            Color::White => row,
+                            Color::Black => board.rows - 1 - row,
+                        };
+                        let rank = advanced * (PST_SIZE - 1) / (board.rows - 1);
+                        mg[us] += params.passed_pawn_mg[rank];
+                        eg[us] += params.passed_pawn_eg[rank];
+                    }
+                }
+                PieceType::King => kings[us] = Some((row, col)),
+                _ => {
+                    let moves = piece.valid_moves((row, col), board);
+                    mg[us] += params.mobility_mg[kind] * moves.len() as i32;
+                    eg[us] += params.mobility_eg[kind] * moves.len() as i32;
+                    for (to_row, to_col) in moves {
+                        attacked[us][to_row][to_col] = true;
+                    }
+                    if piece.piece_type == PieceType::Bishop {
+                        bishops[us] += 1;
+                    }
+                    if piece.piece_type == PieceType::Rook {
+                        let own_pawns = (0..board.rows).any(|r| is_pawn(board, (r, col), piece.color));
+                        let their_pawns = (0..board.rows).any(|r| is_pawn(board, (r, col), piece.color.opponent()));
+                        if !own_pawns && !their_pawns {
+                            mg[us] += params.rook_open_file_mg;
+                            eg[us] += params.rook_open_file_eg;
+                        } else if !own_pawns {
+                            mg[us] += params.rook_semi_open_file_mg;
+                            eg[us] += params.rook_semi_open_file_eg;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for us in 0..2 {
+        let files = &pawns_on_file[us];
+        for (col, &count) in files.iter().enumerate().take(board.cols) {
+            if count == 0 {
+                continue;
+            }
+            mg[us] += params.doubled_pawn_mg * (count - 1);
+            eg[us] += params.doubled_pawn_eg * (count - 1);
+            let left = col > 0 && files[col - 1] > 0;
+            let right = col + 1 < board.cols && files[col + 1] > 0;
+            if !left && !right {
+                mg[us] += params.isolated_pawn_mg * count;
+                eg[us] += params.isolated_pawn_eg * count;
+            }
+        }
+        if bishops[us] >= 2 {
+            mg[us] += params.bishop_pair_mg;
+            eg[us] += params.bishop_pair_eg;
+        }
+        if let Some(king) = kings[us] {
+            mg[us] += king_safety(board, king, us, &attacked[1 - us], params);
+        }
+    }
+
+    let phase = phase.min(MAX_PHASE);
+    let mg_score = mg[0] - mg[1];
+    let eg_score = eg[0] - eg[1];
+    (mg_score * phase + eg_score * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+fn side(color: Color) -> usize {
+    if color == Color::White { 0 } else { 1 }
+}
+
+fn is_pawn(board: &Board, position: (usize, usize), color: Color) -> bool {
+    board.get_piece_at(position).is_some_and(|piece| piece.piece_type == PieceType::Pawn && piece.color == color)
+}
+
+// no enemy pawn in front of it on its own or a neighbouring file
+fn is_passed(board: &Board, (row, col): (usize, usize), color: Color) -> bool {
+    let ahead: Vec<usize> = match color {
+        Color::White => (row + 1..board.rows).collect(),
+        Color::Black => (0..row).collect(),
+    };
+    for check_col in [col.wrapping_sub(1), col, col + 1] {
+        if check_col >= board.cols {
+            continue;
+        }
+        for &check_row in &ahead {
+            if is_pawn(board, (check_row, check_col), color.opponent()) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+// pawns sheltering the king and enemy attacks on the squares around it, middlegame only
+fn king_safety(board: &Board, (row, col): (usize, usize), us: usize, enemy_attacks: &[[bool; MAX_BOARD_SIZE]; MAX_BOARD_SIZE], params: &EvalParams) -> i32 {
+    let color = if us == 0 { Color::White } else { Color::Black };
+    let mut score = 0;
+    for dr in [-1i32, 0, 1] {
+        for dc in [-1i32, 0, 1] {
+            let r = row as i32 + dr;
+            let c = col as i32 + dc;
+            if r < 0 || c < 0 || r >= board.rows as i32 || c >= board.cols as i32 || (dr == 0 && dc == 0) {
+                continue;
+            }
+            if enemy_attacks[r as usize][c as usize] {
+                score += params.king_attack_mg;
+            }
+        }
+    }
+    for ahead in 1..=2 {
+        let shield_row = match color {
+            Color::White => row + ahead,
+            Color::Black => row.wrapping_sub(ahead),
+        };
+        if shield_row >= board.rows {
+            continue;
+        }
+        for shield_col in [col.wrapping_sub(1), col, col + 1] {
+            if shield_col < board.cols && is_pawn(board, (shield_row, shield_col), color) {
+                score += params.king_shield_mg;
+            }
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, EvalParams};
+    use crate::fen;
+
+    // the same position with the colours swapped: ranks in reverse order and the case of every piece flipped
+    fn mirrored(text: &str) -> String {
+        let placement = text.split_whitespace().next().unwrap();
+        placement.split('/').rev()
+            .map(|rank| rank.chars().map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    #[test]
+    fn colours_are_scored_alike() {
+        let params = EvalParams::default();
+        let positions = [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R",
+            "r2q1rk1/pp2bppp/2np1n2/4p3/2B1P3/2N2N1P/PPP2PP1/R2Q1RK1",
+            "8/5pk1/6p1/3P4/1P6/8/5PPP/6K1",
+            "6k1/5ppp/8/8/8/8/1q3PPP/3R2K1",
+            "rnabqkbcnr/pppp1ppppp/10/4p5/4P5/10/PPPP1PPPPP/RNABQKBCNR",
+        ];
+        for text in positions {
+            let board = fen::parse(&format!("{} w - - 0 1", text)).unwrap().board;
+            let other = fen::parse(&format!("{} w - - 0 1", mirrored(text))).unwrap().board;
+            assert_eq!(evaluate(&board, &params), -evaluate(&other, &params), "{}", text);
+        }
+        assert_eq!(evaluate(&fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1").unwrap().board, &params), 0);
+    }
+}
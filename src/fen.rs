@@ -0,0 +1,256 @@
+use crate::board::{Board, MAX_BOARD_SIZE};
+use crate::chess_move::square_name;
+use crate::game::{Game, Variant};
+use crate::piece::{Color, Piece, PieceType};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+fn piece_type_from_letter(letter: char) -> Option<PieceType> {
+    match letter.to_ascii_lowercase() {
+        'p' => Some(PieceType::Pawn),
+        'n' => Some(PieceType::Knight),
+        'b' => Some(PieceType::Bishop),
+        'r' => Some(PieceType::Rook),
+        'q' => Some(PieceType::Queen),
+        'k' => Some(PieceType::King),
+        'a' => Some(PieceType::Archbishop),
+        'c' => Some(PieceType::Chancellor),
+        'z' => Some(PieceType::Amazon),
+        _ => None,
+    }
+}
+
+// upper case for White, lower case for Black
+pub fn letter(piece: &Piece) -> char {
+    let letter = match piece.piece_type {
+        PieceType::Pawn => 'p',
+        PieceType::Knight => 'n',
+        PieceType::Bishop => 'b',
+        PieceType::Rook => 'r',
+        PieceType::Queen => 'q',
+        PieceType::King => 'k',
+        PieceType::Archbishop => 'a',
+        PieceType::Chancellor => 'c',
+        PieceType::Amazon => 'z',
+    };
+    if piece.color == Color::White { letter.to_ascii_uppercase() } else { letter }
+}
+
+// reads a position in Forsyth-Edwards Notation
+// the board size comes from the number of ranks and files, so 10x8 and 10x10 positions work too
+// and pick the matching variant
+pub fn parse(text: &str) -> Result<Game, String> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    let placement = fields.first().ok_or("empty FEN")?;
+    let ranks: Vec<&str> = placement.split('/').collect();
+    let rows = ranks.len();
+    if rows > MAX_BOARD_SIZE {
+        return Err(format!("FEN has {} ranks, at most {} are supported", rows, MAX_BOARD_SIZE));
+    }
+
+    // the files of each rank, written from rank 8 down
+    let mut squares: Vec<Vec<Option<Piece>>> = Vec::new();
+    for rank in &ranks {
+        let mut files = Vec::new();
+        let mut empty = 0;
+        for symbol in rank.chars() {
+            if let Some(digit) = symbol.to_digit(10) {
+                // multi-digit counts like the 10 in a 10-file rank
+                empty = empty * 10 + digit as usize;
+            } else {
+                files.extend((0..empty).map(|_| None));
+                empty = 0;
+                let piece_type = piece_type_from_letter(symbol).ok_or(format!("unknown piece '{}' in FEN", symbol))?;
+                let color = if symbol.is_ascii_uppercase() { Color::White } else { Color::Black };
+                files.push(Some(Piece { piece_type, color, has_moved: false }));
+            }
+            // checked as the rank is read, so a long run of digits can't overflow or allocate a huge rank
+            if files.len() + empty > MAX_BOARD_SIZE {
+                return Err(format!("FEN rank '{}' has more than {} files", rank, MAX_BOARD_SIZE));
+            }
+        }
+        files.extend((0..empty).map(|_| None));
+        squares.push(files);
+    }
+    let cols = squares[0].len();
+    if cols == 0 || cols > MAX_BOARD_SIZE || squares.iter().any(|files| files.len() != cols) {
+        return Err(String::from("FEN ranks don't all have the same number of files"));
+    }
+
+    let variant = match (rows, cols) {
+        (8, 8) => Variant::Standard,
+        (8, 10) => Variant::Capablanca,
+        (10, 10) => Variant::Grand,
+        _ => return Err(format!("no variant is played on a {}x{} board", cols, rows)),
+    };
+    let mut game = Game::with_variant(variant);
+    let pawn_row = game.board.pawn_row;
+    game.board = Board::empty(rows, cols);
+    game.board.pawn_row = pawn_row;
+    for (rank_from_top, files) in squares.into_iter().enumerate() {
+        for (col, piece) in files.into_iter().enumerate() {
+            if let Some(piece) = piece {
+                game.board.set_piece_at((rows - 1 - rank_from_top, col), piece);
+            }
+        }
+    }
+
+    game.current_player = match fields.get(1).copied().unwrap_or("w") {
+        "w" => Color::White,
+        "b" => Color::Black,
+        other => return Err(format!("side to move should be w or b, not '{}'", other)),
+    };
+
+    // castling rights live in the has_moved flags, so kings and rooks only count as unmoved
+    // when a right says so
+    let castling = fields.get(2).copied().unwrap_or("-");
+    if castling != "-" && castling.chars().any(|right| !"KQkq".contains(right)) {
+        return Err(format!("castling rights should be made of KQkq, not '{}'", castling));
+    }
+    for row in 0..rows {
+        for col in 0..cols {
+            let mut piece = match game.board.get_piece_at((row, col)) {
+                Some(piece) => piece,
+                None => continue,
+            };
+            let (home_row, king_side, queen_side) = match piece.color {
+                Color::White => (0, castling.contains('K'), castling.contains('Q')),
+                Color::Black => (rows - 1, castling.contains('k'), castling.contains('q')),
+            };
+            let home = row == home_row;
+            piece.has_moved = match piece.piece_type {
+                PieceType::King => !(home && (king_side || queen_side)),
+                PieceType::Rook => !(home && ((col == cols - 1 && king_side) || (col == 0 && queen_side))),
+                _ => false,
+            };
+            game.board.set_piece_at((row, col), piece);
+        }
+    }
+
+    game.en_passant = match fields.get(3).copied().unwrap_or("-") {
+        "-" => None,
+        square => {
+            let target = parse_square(square, &game.board).ok_or(format!("bad en passant square '{}'", square))?;
+            // the square skipped by a double step of the side that just moved, so it has to be empty
+            // with the pawn right behind it and its starting square empty again
+            let (skipped, landed, started, mover) = match game.current_player {
+                Color::Black => (pawn_row + 1, pawn_row + 2, pawn_row, Color::White),
+                Color::White => (rows - 2 - pawn_row, rows - 3 - pawn_row, rows - 1 - pawn_row, Color::Black),
+            };
+            let pawn_behind = game.board.get_piece_at((landed, target.1))
+                .is_some_and(|piece| piece.piece_type == PieceType::Pawn && piece.color == mover);
+            if target.0 != skipped || game.board.get_piece_at(target).is_some()
+                || game.board.get_piece_at((started, target.1)).is_some() || !pawn_behind {
+                return Err(format!("no pawn can just have passed the en passant square '{}'", square));
+            }
+            Some(target)
+        }
+    };
+    let counter = |index: usize, default: u32, name: &str| match fields.get(index) {
+        Some(text) => text.parse::<u32>().map_err(|_| format!("the {} should be a number, not '{}'", name, text)),
+        None => Ok(default),
+    };
+    game.halfmove_clock = counter(4, 0, "halfmove clock")?;
+    // some programs write 0 for the first move
+    game.fullmove_number = counter(5, 1, "fullmove number")?.max(1);
+    if fields.len() > 6 {
+        return Err(String::from("FEN has too many fields"));
+    }
+    Ok(game)
+}
+
+// reads a position for a game of the given variant, with the state worked out
+// antichess is played on the standard board so the FEN can't tell it apart, the variant says so instead
+pub fn parse_for(variant: Variant, text: &str) -> Result<Game, String> {
+    let mut game = parse(text)?;
+    if variant == Variant::Antichess && game.variant == Variant::Standard {
+        game.variant = Variant::Antichess;
+    }
+    game.update_state();
+    Ok(game)
+}
+
+pub fn parse_square(text: &str, board: &Board) -> Option<(usize, usize)> {
+    let mut chars = text.chars();
+    let file = chars.next()?;
+    let col = (file as usize).checked_sub('a' as usize)?;
+    let row = chars.as_str().parse::<usize>().ok()?.checked_sub(1)?;
+    (row < board.rows && col < board.cols).then_some((row, col))
+}
+
+// writes the position as FEN
+pub fn to_fen(game: &Game) -> String {
+    let board = &game.board;
+    let mut ranks = Vec::new();
+    for row in (0..board.rows).rev() {
+        let mut rank = String::new();
+        let mut empty = 0;
+        for col in 0..board.cols {
+            match board.get_piece_at((row, col)) {
+                Some(piece) => {
+                    if empty > 0 {
+                        rank.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    rank.push(letter(&piece));
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            rank.push_str(&empty.to_string());
+        }
+        ranks.push(rank);
+    }
+
+    let rights = game.castling_rights();
+    let castling: String = ['K', 'Q', 'k', 'q'].iter().zip(rights).filter(|(_, right)| *right).map(|(letter, _)| *letter).collect();
+    format!(
+        "{} {} {} {} {} {}",
+        ranks.join("/"),
+        if game.current_player == Color::White { "w" } else { "b" },
+        if castling.is_empty() { "-" } else { &castling },
+        game.en_passant.map(square_name).unwrap_or_else(|| String::from("-")),
+        game.halfmove_clock,
+        game.fullmove_number,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_empty_counts_are_rejected() {
+        assert!(parse("99999999999999999999/8/8/8/8/8/8/8 w - - 0 1").is_err());
+        assert!(parse("4000000000/8/8/8/8/8/8/8 w - - 0 1").is_err());
+        assert!(parse("k12/8/8/8/8/8/8/7K w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn move_counters_are_kept() {
+        let text = "4k3/8/8/8/8/8/8/4K2R b K - 37 52";
+        assert_eq!(to_fen(&parse(text).unwrap()), text);
+        let mut game = Game::new();
+        for (from, to) in [((1, 4), (3, 4)), ((7, 6), (5, 5)), ((0, 6), (2, 5))] {
+            game.apply_move(from, to, PieceType::Queen);
+        }
+        assert!(to_fen(&game).ends_with(" 2 2"));
+    }
+
+    #[test]
+    fn en_passant_square_has_to_fit_the_position() {
+        let text = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        assert_eq!(parse(text).unwrap().en_passant, Some((2, 4)));
+        // wrong rank for the side to move
+        assert!(parse("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e6 0 1").is_err());
+        assert!(parse("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 1").is_err());
+        // no pawn behind the square
+        assert!(parse("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq d3 0 1").is_err());
+        // the square itself is taken
+        assert!(parse("rnbqkbnr/pppppppp/8/8/4P3/4N3/PPPP1PPP/RNBQKB1R b KQkq e3 0 1").is_err());
+        let black = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+        assert_eq!(parse(black).unwrap().en_passant, Some((5, 3)));
+    }
+}
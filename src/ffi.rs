@@ -0,0 +1,201 @@
+// the C API, declared in include/final_proj.h (generated with cbindgen, see cbindgen.toml)
+// every function that takes pointers is unsafe for the same reasons, so they are listed once here
+// instead of on each function:
+//   a ChessGame pointer comes from chess_game_new or chess_game_new_variant and hasn't been freed
+//   strings are NUL-terminated UTF-8
+//   a buffer has room for at least `capacity` bytes
+// null pointers are allowed everywhere and answered with CHESS_ERROR_NULL or an empty result
+// a panic never unwinds into C, each function catches it and answers CHESS_ERROR_PANIC, NULL or
+// an empty result instead, a game that was being changed at the time shouldn't be used any more
+#![allow(clippy::missing_safety_doc)]
+
+use crate::fen;
+use crate::game::{Game, GameState, Variant};
+use crate::notation;
+use crate::piece::Color;
+use std::ffi::{c_char, CStr};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+pub const CHESS_OK: i32 = 0;
+pub const CHESS_ERROR_NULL: i32 = -1;
+pub const CHESS_ERROR_UTF8: i32 = -2;
+pub const CHESS_ERROR_FEN: i32 = -3;
+pub const CHESS_ERROR_ILLEGAL_MOVE: i32 = -4;
+pub const CHESS_ERROR_PANIC: i32 = -5;
+
+// a game as C sees it, only ever handled through a pointer
+pub struct ChessGame {
+    game: Game,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChessState {
+    Ongoing,
+    Check,
+    Checkmate,
+    Stalemate,
+    Draw,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChessColor {
+    White,
+    Black,
+}
+
+// a standard game in its starting position, free it with chess_game_free
+#[no_mangle]
+pub extern "C" fn chess_game_new() -> *mut ChessGame {
+    guard(ptr::null_mut(), || {
+        Box::into_raw(Box::new(ChessGame { game: Game::new() }))
+    })
+}
+
+// a game of the named variant (standard, antichess, capablanca or grand), NULL for any other name
+#[no_mangle]
+pub unsafe extern "C" fn chess_game_new_variant(variant: *const c_char) -> *mut ChessGame {
+    guard(ptr::null_mut(), || {
+        match read_str(variant).ok().and_then(Variant::from_name) {
+            Some(variant) => Box::into_raw(Box::new(ChessGame { game: Game::with_variant(variant) })),
+            None => ptr::null_mut(),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn chess_game_free(game: *mut ChessGame) {
+    guard((), || {
+        if !game.is_null() {
+            drop(Box::from_raw(game));
+        }
+    })
+}
+
+// sets the position up from a FEN, the game keeps its variant when the FEN fits it
+// the game is left as it was when the FEN can't be read
+#[no_mangle]
+pub unsafe extern "C" fn chess_game_load_fen(game: *mut ChessGame, text: *const c_char) -> i32 {
+    guard(CHESS_ERROR_PANIC, || {
+        let Some(game) = game.as_mut() else { return CHESS_ERROR_NULL };
+        let text = match read_str(text) {
+            Ok(text) => text,
+            Err(error) => return error,
+        };
+        let Ok(position) = fen::parse_for(game.game.variant, text) else { return CHESS_ERROR_FEN };
+        game.game = position;
+        CHESS_OK
+    })
+}
+
+// writes the position as FEN into 'buffer', see write_string for how it is cut to fit
+#[no_mangle]
+pub unsafe extern "C" fn chess_game_fen(game: *const ChessGame, buffer: *mut c_char, capacity: usize) -> usize {
+    guard(0, || {
+        match game.as_ref() {
+            Some(game) => write_string(&fen::to_fen(&game.game), buffer, capacity),
+            None => write_string("", buffer, capacity),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn chess_game_legal_move_count(game: *const ChessGame) -> usize {
+    guard(0, || {
+        game.as_ref().map_or(0, |game| game.game.generate_moves().len())
+    })
+}
+
+// writes the legal moves into 'buffer' in UCI notation separated by spaces, e.g. "e2e4 d2d4 ..."
+#[no_mangle]
+pub unsafe extern "C" fn chess_game_legal_moves(game: *const ChessGame, buffer: *mut c_char, capacity: usize) -> usize {
+    guard(0, || {
+        let moves = match game.as_ref() {
+            Some(game) => game.game.generate_moves().iter().map(|mv| mv.notation()).collect::<Vec<_>>().join(" "),
+            None => String::new(),
+        };
+        write_string(&moves, buffer, capacity)
+    })
+}
+
+// plays a move in UCI notation (e2e4, e7e8q) once Game::is_valid_move allows it
+#[no_mangle]
+pub unsafe extern "C" fn chess_game_make_move(game: *mut ChessGame, text: *const c_char) -> i32 {
+    guard(CHESS_ERROR_PANIC, || {
+        let Some(game) = game.as_mut() else { return CHESS_ERROR_NULL };
+        let text = match read_str(text) {
+            Ok(text) => text,
+            Err(error) => return error,
+        };
+        let game = &mut game.game;
+        match notation::parse_uci_move(game, text).filter(|mv| game.is_valid_move(mv.from, mv.to)) {
+            Some(mv) => {
+                game.make_move(&mv);
+                CHESS_OK
+            }
+            None => CHESS_ERROR_ILLEGAL_MOVE,
+        }
+    })
+}
+
+// Ongoing for a null game
+#[no_mangle]
+pub unsafe extern "C" fn chess_game_state(game: *const ChessGame) -> ChessState {
+    guard(ChessState::Ongoing, || {
+        match game.as_ref().map(|game| game.game.state) {
+            Some(GameState::Check) => ChessState::Check,
+            Some(GameState::Checkmate) => ChessState::Checkmate,
+            Some(GameState::Stalemate) => ChessState::Stalemate,
+            Some(GameState::Draw) => ChessState::Draw,
+            Some(GameState::Ongoing) | None => ChessState::Ongoing,
+        }
+    })
+}
+
+// the side to move, White for a null game
+#[no_mangle]
+pub unsafe extern "C" fn chess_game_turn(game: *const ChessGame) -> ChessColor {
+    guard(ChessColor::White, || {
+        match game.as_ref().map(|game| game.game.current_player) {
+            Some(Color::Black) => ChessColor::Black,
+            _ => ChessColor::White,
+        }
+    })
+}
+
+// runs the body of an exported function, answering 'on_panic' if it panics
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+unsafe fn read_str<'a>(text: *const c_char) -> Result<&'a str, i32> {
+    if text.is_null() {
+        return Err(CHESS_ERROR_NULL);
+    }
+    CStr::from_ptr(text).to_str().map_err(|_| CHESS_ERROR_UTF8)
+}
+
+// copies as much of 'text' as fits and always ends it with a NUL, like snprintf
+// returns the full length of 'text', so a result of 'capacity' or more means it was cut short
+// and the call can be repeated with a buffer of the result + 1 bytes
+unsafe fn write_string(text: &str, buffer: *mut c_char, capacity: usize) -> usize {
+    if !buffer.is_null() && capacity > 0 {
+        let length = text.len().min(capacity - 1);
+        ptr::copy_nonoverlapping(text.as_ptr().cast::<c_char>(), buffer, length);
+        *buffer.add(length) = 0;
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_become_error_codes() {
+        assert_eq!(guard(CHESS_ERROR_PANIC, || panic!("a bug in the library")), CHESS_ERROR_PANIC);
+        assert_eq!(guard(CHESS_ERROR_PANIC, || CHESS_OK), CHESS_OK);
+    }
+}
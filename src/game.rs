@@ -1,10 +1,14 @@
-use crate::board::{Board, Cell};
-use crate::board::BOARD_SIZE;
+use crate::board::Board;
 use crate::piece::Piece;
+use crate::chess_move::Move;
 use crate::piece::{PieceType, Color};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum GameState {
     Ongoing,
     Check,
@@ -12,21 +16,100 @@ pub enum GameState {
     Stalemate,
     Draw,
 }
+impl GameState {
+    //lowercase name for text formats such as JSON
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameState::Ongoing => "ongoing",
+            GameState::Check => "check",
+            GameState::Checkmate => "checkmate",
+            GameState::Stalemate => "stalemate",
+            GameState::Draw => "draw",
+        }
+    }
+}
+//rule sets the game can be played under
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Variant {
+    Standard,
+    //losing chess: captures are compulsory, the king is an ordinary piece and
+    //the first player to run out of pieces or moves wins
+    Antichess,
+    //standard rules on a 10x8 board with the archbishop and chancellor
+    Capablanca,
+    //standard rules on a 10x10 board with the archbishop and chancellor
+    Grand,
+}
+
+impl Variant {
+    pub fn from_name(name: &str) -> Option<Variant> {
+        let names: [(&[&str], Variant); 4] = [
+            (&["standard", "chess"], Variant::Standard),
+            (&["antichess", "losing"], Variant::Antichess),
+            (&["capablanca"], Variant::Capablanca),
+            (&["grand"], Variant::Grand),
+        ];
+        names.into_iter().find(|(aliases, _)| aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))).map(|(_, variant)| variant)
+    }
+    //the name from_name reads back
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Standard => "standard",
+            Variant::Antichess => "antichess",
+            Variant::Capablanca => "capablanca",
+            Variant::Grand => "grand",
+        }
+    }
+    //starting position for the variant
+    pub fn board(&self) -> Board {
+        match self {
+            Variant::Standard | Variant::Antichess => Board::new(),
+            Variant::Capablanca => Board::capablanca(),
+            Variant::Grand => Board::grand(),
+        }
+    }
+}
 #[derive(Clone)]
 pub struct Game {
     pub board: Board,
     pub current_player: Color,
     pub state: GameState,
-    
+    pub variant: Variant,
+    //every move played so far, oldest first, only kept when there is an allocator
+    #[cfg(feature = "alloc")]
+    pub history: Vec<Move>,
+    //square a pawn can move to when capturing en passant, set right after a pawn's double step
+    pub en_passant: Option<(usize, usize)>,
+    //plies since the last capture or pawn move, for the fifty-move rule
+    pub halfmove_clock: u32,
+    //starts at 1 and goes up after each of Black's moves, as in FEN
+    pub fullmove_number: u32,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
 }
 
 impl Game {
     pub fn new() -> Self {
-        // initialize new game
+        Game::with_variant(Variant::Standard)
+    }
+    // initialize new game for the given rule set
+    pub fn with_variant(variant: Variant) -> Self {
         Self {
-            board: Board::new(),
-            current_player: Color::Black, 
+            board: variant.board(),
+            current_player: Color::White,
             state: GameState::Ongoing,
+            variant,
+            #[cfg(feature = "alloc")]
+            history: Vec::new(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
         }
     }
     //swap player after each turn
@@ -49,18 +132,292 @@ impl Game {
             }
     
             let valid_moves = piece.valid_moves(from, &self.board);
-            if valid_moves.contains(&to) {
-                return true;
+            if !valid_moves.contains(&to) && !self.is_castling_move(from, to) && !self.is_en_passant_move(from, to) {
+                return false;
+            }
+            //in antichess a quiet move is only allowed when no capture is available
+            if self.variant == Variant::Antichess && !self.is_capture(from, to) && self.has_capture() {
+                return false;
+            }
+            //a move can never leave your own king in check
+            if self.variant != Variant::Antichess && self.leaves_king_in_check(from, to) {
+                return false;
             }
+            return true;
         }
     
         false
     }
+    //checks if the move takes a piece, including en passant
+    pub fn is_capture(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        self.board.get_piece_at(to).is_some() || self.is_en_passant_move(from, to)
+    }
+    //a pawn moving diagonally onto the square just skipped by an enemy pawn's double step
+    pub fn is_en_passant_move(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        match self.board.get_piece_at(from) {
+            Some(piece) if piece.piece_type == PieceType::Pawn && self.en_passant == Some(to) => {
+                let forward = match piece.color {
+                    Color::White => from.0 + 1 == to.0,
+                    Color::Black => to.0 + 1 == from.0,
+                };
+                forward && from.1.abs_diff(to.1) == 1
+            }
+            _ => false,
+        }
+    }
+    //the king castles by moving to the c-file or to the second file from the right (g on a normal board)
+    pub fn is_castling_move(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        if self.variant == Variant::Antichess || from.0 != to.0 || from.1.abs_diff(to.1) < 2 {
+            return false;
+        }
+        if to.1 != 2 && to.1 != self.board.cols - 2 {
+            return false;
+        }
+        self.can_castle(from, to)
+    }
+    //tries the move on a copy of the game and looks for attacks on the mover's king
+    fn leaves_king_in_check(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let mut temp_game = self.without_history();
+        temp_game.play(from, to, PieceType::Queen);
+        match temp_game.find_king(self.current_player) {
+            Some(king_pos) => temp_game.is_square_under_attack(king_pos, self.current_player),
+            None => false,
+        }
+    }
+    //copy of the game for trying moves out, the move list isn't needed for that
+    pub fn without_history(&self) -> Game {
+        Game {
+            board: self.board.clone(),
+            current_player: self.current_player,
+            state: self.state,
+            variant: self.variant,
+            #[cfg(feature = "alloc")]
+            history: Vec::new(),
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        }
+    }
+    //checks if the current player can capture anything this turn
+    fn has_capture(&self) -> bool {
+        for i in 0..self.board.rows {
+            for j in 0..self.board.cols {
+                if let Some(piece) = self.board.get_piece_at((i, j)) {
+                    if piece.color == self.current_player {
+                        for to in piece.valid_moves((i, j), &self.board) {
+                            if self.board.get_piece_at(to).is_some() {
+                                return true;
+                            }
+                        }
+                        if let Some(target) = self.en_passant {
+                            if self.is_en_passant_move((i, j), target) {
+                                return true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+    //whether the current player has any move at all, without building the move list
+    pub fn has_legal_move(&self) -> bool {
+        for i in 0..self.board.rows {
+            for j in 0..self.board.cols {
+                if let Some(piece) = self.board.get_piece_at((i, j)) {
+                    if piece.color == self.current_player {
+                        let mut targets = piece.valid_moves((i, j), &self.board);
+                        if piece.piece_type == PieceType::King {
+                            targets.push((i, 2));
+                            targets.push((i, self.board.cols - 2));
+                        }
+                        if let Some(target) = self.en_passant {
+                            targets.push(target);
+                        }
+                        if targets.iter().any(|&to| self.is_valid_move((i, j), to)) {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+    //every move the current player is allowed to make, as (from, to) pairs
+    #[cfg(feature = "alloc")]
+    pub fn legal_moves(&self) -> Vec<((usize, usize), (usize, usize))> {
+        let mut moves = Vec::new();
+        for i in 0..self.board.rows {
+            for j in 0..self.board.cols {
+                if let Some(piece) = self.board.get_piece_at((i, j)) {
+                    if piece.color == self.current_player {
+                        let mut targets = piece.valid_moves((i, j), &self.board);
+                        // special moves that piece.rs doesn't know about
+                        if piece.piece_type == PieceType::King {
+                            targets.push((i, 2));
+                            targets.push((i, self.board.cols - 2));
+                        }
+                        if let Some(target) = self.en_passant {
+                            targets.push(target);
+                        }
+                        for to in targets {
+                            if !moves.contains(&((i, j), to)) && self.is_valid_move((i, j), to) {
+                                moves.push(((i, j), to));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        moves
+    }
+    //legal moves with one entry per promotion choice, used for listing moves and by the search
+    #[cfg(feature = "alloc")]
+    pub fn generate_moves(&self) -> Vec<Move> {
+        let promotions: Vec<PieceType> = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight,
+            PieceType::Archbishop, PieceType::Chancellor, PieceType::King]
+            .into_iter()
+            .filter(|&piece_type| self.can_promote_to(piece_type))
+            .collect();
+        let mut moves = Vec::new();
+        for (from, to) in self.legal_moves() {
+            let piece_moved = match self.board.get_piece_at(from) {
+                Some(piece) => piece,
+                None => continue,
+            };
+            let mut record = Move::new(from, to, piece_moved, self.board.get_piece_at(to));
+            record.is_castling_move = self.is_castling_move(from, to);
+            record.is_en_passant_move = self.is_en_passant_move(from, to);
+            if record.is_en_passant_move {
+                record.piece_captured = self.board.get_piece_at((from.0, to.1));
+            }
+            if self.is_promotion_move(from, to) {
+                for &piece_type in &promotions {
+                    record.promoted_to = Some(piece_type);
+                    moves.push(record);
+                }
+            } else {
+                moves.push(record);
+            }
+        }
+        moves
+    }
+    //plays a move produced by generate_moves
+    pub fn make_move(&mut self, mv: &Move) {
+        self.apply_move(mv.from, mv.to, mv.promoted_to.unwrap_or(PieceType::Queen));
+    }
+    //the same but the state is left as it was, working it out costs a search for a legal move
+    //for the search and anything else that plays moves in positions nobody is shown
+    pub fn make_move_without_state(&mut self, mv: &Move) {
+        self.play(mv.from, mv.to, mv.promoted_to.unwrap_or(PieceType::Queen));
+    }
+    //in antichess the player to move wins once they have no pieces left or are stalemated
+    pub fn antichess_winner(&self) -> Option<Color> {
+        if self.variant != Variant::Antichess {
+            return None;
+        }
+        if !self.has_legal_move() {
+            Some(self.current_player)
+        } else {
+            None
+        }
+    }
+    //sets the state from the position: checkmate or stalemate when the side to move has no moves
+    //(in antichess that is a win for them), a draw by the rules, otherwise check or ongoing
+    //draws by agreement can't be seen from the game and are never set here
+    pub fn update_state(&mut self) {
+        self.state = if !self.has_legal_move() {
+            if self.is_in_check() { GameState::Checkmate } else { GameState::Stalemate }
+        } else if self.draw_reason().is_some() {
+            GameState::Draw
+        } else if self.is_in_check() {
+            GameState::Check
+        } else {
+            GameState::Ongoing
+        };
+    }
+    //why the game is drawn by the rules when the side to move still has moves, if it is:
+    //threefold repetition, the fifty-move rule or too little material left for either side to mate
+    //repetitions are found in the history, so there need to be an allocator and the moves that led here
+    pub fn draw_reason(&self) -> Option<&'static str> {
+        #[cfg(feature = "alloc")]
+        if self.repetitions() >= 3 {
+            return Some("threefold repetition");
+        }
+        if self.halfmove_clock >= 100 {
+            return Some("fifty-move rule");
+        }
+        if self.insufficient_material() {
+            return Some("insufficient material");
+        }
+        None
+    }
+    //how many times the current position has come up, this time included
+    //positions count as the same with the same pieces on the same squares, the same side to move,
+    //the same castling rights and the same en passant captures
+    #[cfg(feature = "alloc")]
+    pub fn repetitions(&self) -> usize {
+        let mut earlier = self.clone();
+        let mut count = 1;
+        //a capture or pawn move can't be taken back over the board, so nothing before one can come back
+        for _ in 0..self.halfmove_clock.min(self.history.len() as u32) {
+            earlier.undo_move();
+            if earlier.current_player == self.current_player && earlier.same_position(self) {
+                count += 1;
+            }
+        }
+        count
+    }
+    #[cfg(feature = "alloc")]
+    fn same_position(&self, other: &Game) -> bool {
+        let squares_match = (0..self.board.rows).all(|row| {
+            (0..self.board.cols).all(|col| {
+                let (ours, theirs) = (self.board.get_piece_at((row, col)), other.board.get_piece_at((row, col)));
+                ours.map(|piece| (piece.piece_type, piece.color)) == theirs.map(|piece| (piece.piece_type, piece.color))
+            })
+        });
+        squares_match && self.castling_rights() == other.castling_rights() && self.en_passant_capture() == other.en_passant_capture()
+    }
+    //the en passant square when the side to move can really take there, a double step alone doesn't change the position
+    #[cfg(feature = "alloc")]
+    fn en_passant_capture(&self) -> Option<(usize, usize)> {
+        let square = self.en_passant?;
+        let row = match self.current_player {
+            Color::White => square.0.checked_sub(1)?,
+            Color::Black => square.0 + 1,
+        };
+        [square.1.checked_sub(1), Some(square.1 + 1)].into_iter().flatten()
+            .filter(|&col| row < self.board.rows && col < self.board.cols)
+            .any(|col| self.is_en_passant_move((row, col), square) && self.is_valid_move((row, col), square))
+            .then_some(square)
+    }
+    //neither side can mate: bare kings, or a single knight or bishop besides them
+    //in antichess losing the pieces is the aim, so there is always enough
+    pub fn insufficient_material(&self) -> bool {
+        if self.variant == Variant::Antichess {
+            return false;
+        }
+        let mut minors = 0;
+        for row in 0..self.board.rows {
+            for col in 0..self.board.cols {
+                match self.board.get_piece_at((row, col)).map(|piece| piece.piece_type) {
+                    None | Some(PieceType::King) => {}
+                    Some(PieceType::Knight) | Some(PieceType::Bishop) => minors += 1,
+                    Some(_) => return false,
+                }
+            }
+        }
+        minors <= 1
+    }
     //prevents pieces from moving off the board
     fn is_within_bounds(&self, position: (usize, usize)) -> bool {
-        position.0 < BOARD_SIZE && position.1 < BOARD_SIZE
+        position.0 < self.board.rows && position.1 < self.board.cols
     }
     pub fn is_in_check(&self) -> bool {
+        //the king is just another piece in antichess so there is no check
+        if self.variant == Variant::Antichess {
+            return false;
+        }
         // Find the king's position
         let king_pos = self.find_king(self.current_player);
         if king_pos.is_none() {
@@ -69,8 +426,8 @@ impl Game {
         let king_pos = king_pos.unwrap();
 
         // Check if any of the opponent's pieces can move to the king's position
-        for i in 0..BOARD_SIZE {
-            for j in 0..BOARD_SIZE {
+        for i in 0..self.board.rows {
+            for j in 0..self.board.cols {
                 if let Some(piece) = self.board.get_piece_at((i, j)) {
                     if piece.color != self.current_player {
                         let valid_moves = piece.valid_moves((i, j), &self.board);
@@ -86,8 +443,8 @@ impl Game {
     }
     // Helper function to find the king's position for check and checkmate
     fn find_king(&self, color: Color) -> Option<(usize, usize)> {
-        for i in 0..BOARD_SIZE {
-            for j in 0..BOARD_SIZE {
+        for i in 0..self.board.rows {
+            for j in 0..self.board.cols {
                 if let Some(piece) = self.board.get_piece_at((i, j)) {
                     if piece.color == color && piece.piece_type == PieceType::King {
                         return Some((i, j));
@@ -97,73 +454,110 @@ impl Game {
         }
         None
     }
+    //in check with no legal move, found by the same move generator as everything else so an
+    //en passant capture of the checking pawn counts as a way out
     pub fn is_in_checkmate(&self) -> bool {
-        if !self.is_in_check() {
-            return false;
+        self.is_in_check() && !self.has_legal_move()
+    }
+    //plays an already validated move and works out the state of the game after it
+    //promotion is only used when a pawn reaches the last row
+    pub fn apply_move(&mut self, from: (usize, usize), to: (usize, usize), promotion: PieceType) {
+        self.play(from, to, promotion);
+        self.update_state();
+    }
+    //the move itself: special moves, the piece, promotion and the turn switch
+    fn play(&mut self, from: (usize, usize), to: (usize, usize), promotion: PieceType) {
+        let moved = self.board.get_piece_at(from);
+        // a pawn move (en passant included) or a capture resets the fifty-move count
+        let zeroing = matches!(moved, Some(piece) if piece.piece_type == PieceType::Pawn) || self.board.get_piece_at(to).is_some();
+        self.halfmove_clock = if zeroing { 0 } else { self.halfmove_clock + 1 };
+        if self.current_player == Color::Black {
+            self.fullmove_number += 1;
         }
-
-        // Check if there are any legal moves left that can get the king out of check
-        for i in 0..BOARD_SIZE {
-            for j in 0..BOARD_SIZE {
-                if let Some(piece) = self.board.get_piece_at((i, j)) {
-                    if piece.color == self.current_player {
-                        let valid_moves = piece.valid_moves((i, j), &self.board);
-                        for &move_pos in &valid_moves {
-                            //initialize a temp board with all the pieces in the same place to see if there are moves to save the king
-                            // Make the move and check if the king is still in check
-                            let mut temp_board = self.board.clone();
-                            temp_board.make_move((i, j), move_pos);
-                            let temp_game = Game { board: temp_board, current_player: self.current_player, state:self.state};
-                            if !temp_game.is_in_check() {
-                                return false;
-                            }
-                        }
-                    }
-                }
+        #[cfg(feature = "alloc")]
+        self.record_move(from, to, promotion);
+        self.handle_special_moves(from, to);
+        // castling moves the king itself so this does nothing in that case
+        self.board.move_piece(from, to);
+        self.handle_pawn_promotion(to, promotion);
+        // a double step lets the opponent take en passant on the square that was skipped
+        self.en_passant = match moved {
+            Some(piece) if piece.piece_type == PieceType::Pawn && from.0.abs_diff(to.0) == 2 => Some(((from.0 + to.0) / 2, from.1)),
+            _ => None,
+        };
+        self.switch_player();
+    }
+    //adds the move about to be played to the history, before the board changes
+    #[cfg(feature = "alloc")]
+    fn record_move(&mut self, from: (usize, usize), to: (usize, usize), promotion: PieceType) {
+        if let Some(piece_moved) = self.board.get_piece_at(from) {
+            let mut record = Move::new(from, to, piece_moved, self.board.get_piece_at(to));
+            record.is_castling_move = piece_moved.piece_type == PieceType::King && from.1.abs_diff(to.1) > 1;
+            record.is_en_passant_move = self.is_en_passant_move(from, to);
+            if record.is_en_passant_move {
+                record.piece_captured = self.board.get_piece_at((from.0, to.1));
             }
+            if self.is_promotion_move(from, to) {
+                record.promoted_to = Some(promotion);
+            }
+            self.history.push(record);
         }
-
-        true
     }
     //pawn promotion and castling were the only special moves implemented but these moves can be made the same way as regular moves
+    //has to run before the piece leaves 'from'
     pub fn handle_special_moves(&mut self, from: (usize, usize), to: (usize, usize)) {
         if let Some(piece) = self.board.get_piece_at(from) {
-            match piece.piece_type {
-                PieceType::King => self.handle_castling(from, to),
-                PieceType::Pawn => {
-                    //self.handle_en_passant(from, to);
-                    self.handle_pawn_promotion(to);
-                }
-                _ => {}
+            if piece.piece_type == PieceType::King && from.1.abs_diff(to.1) > 1 {
+                self.handle_castling(from, to);
+            }
+            if self.is_en_passant_move(from, to) {
+                self.handle_en_passant(from, to);
             }
         }
     }
+    //the captured pawn is beside the moving pawn rather than on the square it moves to
+    fn handle_en_passant(&mut self, from: (usize, usize), to: (usize, usize)) {
+        self.board.remove_piece_at((from.0, to.1));
+    }
     fn handle_castling(&mut self, from: (usize, usize), to: (usize, usize)) {
-        let y = from.1;
-        let (rook_from, rook_to, king_to) = if to.0 > from.0 {
+        let row = from.0;
+        let (rook_from, rook_to, king_to) = if to.1 > from.1 {
             // King-side castling
-            ((BOARD_SIZE - 1, y), (from.0 + 1, y), (to.0, y))
+            ((row, self.board.cols - 1), (row, to.1 - 1), to)
         } else {
             // Queen-side castling
-            ((0, y), (from.0 - 1, y), (to.0, y))
+            ((row, 0), (row, to.1 + 1), to)
         };
         if !self.can_castle(from, to) {
             return;
         }
 
-        // Move the king
-        if let Some(king) = self.board.get_piece_at(from) {
-            self.board.set_piece_at(king_to, king);
-            self.board.remove_piece_at(from);
-        }
-
-        // Move the rook
-        if let Some(rook) = self.board.get_piece_at(rook_from) {
-            self.board.set_piece_at(rook_to, rook);
-            self.board.remove_piece_at(rook_from);
-        }
+        // Move the king and the rook, both lose their castling rights
+        self.board.move_piece(from, king_to);
+        self.board.move_piece(rook_from, rook_to);
 
     
+    }
+    //which castles are still possible later in the game: an unmoved king on its back rank and an unmoved rook in the corner
+    //order is White king-side, White queen-side, Black king-side, Black queen-side
+    pub fn castling_rights(&self) -> [bool; 4] {
+        let mut rights = [false; 4];
+        if self.variant == Variant::Antichess {
+            return rights;
+        }
+        for (i, (color, row)) in [(Color::White, 0), (Color::Black, self.board.rows - 1)].into_iter().enumerate() {
+            let king_home = (0..self.board.cols).any(|col| {
+                self.board.get_piece_at((row, col)).is_some_and(|piece| piece.piece_type == PieceType::King && piece.color == color && !piece.has_moved)
+            });
+            if !king_home {
+                continue;
+            }
+            for (j, col) in [self.board.cols - 1, 0].into_iter().enumerate() {
+                rights[i * 2 + j] = self.board.get_piece_at((row, col))
+                    .is_some_and(|piece| piece.piece_type == PieceType::Rook && piece.color == color && !piece.has_moved);
+            }
+        }
+        rights
     }
     //helper function for handle_castling 
     fn can_castle(&self, from: (usize, usize), to: (usize, usize)) -> bool {
@@ -175,20 +569,17 @@ impl Game {
         } else {
             return false;
         }
+        if to.0 != from.0 || from.1.abs_diff(to.1) < 2 {
+            return false;
+        }
         
         // Determine if it's king-side or queen-side castling
-        let king_side = to.0 > from.0;
-        let y = from.1;
-        let (rook_x, clear_path_start, clear_path_end) = if king_side {
-            // King-side castling
-            (7, from.0 + 1, 6)
-        } else {
-            // Queen-side castling
-            (0, 3, from.0 - 1)
-        };
+        let king_side = to.1 > from.1;
+        let row = from.0;
+        let rook_col = if king_side { self.board.cols - 1 } else { 0 };
 
         // Check if the rook has moved
-        if let Some(rook) = self.board.get_piece_at((rook_x, y)) {
+        if let Some(rook) = self.board.get_piece_at((row, rook_col)) {
             if rook.piece_type != PieceType::Rook || rook.has_moved {
                 return false;
             }
@@ -197,21 +588,23 @@ impl Game {
         }
 
         // Check if the path between the king and rook is clear
-        for x in clear_path_start..=clear_path_end {
-            if self.board.get_piece_at((x, y)).is_some() {
+        let (clear_path_start, clear_path_end) = (from.1.min(rook_col) + 1, from.1.max(rook_col) - 1);
+        for col in clear_path_start..=clear_path_end {
+            if self.board.get_piece_at((row, col)).is_some() {
                 return false;
             }
         }
-        let path: Vec<(usize, usize)> = (clear_path_start..=clear_path_end).map(|x| (x, y)).collect();
+        // the squares the king walks over, including where it lands
+        let path = (from.1.min(to.1)..=from.1.max(to.1)).filter(|&col| col != from.1).map(|col| (row, col));
         // Check if the king is in check or the path is under attack
-        if self.is_in_check() || self.is_path_under_attack(&path, self.current_player) {
+        if self.is_in_check() || self.is_path_under_attack(path, self.current_player) {
             return false;
         }
 
         true
     }
-    fn is_path_under_attack(&self, path: &[(usize, usize)], player_color: Color) -> bool {
-        for &square in path {
+    fn is_path_under_attack(&self, path: impl Iterator<Item = (usize, usize)>, player_color: Color) -> bool {
+        for square in path {
             if self.is_square_under_attack(square, player_color) {
                 return true;
             }
@@ -219,27 +612,105 @@ impl Game {
         false
     }
 
-    fn is_square_under_attack(&self, square: (usize, usize), player_color: Color) -> bool {
+    pub fn is_square_under_attack(&self, square: (usize, usize), player_color: Color) -> bool {
         // Check if any of the opponent's pieces can move to 'square'
-        for x in 0..BOARD_SIZE {
-            for y in 0..BOARD_SIZE {
+        for x in 0..self.board.rows {
+            for y in 0..self.board.cols {
                 if let Some(piece) = self.board.get_piece_at((x, y)) {
-                    if piece.color != player_color {
-                        let potential_moves = piece.valid_moves((x, y), &self.board);
-                        if potential_moves.contains(&square) {
-                            return true;
-                        }
+                    if piece.color != player_color && Game::attacks(&piece, (x, y), square, &self.board) {
+                        return true;
                     }
                 }
             }
         }
         false
     }
-    fn handle_pawn_promotion(&mut self, to: (usize, usize)) {
+    //pawns only attack diagonally forward, even when nothing is on the square yet
+    //every other piece attacks the squares it could move to
+    pub fn attacks(piece: &Piece, from: (usize, usize), square: (usize, usize), board: &Board) -> bool {
+        if piece.piece_type == PieceType::Pawn {
+            let forward = match piece.color {
+                Color::White => from.0 + 1 == square.0,
+                Color::Black => square.0 + 1 == from.0,
+            };
+            return forward && from.1.abs_diff(square.1) == 1;
+        }
+        if board.get_piece_at(square).is_some_and(|target| target.color == piece.color) {
+            // valid_moves never lands on a friendly piece, so look at the square as if an enemy were on it
+            let mut board = board.clone();
+            board.set_piece_at(square, Piece { piece_type: PieceType::Pawn, color: piece.color.opponent(), has_moved: true });
+            return piece.valid_moves(from, &board).contains(&square);
+        }
+        piece.valid_moves(from, board).contains(&square)
+    }
+    #[cfg(feature = "alloc")]
+    pub fn last_move(&self) -> Option<&Move> {
+        self.history.last()
+    }
+    //takes the last move in the history back and returns it, the state isn't worked out again
+    //an en passant capture that was possible before the first move isn't recorded anywhere, so that is lost,
+    //and so is the halfmove clock from before the first capture or pawn move, which is taken as 0
+    #[cfg(feature = "alloc")]
+    pub fn undo_move(&mut self) -> Option<Move> {
+        let mv = self.history.pop()?;
+        let board = &mut self.board;
+        board.remove_piece_at(mv.to);
+        board.set_piece_at(mv.from, mv.piece_moved);
+        match mv.piece_captured {
+            Some(captured) if mv.is_en_passant_move => board.set_piece_at((mv.from.0, mv.to.1), captured),
+            Some(captured) => board.set_piece_at(mv.to, captured),
+            None => {}
+        }
+        // the rook goes back to its corner, the same squares handle_castling moves it between
+        if mv.is_castling_move {
+            let row = mv.from.0;
+            let (rook_from, rook_to) = if mv.to.1 > mv.from.1 { ((row, board.cols - 1), (row, mv.to.1 - 1)) } else { ((row, 0), (row, mv.to.1 + 1)) };
+            if let Some(mut rook) = board.get_piece_at(rook_to) {
+                rook.has_moved = false;
+                board.remove_piece_at(rook_to);
+                board.set_piece_at(rook_from, rook);
+            }
+        }
+        self.current_player = mv.piece_moved.color;
+        self.en_passant = self.history.last().and_then(|previous| {
+            let double_step = previous.piece_moved.piece_type == PieceType::Pawn && previous.from.0.abs_diff(previous.to.0) == 2;
+            double_step.then_some(((previous.from.0 + previous.to.0) / 2, previous.from.1))
+        });
+        if mv.piece_moved.color == Color::Black {
+            self.fullmove_number = self.fullmove_number.saturating_sub(1).max(1);
+        }
+        let zeroing = |mv: &Move| mv.piece_moved.piece_type == PieceType::Pawn || mv.piece_captured.is_some();
+        self.halfmove_clock = if zeroing(&mv) {
+            self.history.iter().rev().take_while(|earlier| !zeroing(earlier)).count() as u32
+        } else {
+            self.halfmove_clock.saturating_sub(1)
+        };
+        Some(mv)
+    }
+    //checks if moving 'from' to 'to' takes a pawn to the last row, so the player can be asked what to promote to
+    pub fn is_promotion_move(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        match self.board.get_piece_at(from) {
+            Some(piece) if piece.piece_type == PieceType::Pawn => match piece.color {
+                Color::White => to.0 == self.board.rows - 1,
+                Color::Black => to.0 == 0,
+            },
+            _ => false,
+        }
+    }
+    //pawns can't stay pawns, only antichess allows promoting to a king
+    //and fairy pieces are only available in the variants that start with them
+    pub fn can_promote_to(&self, piece_type: PieceType) -> bool {
+        match piece_type {
+            PieceType::Pawn | PieceType::Amazon => false,
+            PieceType::King => self.variant == Variant::Antichess,
+            PieceType::Archbishop | PieceType::Chancellor => matches!(self.variant, Variant::Capablanca | Variant::Grand),
+            _ => true,
+        }
+    }
+    fn handle_pawn_promotion(&mut self, to: (usize, usize), promotion: PieceType) {
         if self.is_pawn_promotion(to) {
-            // Promote the pawn to Queen.
             let promoted_piece = Piece {
-                piece_type: PieceType::Queen, 
+                piece_type: promotion,
                 color: self.current_player,
                 has_moved: true
             };
@@ -252,8 +723,8 @@ impl Game {
             if piece.piece_type == PieceType::Pawn {
                 // Check if the pawn has reached the opposite end of the board
                 match piece.color {
-                    Color::White => to.1 == BOARD_SIZE - 1, // White pawns promote at the last row
-                    Color::Black => to.1 == 0,              // Black pawns promote at the first row
+                    Color::White => to.0 == self.board.rows - 1, // White pawns promote at the last row
+                    Color::Black => to.0 == 0,              // Black pawns promote at the first row
                 }
             } else {
                 false
@@ -263,4 +734,100 @@ impl Game {
         }
     }
   
-}
\ No newline at end of file
+}
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::{Game, GameState, Variant};
+    use crate::fen;
+    use crate::piece::{Color, PieceType};
+
+    #[test]
+    fn en_passant_capture_escapes_check() {
+        //g7-g5 checks the king on h4 and f5xg6 is the only reply
+        let game = fen::parse("k5r1/8/8/5Pp1/5n1K/8/4b2b/8 w - g6 0 1").unwrap();
+        assert!(game.is_in_check());
+        assert_eq!(game.legal_moves(), vec![((4, 5), (5, 6))]);
+        assert!(!game.is_in_checkmate());
+    }
+
+    #[test]
+    fn playing_a_move_works_out_the_state() {
+        let mut game = fen::parse("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1").unwrap();
+        game.apply_move((0, 2), (6, 2), PieceType::Queen);
+        assert_eq!(game.state, GameState::Stalemate);
+        let mut game = fen::parse("k7/8/1K6/8/8/8/8/3Q4 w - - 0 1").unwrap();
+        let mate = game.generate_moves().into_iter().find(|mv| mv.to == (7, 3)).unwrap();
+        game.make_move(&mate);
+        assert_eq!(game.state, GameState::Checkmate);
+    }
+
+    #[test]
+    fn draws_by_the_rules() {
+        //the knights go out and back twice, the start position comes up for the third time
+        let mut game = Game::new();
+        for (index, (from, to)) in [((0, 6), (2, 5)), ((7, 6), (5, 5)), ((2, 5), (0, 6)), ((5, 5), (7, 6))].iter().cycle().take(8).enumerate() {
+            assert_eq!(game.state, GameState::Ongoing, "after {} moves", index);
+            game.apply_move(*from, *to, PieceType::Queen);
+        }
+        assert_eq!(game.repetitions(), 3);
+        assert_eq!((game.state, game.draw_reason()), (GameState::Draw, Some("threefold repetition")));
+        //the hundredth quiet ply
+        let mut game = fen::parse("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        game.apply_move((0, 0), (1, 0), PieceType::Queen);
+        assert_eq!((game.state, game.draw_reason()), (GameState::Draw, Some("fifty-move rule")));
+        //taking the last rook leaves bare kings
+        let mut game = fen::parse("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1").unwrap();
+        game.apply_move((0, 4), (1, 4), PieceType::Queen);
+        assert_eq!((game.state, game.draw_reason()), (GameState::Draw, Some("insufficient material")));
+        //a lone bishop can't mate but a pawn can still promote
+        assert!(fen::parse("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap().insufficient_material());
+        assert!(!fen::parse("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap().insufficient_material());
+    }
+
+    #[test]
+    fn a_double_step_nobody_can_take_doesnt_change_the_position() {
+        //after e2-e4 Black has no pawn to take en passant, so Nf6 Nf3 Ng8 Ng1 goes back to the same position
+        let mut game = fen::parse("4k1n1/8/8/8/8/8/4P3/4K1N1 w - - 0 1").unwrap();
+        for (from, to) in [((1, 4), (3, 4)), ((7, 6), (5, 5)), ((0, 6), (2, 5)), ((5, 5), (7, 6)), ((2, 5), (0, 6))] {
+            game.apply_move(from, to, PieceType::Queen);
+        }
+        assert_eq!(game.repetitions(), 2);
+    }
+
+    #[test]
+    fn castling_uses_up_the_rights() {
+        let mut game = fen::parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        game.apply_move((0, 4), (0, 6), PieceType::Queen);
+        assert_eq!(fen::to_fen(&game), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+        game.apply_move((7, 0), (7, 1), PieceType::Queen);
+        //the king on g1 can't castle again with the rook on a1
+        assert!(game.legal_moves().iter().all(|&(from, to)| from != (0, 6) || from.1.abs_diff(to.1) <= 1));
+        assert_eq!(fen::to_fen(&game), "1r2k2r/8/8/8/8/8/8/R4RK1 w k - 2 2");
+    }
+
+    #[test]
+    fn antichess_captures_are_forced() {
+        //the rook could go anywhere, but the king has a pawn to take
+        let game = fen::parse_for(Variant::Antichess, "4k3/8/8/8/8/8/3p4/R3K3 w - - 0 1").unwrap();
+        assert_eq!(game.legal_moves(), vec![((0, 4), (1, 3))]);
+        //with nothing to take any move goes, even one next to the other king
+        let game = fen::parse_for(Variant::Antichess, "8/8/8/8/8/3k4/8/4K3 w - - 0 1").unwrap();
+        assert!(!game.is_in_check());
+        assert!(game.legal_moves().contains(&((0, 4), (1, 3))));
+        assert!(!game.insufficient_material());
+    }
+
+    #[test]
+    fn antichess_is_won_by_losing_every_piece() {
+        //the king is just another piece, taking it leaves Black with nothing, so Black wins
+        let mut game = fen::parse_for(Variant::Antichess, "8/8/8/8/8/8/3k4/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.antichess_winner(), None);
+        game.apply_move((0, 4), (1, 3), PieceType::Queen);
+        assert_eq!(game.state, GameState::Stalemate);
+        assert_eq!(game.antichess_winner(), Some(Color::Black));
+        //a pawn may become a king
+        let game = fen::parse_for(Variant::Antichess, "8/4P3/8/8/8/8/8/k7 w - - 0 1").unwrap();
+        assert!(game.can_promote_to(PieceType::King));
+        assert!(!Game::new().can_promote_to(PieceType::King));
+    }
+}
@@ -0,0 +1,47 @@
+//the rules of the game and the search, shared by the program in main.rs and the bindings for other languages
+//the rules build without the standard library for boards driven by a microcontroller:
+//  default features: everything, `std` turns on `alloc`
+//  `alloc` only: the rules, the move history, move lists, FEN and SAN
+//  neither: pieces, the board and move legality, moves are generated into a fixed-size MoveList
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod board;
+pub mod chess_move;
+#[cfg(feature = "std")]
+pub mod eval;
+#[cfg(feature = "std")]
+pub mod ffi;
+#[cfg(feature = "alloc")]
+pub mod fen;
+pub mod game;
+pub mod move_list;
+#[cfg(feature = "alloc")]
+pub mod notation;
+#[cfg(feature = "std")]
+pub mod pgn;
+pub mod piece;
+#[cfg(feature = "std")]
+pub mod polyglot;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+pub mod search;
+#[cfg(feature = "std")]
+pub mod see;
+#[cfg(feature = "serde")]
+pub mod serialization;
+#[cfg(feature = "std")]
+pub mod tablebase;
+#[cfg(all(test, feature = "std"))]
+mod tbgen;
+#[cfg(feature = "std")]
+pub mod time_manager;
+#[cfg(feature = "std")]
+pub mod tt;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod zobrist;
@@ -1,43 +1,650 @@
-mod game;
-mod board;
-mod piece;
-mod chess_move;
-use std::io::{self};
+mod engine_match;
+mod network;
+mod tui;
+mod ratings;
+mod server;
+mod tournament;
+mod tune;
+mod uci;
+mod uci_engine;
+//the rules and the search live in the library so the bindings can share them
+use final_proj::{board, chess_move, eval, fen, game, notation, pgn, piece, polyglot, search, tablebase, time_manager, tt};
+#[cfg(feature = "serde")]
+use final_proj::serialization;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
 
 
 
 
 
 fn main() {
-    let mut game = game::Game::new();
+    // pick the rule set from the command line, e.g. `final_proj antichess`
+    // `--plain` keeps the line based mode even on a terminal
+    let args: Vec<String> = env::args().skip(1).collect();
+    // subcommands that don't start a game
+    match args.first().map(|arg| arg.as_str()) {
+        Some("uci") => {
+            uci::run();
+            return;
+        }
+        Some("bench") => {
+            search::bench(args.get(1).and_then(|depth| depth.parse().ok()).unwrap_or(4));
+            return;
+        }
+        // `makebook games.pgn book.bin [--plies N]`
+        Some("makebook") => {
+            let (games, book) = match (args.get(1), args.get(2)) {
+                (Some(games), Some(book)) => (games, book),
+                _ => {
+                    eprintln!("usage: makebook GAMES.pgn BOOK.bin [--plies N]");
+                    std::process::exit(1);
+                }
+            };
+            let plies = option_value(&args, "--plies").and_then(|plies| plies.parse().ok()).unwrap_or(BOOK_PLIES);
+            match polyglot::build(games, book, plies) {
+                Ok(count) => println!("wrote {} entries to {}", count, book),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        // `probe /path/to/syzygy [FEN]`
+        Some("probe") => {
+            if let Err(error) = run_probe(&args) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        // `tune positions.epd [--params start.txt] [--out tuned.txt] [--passes N]`
+        Some("tune") => {
+            if let Err(error) = run_tune(&args) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        // `match --engine cmd=PATH [name=NAME] [arg=ARG] [option.NAME=VALUE] --engine ... [--games N] ...`
+        Some("match") => {
+            if let Err(error) = run_match(&args) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        // `tournament new|add|pair|result|play|rounds|standings FILE ...`
+        Some("tournament") => {
+            if let Err(error) = run_tournament(&args) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        // `ratings record|import|tournament|leaderboard|history FILE ...`
+        Some("ratings") => {
+            if let Err(error) = run_ratings(&args) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        // `server [PORT] [--tc SECONDS+INC]`
+        Some("server") => {
+            if let Err(error) = run_server(&args) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+    let variant = args.iter()
+        .find_map(|name| game::Variant::from_name(name))
+        .unwrap_or(game::Variant::Standard);
+    let plain = args.iter().any(|arg| arg == "--plain");
+    // `--glyphs ascii` for terminals without chess fonts, `--orientation auto` turns the board to the side to move
+    let glyphs = option_value(&args, "--glyphs")
+        .and_then(board::GlyphSet::from_name)
+        .unwrap_or(board::GlyphSet::Unicode);
+    let orientation = match option_value(&args, "--orientation") {
+        Some("black") => board::Orientation::Black,
+        Some("auto") => board::Orientation::SideToMove,
+        _ => board::Orientation::White,
+    };
+    // `--computer black` lets the engine play that side, from `--book FILE` while it knows the opening
+    let mut computer = match computer_opponent(&args) {
+        Ok(computer) => computer,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    let game = game::Game::with_variant(variant);
+    // `serve [PORT] [--color white|black]` hosts a game over TCP, `connect HOST[:PORT]` joins one and plays the game the host set up
+    let (game, mut peer) = match network_peer(&args, game) {
+        Ok(started) => started,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+
+    // the full-screen mode needs a real terminal on both ends, otherwise fall back to plain text
+    // network games always use the plain mode, which waits for the opponent's moves
+    if !plain && peer.is_none() && io::stdin().is_terminal() && io::stdout().is_terminal() && tui::run(game.clone(), glyphs, orientation, computer.as_mut()) {
+        return;
+    }
+    play_plain(game, glyphs, orientation, computer.as_mut(), peer.as_mut());
+}
 
+//opens the connection for `serve` and `connect`, no peer for a game on this machine
+fn network_peer(args: &[String], game: game::Game) -> Result<(game::Game, Option<network::Peer>), String> {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("serve") => {
+            let port = args.get(1).and_then(|port| port.parse().ok()).unwrap_or(network::DEFAULT_PORT);
+            let color = match option_value(args, "--color") {
+                None | Some("white") => piece::Color::White,
+                Some("black") => piece::Color::Black,
+                Some(other) => return Err(format!("--color should be white or black, not '{}'", other)),
+            };
+            let peer = network::Peer::host(port, color, &game)?;
+            println!("You play {}.", color.name());
+            Ok((game, Some(peer)))
+        }
+        Some("connect") => {
+            let address = args.get(1).ok_or("usage: connect HOST[:PORT]")?;
+            let address = if address.contains(':') { address.clone() } else { format!("{}:{}", address, network::DEFAULT_PORT) };
+            let (peer, game) = network::Peer::join(&address)?;
+            println!("You play {}.", peer.color.opponent().name());
+            Ok((game, Some(peer)))
+        }
+        _ => Ok((game, None)),
+    }
+}
+
+//depth the computer opponent searches to once it is out of book
+const COMPUTER_DEPTH: u32 = 4;
+//how many plies of each game go into a book built by makebook
+const BOOK_PLIES: usize = 20;
+
+//reads `--computer white|black`, `--book FILE` and `--book-mode best|random`
+fn computer_opponent(args: &[String]) -> Result<Option<search::Computer>, String> {
+    let color = match option_value(args, "--computer") {
+        None => return Ok(None),
+        Some("white") => piece::Color::White,
+        Some("black") => piece::Color::Black,
+        Some(other) => return Err(format!("--computer should be white or black, not '{}'", other)),
+    };
+    let mut engine = search::Engine::new();
+    if let Some(path) = option_value(args, "--book") {
+        engine.book = Some(polyglot::Book::load(path)?);
+    }
+    if let Some(name) = option_value(args, "--book-mode") {
+        engine.book_mode = polyglot::BookMode::from_name(name).ok_or(format!("--book-mode should be best or random, not '{}'", name))?;
+    }
+    Ok(Some(search::Computer { color, depth: COMPUTER_DEPTH, engine }))
+}
+
+//the argument following a `--name` option
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1)).map(|value| value.as_str())
+}
+
+//looks a position up in the Syzygy tables, the start position when no FEN is given
+fn run_probe(args: &[String]) -> Result<(), String> {
+    let path = args.get(1).ok_or("usage: probe SYZYGY_PATH [FEN]")?;
+    let tables = tablebase::Tablebases::open(path)?;
+    let game = match args.get(2) {
+        Some(_) => fen::parse(&args[2..].join(" "))?,
+        None => game::Game::new(),
+    };
+    println!("{} table files, up to {} pieces", tables.len(), tables.max_pieces);
+    match tables.probe_wdl(&game) {
+        Ok(wdl) => println!("WDL: {:?}", wdl),
+        Err(error) => println!("WDL: {}", error),
+    }
+    match tables.probe_dtz(&game) {
+        Ok(dtz) => println!("DTZ: {}", dtz),
+        Err(error) => println!("DTZ: {}", error),
+    }
+    if let Ok(moves) = tables.best_moves(&game) {
+        let names: Vec<String> = moves.iter().map(|mv| notation::san(&game, mv)).collect();
+        println!("best moves: {}", names.join(" "));
+    }
+    Ok(())
+}
+
+//tunes the evaluation weights from a file of scored positions
+fn run_tune(args: &[String]) -> Result<(), String> {
+    let positions = args.get(1).filter(|arg| !arg.starts_with("--")).ok_or("usage: tune POSITIONS [--params FILE] [--out FILE] [--passes N]")?;
+    let start = match option_value(args, "--params") {
+        Some(path) => eval::EvalParams::load(path)?,
+        None => eval::EvalParams::default(),
+    };
+    let output = option_value(args, "--out").unwrap_or("tuned.txt");
+    let passes = option_value(args, "--passes").and_then(|passes| passes.parse().ok()).unwrap_or(100);
+    tune::run(positions, start, output, passes)
+}
+
+//the words after each `--name`, up to the next option
+fn option_words<'a>(args: &'a [String], name: &str) -> Vec<Vec<&'a str>> {
+    args.iter().enumerate()
+        .filter(|(_, arg)| *arg == name)
+        .map(|(i, _)| args[i + 1..].iter().take_while(|arg| !arg.starts_with("--")).map(|arg| arg.as_str()).collect())
+        .collect()
+}
+
+//a number option, an error if it is there but doesn't parse
+fn number_option<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> Result<T, String> {
+    match option_value(args, name) {
+        Some(value) => value.parse().map_err(|_| format!("{} expects a number, not '{}'", name, value)),
+        None => Ok(default),
+    }
+}
+
+//plays a match between two UCI engines
+fn run_match(args: &[String]) -> Result<(), String> {
+    let engines = option_words(args, "--engine").iter()
+        .map(|words| uci_engine::EngineConfig::parse(words))
+        .collect::<Result<Vec<_>, String>>()?;
+    let engines: [uci_engine::EngineConfig; 2] = engines.try_into()
+        .map_err(|_| "usage: match --engine cmd=PATH [name=NAME] [arg=ARG] [option.NAME=VALUE] --engine ... [--games N] [--concurrency N] [--openings FILE] [--plies N] [--tc SECONDS+INC | --movetime MS | --depth N] [--pgn FILE] [--sprt elo0=0 elo1=5 alpha=0.05 beta=0.05]")?;
+    let time_control = time_control_option(args)?;
+    let openings = match option_value(args, "--openings") {
+        Some(path) => engine_match::load_openings(path, number_option(args, "--plies", usize::MAX)?)?,
+        None => vec![engine_match::Opening { fen: None, moves: Vec::new() }],
+    };
+    let defaults = engine_match::Adjudication::default();
+    let adjudication = engine_match::Adjudication {
+        draw_move_number: number_option(args, "--draw-move", defaults.draw_move_number)?,
+        draw_count: number_option(args, "--draw-count", defaults.draw_count)?,
+        draw_score: number_option(args, "--draw-score", defaults.draw_score)?,
+        resign_count: number_option(args, "--resign-count", defaults.resign_count)?,
+        resign_score: number_option(args, "--resign-score", defaults.resign_score)?,
+        max_moves: number_option(args, "--max-moves", defaults.max_moves)?,
+    };
+    let sprt = match option_words(args, "--sprt").first() {
+        Some(words) => Some(engine_match::Sprt::parse(words)?),
+        None => None,
+    };
+    let config = engine_match::MatchConfig {
+        engines,
+        games: number_option(args, "--games", MATCH_GAMES)?,
+        concurrency: number_option(args, "--concurrency", 1)?,
+        openings,
+        time_control,
+        adjudication,
+        pgn: option_value(args, "--pgn").map(str::to_string),
+        sprt,
+    };
+    engine_match::run(&config).map(|_| ())
+}
+
+//`--tc SECONDS+INC`, `--movetime MS` or `--depth N`, a fixed depth when none is given
+fn time_control_option(args: &[String]) -> Result<engine_match::TimeControl, String> {
+    if let Some(text) = option_value(args, "--tc") {
+        engine_match::TimeControl::parse_clock(text)
+    } else if option_value(args, "--movetime").is_some() {
+        Ok(engine_match::TimeControl::MoveTime(std::time::Duration::from_millis(number_option(args, "--movetime", 0)?)))
+    } else {
+        Ok(engine_match::TimeControl::Depth(number_option(args, "--depth", MATCH_DEPTH)?))
+    }
+}
+
+//serves games over HTTP, `--tc` gives new games a clock unless they ask for their own
+fn run_server(args: &[String]) -> Result<(), String> {
+    let port = args.get(1).and_then(|port| port.parse().ok()).unwrap_or(server::DEFAULT_PORT);
+    let time_control = option_value(args, "--tc").map(engine_match::TimeControl::parse_clock).transpose()?;
+    server::run(port, time_control)
+}
+
+//match defaults when no time control or game count is given
+const MATCH_DEPTH: u32 = 4;
+const MATCH_GAMES: usize = 100;
+
+//rounds a Swiss tournament runs for unless `--rounds` says otherwise
+const SWISS_ROUNDS: usize = 5;
+
+const TOURNAMENT_USAGE: &str = "usage: tournament new FILE roundrobin|swiss [--rounds N] [--name NAME]
+       tournament add FILE NAME [--rating N] [--engine cmd=PATH [arg=ARG] [option.NAME=VALUE]]
+       tournament pair FILE
+       tournament result FILE ROUND BOARD 1-0|0-1|1/2-1/2|*
+       tournament play FILE [--tc SECONDS+INC | --movetime MS | --depth N] [--pgn FILE]
+       tournament rounds FILE
+       tournament standings FILE";
+
+//runs one tournament command, the tournament lives in FILE between commands
+fn run_tournament(args: &[String]) -> Result<(), String> {
+    let (command, path) = match (args.get(1), args.get(2)) {
+        (Some(command), Some(path)) => (command.as_str(), path.as_str()),
+        _ => return Err(TOURNAMENT_USAGE.to_string()),
+    };
+    if command == "new" {
+        let format = args.get(3).and_then(|name| tournament::Format::from_name(name)).ok_or(TOURNAMENT_USAGE)?;
+        let name = option_value(args, "--name").unwrap_or(path);
+        return tournament::Tournament::new(name, format, number_option(args, "--rounds", SWISS_ROUNDS)?).save(path);
+    }
+    let mut tournament = tournament::Tournament::load(path)?;
+    match command {
+        "add" => {
+            let name = args.get(3).filter(|name| !name.starts_with("--")).ok_or(TOURNAMENT_USAGE)?;
+            let engine = match option_words(args, "--engine").first() {
+                Some(words) => Some(uci_engine::EngineConfig { name: name.clone(), ..uci_engine::EngineConfig::parse(words)? }),
+                None => None,
+            };
+            tournament.add_player(tournament::Player { name: name.clone(), rating: number_option(args, "--rating", 0)?, engine })?;
+        }
+        "pair" => {
+            tournament.pair_next_round()?;
+            print_round(&tournament, tournament.rounds.len());
+        }
+        "result" => {
+            let number = |at: usize| args.get(at).and_then(|value| value.parse().ok()).ok_or(TOURNAMENT_USAGE);
+            let result = args.get(5).ok_or(TOURNAMENT_USAGE)?;
+            tournament.set_result(number(3)?, number(4)?, result)?;
+        }
+        "play" => play_tournament_round(&mut tournament, path, args)?,
+        "rounds" => (1..=tournament.rounds.len()).for_each(|round| print_round(&tournament, round)),
+        "standings" => print_standings(&tournament),
+        _ => return Err(TOURNAMENT_USAGE.to_string()),
+    }
+    tournament.save(path)
+}
+
+//plays the unfinished engine-vs-engine games of the current round, saving after each one
+fn play_tournament_round(tournament: &mut tournament::Tournament, path: &str, args: &[String]) -> Result<(), String> {
+    let time_control = time_control_option(args)?;
+    let number = tournament.rounds.len();
+    let round = tournament.rounds.last().ok_or("no round has been paired yet")?.clone();
+    for (board, pairing) in round.pairings.iter().enumerate().filter(|(_, pairing)| !pairing.is_finished()) {
+        let (white, black) = (&tournament.players[pairing.white], &tournament.players[pairing.black]);
+        let (Some(white_engine), Some(black_engine)) = (&white.engine, &black.engine) else {
+            println!("Board {}: {} - {} needs a result entered by hand", board + 1, white.name, black.name);
+            continue;
+        };
+        let game = engine_match::play_engines(white_engine, black_engine, time_control, engine_match::Adjudication::default(), &tournament.name, number)?;
+        println!("Board {}: {} - {} {}", board + 1, white.name, black.name, game.result);
+        if let Some(pgn) = option_value(args, "--pgn") {
+            let mut file = std::fs::File::options().create(true).append(true).open(pgn).map_err(|error| format!("{}: {}", pgn, error))?;
+            io::Write::write_all(&mut file, game.to_pgn().as_bytes()).map_err(|error| format!("{}: {}", pgn, error))?;
+        }
+        tournament.set_result(number, board + 1, &game.result)?;
+        tournament.save(path)?;
+    }
+    Ok(())
+}
+
+fn print_round(tournament: &tournament::Tournament, number: usize) {
+    let round = &tournament.rounds[number - 1];
+    println!("Round {}", number);
+    for (board, pairing) in round.pairings.iter().enumerate() {
+        println!("{:>3}. {} - {}  {}", board + 1, tournament.players[pairing.white].name, tournament.players[pairing.black].name, pairing.result);
+    }
+    if let Some(bye) = round.bye {
+        println!("     bye: {}", tournament.players[bye].name);
+    }
+}
+
+fn print_standings(tournament: &tournament::Tournament) {
+    println!("{} ({}, {} of {} rounds)", tournament.name, tournament.format.name(), tournament.rounds.len(), tournament.total_rounds());
+    println!("{:>4}  {:<24} {:>6} {:>6} {:>9} {:>6}", "rank", "player", "points", "games", "Buchholz", "SB");
+    for (rank, standing) in tournament.standings().iter().enumerate() {
+        println!(
+            "{:>4}  {:<24} {:>6.1} {:>6} {:>9.1} {:>6.2}",
+            rank + 1, tournament.players[standing.player].name, standing.points, standing.games, standing.buchholz, standing.sonneborn_berger
+        );
+    }
+}
+
+const RATINGS_USAGE: &str = "usage: ratings record FILE WHITE BLACK 1-0|0-1|1/2-1/2
+       ratings import FILE GAMES.pgn
+       ratings tournament FILE TOURNAMENT_FILE
+       ratings leaderboard FILE [--by elo|glicko]
+       ratings history FILE NAME";
+
+//keeps Elo and Glicko-2 ratings from the games recorded in FILE
+fn run_ratings(args: &[String]) -> Result<(), String> {
+    let (command, path) = match (args.get(1), args.get(2)) {
+        (Some(command), Some(path)) => (command.as_str(), path.as_str()),
+        _ => return Err(RATINGS_USAGE.to_string()),
+    };
+    let mut list = ratings::RatingList::load(path)?;
+    match command {
+        "record" => {
+            let (white, black, result) = match (args.get(3), args.get(4), args.get(5)) {
+                (Some(white), Some(black), Some(result)) => (white, black, result),
+                _ => return Err(RATINGS_USAGE.to_string()),
+            };
+            let outcome = ratings::Outcome::from_result(result).ok_or(format!("'{}' should be 1-0, 0-1 or 1/2-1/2", result))?;
+            list.record(white, black, outcome)?;
+        }
+        //the result is read from the final position when the game ended on the board,
+        //otherwise (resignation, agreed draw, time) from the Result tag
+        "import" => {
+            let pgn_path = args.get(3).ok_or(RATINGS_USAGE)?;
+            let text = std::fs::read_to_string(pgn_path).map_err(|error| format!("{}: {}", pgn_path, error))?;
+            let mut imported = 0;
+            for (number, game) in pgn::parse(&text).iter().enumerate() {
+                let position = game.final_position().map_err(|error| format!("{}: game {}: {}", pgn_path, number + 1, error))?;
+                let outcome = ratings::Outcome::from_game(&position).or(ratings::Outcome::from_result(&game.result));
+                if let (Some(white), Some(black), Some(outcome)) = (game.tag("White"), game.tag("Black"), outcome) {
+                    list.record(white, black, outcome)?;
+                    imported += 1;
+                }
+            }
+            println!("imported {} games", imported);
+        }
+        "tournament" => {
+            let tournament = tournament::Tournament::load(args.get(3).ok_or(RATINGS_USAGE)?)?;
+            let mut imported = 0;
+            for pairing in tournament.rounds.iter().flat_map(|round| &round.pairings) {
+                if let Some(outcome) = ratings::Outcome::from_result(&pairing.result) {
+                    list.record(&tournament.players[pairing.white].name, &tournament.players[pairing.black].name, outcome)?;
+                    imported += 1;
+                }
+            }
+            println!("imported {} games", imported);
+        }
+        "leaderboard" => {
+            let mut players = list.players();
+            match option_value(args, "--by") {
+                None | Some("elo") => players.sort_by(|a, b| b.elo.total_cmp(&a.elo)),
+                Some("glicko") => players.sort_by(|a, b| b.glicko.rating.total_cmp(&a.glicko.rating)),
+                Some(other) => return Err(format!("--by should be elo or glicko, not '{}'", other)),
+            }
+            println!("{:>4}  {:<24} {:>6} {:>7} {:>5} {:>6} {:>6}", "rank", "player", "Elo", "Glicko", "RD", "games", "score");
+            for (rank, player) in players.iter().enumerate() {
+                println!(
+                    "{:>4}  {:<24} {:>6.0} {:>7.0} {:>5.0} {:>6} {:>6}",
+                    rank + 1, player.name, player.elo, player.glicko.rating, player.glicko.deviation, player.games(),
+                    format!("{}/{}/{}", player.wins, player.draws, player.losses)
+                );
+            }
+            return Ok(());
+        }
+        "history" => {
+            let name = args.get(3).ok_or(RATINGS_USAGE)?;
+            let players = list.players();
+            let player = players.iter().find(|player| &player.name == name).ok_or(format!("no games recorded for {}", name))?;
+            println!("{:>5}  {:<24} {:>6} {:>6} {:>7} {:>5}", "game", "opponent", "points", "Elo", "Glicko", "RD");
+            for point in &player.history {
+                println!("{:>5}  {:<24} {:>6} {:>6.0} {:>7.0} {:>5.0}", point.game, point.opponent, point.points, point.elo, point.glicko.rating, point.glicko.deviation);
+            }
+            return Ok(());
+        }
+        _ => return Err(RATINGS_USAGE.to_string()),
+    }
+    list.save(path)
+}
+
+//what the player typed at a square prompt
+enum PlainInput {
+    Square((usize, usize)),
+    Flip,
+    Glyphs(board::GlyphSet),
+    Moves(Option<(usize, usize)>),
+    Hint,
+    //searches until the player types `stop`, `analyze N` shows the N best moves
+    Analyze(usize),
+    //`fen` shows the position, `fen <FEN>` sets it up
+    Fen(Option<String>),
+    //`json` and `json verbose` show the game as JSON, `json <JSON>` loads either form
+    #[cfg(feature = "serde")]
+    Json(Option<String>),
+}
+
+//depth used by the hint command, deep enough to see simple tactics while staying quick
+const HINT_DEPTH: u32 = 3;
+
+//line based game loop that reprints the board every turn
+//with a peer, their moves come over the network and the local ones are sent to them
+fn play_plain(mut game: game::Game, mut glyphs: board::GlyphSet, mut orientation: board::Orientation, mut computer: Option<&mut search::Computer>, mut peer: Option<&mut network::Peer>) {
+    //squares marked by the last `moves` command, cleared once the board is shown
+    let mut highlights = Vec::new();
     loop {
-        board::Board::print_board(&game.board);
+        board::Board::print_board(&game.board, glyphs, orientation.black_at_bottom(game.current_player), &highlights);
+        highlights.clear();
         
-        // Check if the game has ended
-        if game.state != game::GameState::Ongoing {
-            println!("Game Over!");
-            break;
-        }
         //check if players king is in check
         if game.is_in_check() {
-            println!("{} is in check!", if game.current_player == piece::Color::White { "Black" } else { "White" });
+            println!("{} is in check!", if game.current_player == piece::Color::White { "White" } else { "Black" });
         }
         //check if checkmate and end game
         if game.is_in_checkmate() {
             println!("Checkmate! {} wins!", if game.current_player == piece::Color::White { "Black" } else { "White" });
             break;  // Exit the loop if the game is over
         }
+        //antichess is won by the player who can no longer move
+        if let Some(winner) = game.antichess_winner() {
+            println!("{} has no moves left and wins!", if winner == piece::Color::White { "White" } else { "Black" });
+            break;
+        }
+        if game.state == game::GameState::Stalemate {
+            println!("Stalemate! The game is a draw.");
+            break;
+        }
+        if let Some(reason) = game.draw_reason().filter(|_| game.state == game::GameState::Draw) {
+            println!("Draw by {}!", reason);
+            break;
+        }
+        // Check if the game has ended some other way, a position loaded in check is still being played
+        if matches!(game.state, game::GameState::Checkmate | game::GameState::Draw) {
+            println!("Game Over!");
+            break;
+        }
+        if let Some(peer) = peer.as_mut().filter(|peer| peer.color == game.current_player) {
+            match peer.wait_for_move(&mut game) {
+                Ok(()) => {
+                    if let Some(mv) = game.last_move() {
+                        println!("{} played {}", game.current_player.opponent().name(), mv.notation());
+                    }
+                    continue;
+                }
+                Err(error) => {
+                    println!("{}", error);
+                    break;
+                }
+            }
+        }
+        if let Some(computer) = computer.as_mut().filter(|computer| computer.color == game.current_player) {
+            match computer.engine.choose_move(&game, computer.depth) {
+                Some((mv, from_book)) => {
+                    println!("{} plays {}{}", game.current_player.name(), notation::san(&game, &mv), if from_book { " (book)" } else { "" });
+                    game.make_move(&mv);
+                    continue;
+                }
+                // only when it has no legal move, which the checks above should already have caught
+                None => {
+                    println!("{} has no move to play.", game.current_player.name());
+                    break;
+                }
+            }
+        }
         // Get the current player's move
-        println!("{} Move: ", if game.current_player == piece::Color::White { "Black" } else { "White" });
-        let from = ask_for_move_input("Enter the source square (e.g., 'e2'): ");
-        let to = ask_for_move_input("Enter the destination square (e.g., 'e4'): ");
+        println!("{} Move: ", if game.current_player == piece::Color::White { "White" } else { "Black" });
+        let mut squares = Vec::new();
+        for prompt in ["Enter the source square (e.g., 'e2'): ", "Enter the destination square (e.g., 'e4'): "] {
+            match ask_for_move_input(prompt, &game.board) {
+                PlainInput::Square(position) => squares.push(position),
+                // display commands redraw the board and start the move again
+                PlainInput::Flip => {
+                    orientation = orientation.flipped(game.current_player);
+                    break;
+                }
+                PlainInput::Glyphs(set) => {
+                    glyphs = set;
+                    break;
+                }
+                PlainInput::Moves(square) => {
+                    highlights = show_moves(&game, square);
+                    break;
+                }
+                PlainInput::Hint => {
+                    show_hint(&game);
+                    break;
+                }
+                PlainInput::Analyze(lines) => {
+                    match computer.as_mut() {
+                        Some(computer) => analyze(&game, &mut computer.engine, lines),
+                        None => analyze(&game, &mut search::Engine::new(), lines),
+                    }
+                    break;
+                }
+                PlainInput::Fen(None) => {
+                    println!("{}", fen::to_fen(&game));
+                    break;
+                }
+                //both ends have to play the same game, so a network game can't be set up again
+                PlainInput::Fen(Some(_)) if peer.is_some() => {
+                    println!("The position can't be changed during a network game.");
+                    break;
+                }
+                #[cfg(feature = "serde")]
+                PlainInput::Json(text) => {
+                    match text.as_deref() {
+                        None => println!("{}", serialization::to_json(&game, serialization::Schema::Compact).unwrap_or_else(|error| error)),
+                        Some("verbose") => println!("{}", serialization::to_json(&game, serialization::Schema::Verbose).unwrap_or_else(|error| error)),
+                        Some(_) if peer.is_some() => println!("The position can't be changed during a network game."),
+                        Some(text) => match serialization::from_json(text) {
+                            Ok(loaded) => game = loaded,
+                            Err(error) => println!("{}", error),
+                        },
+                    }
+                    break;
+                }
+                PlainInput::Fen(Some(text)) => {
+                    match fen::parse_for(game.variant, &text) {
+                        Ok(position) => game = position,
+                        Err(error) => println!("{}", error),
+                    }
+                    break;
+                }
+            }
+        }
+        if squares.len() < 2 {
+            continue;
+        }
+        let (from, to) = (squares[0], squares[1]);
 
         // Validate and make the move
         if game.is_valid_move(from, to) {
-            game.board.move_piece(from, to);
-            game.handle_special_moves(from, to);
-            game.switch_player();
+            let promotion = if game.is_promotion_move(from, to) {
+                ask_for_promotion(&game)
+            } else {
+                piece::PieceType::Queen
+            };
+            game.apply_move(from, to, promotion);
+            if let Some(peer) = peer.as_mut() {
+                if let Err(error) = peer.send_last_move(&mut game) {
+                    println!("{}", error);
+                    break;
+                }
+            }
         } else {
             println!("Invalid move. Please try again.");
         }
@@ -45,28 +652,164 @@ fn main() {
 }
 
 //function to handle user input
-fn ask_for_move_input(prompt: &str) -> (usize, usize) {
+//`flip` turns the board around and `glyphs <set>` changes how pieces are drawn
+fn ask_for_move_input(prompt: &str, board: &board::Board) -> PlainInput {
     loop {
         println!("{}", prompt);
         let mut input = String::new();
         io::stdin().read_line(&mut input).expect("Failed to read line");
 
-        if let Some(position) = parse_chess_notation(&input.trim()) {
-            return position;
+        let input = input.trim();
+        if input == "flip" {
+            return PlainInput::Flip;
+        }
+        if input == "hint" {
+            return PlainInput::Hint;
+        }
+        if input == "analyze" {
+            return PlainInput::Analyze(1);
+        }
+        if let Some(lines) = input.strip_prefix("analyze ").and_then(|count| count.trim().parse().ok()) {
+            return PlainInput::Analyze(lines);
+        }
+        if input == "fen" {
+            return PlainInput::Fen(None);
+        }
+        if let Some(text) = input.strip_prefix("fen ") {
+            return PlainInput::Fen(Some(text.trim().to_string()));
+        }
+        #[cfg(feature = "serde")]
+        if input == "json" {
+            return PlainInput::Json(None);
+        }
+        #[cfg(feature = "serde")]
+        if let Some(text) = input.strip_prefix("json ") {
+            return PlainInput::Json(Some(text.trim().to_string()));
+        }
+        if input == "moves" {
+            return PlainInput::Moves(None);
+        }
+        if let Some(square) = input.strip_prefix("moves ").and_then(|name| parse_chess_notation(name.trim(), board)) {
+            return PlainInput::Moves(Some(square));
+        }
+        if let Some(set) = input.strip_prefix("glyphs ").and_then(|name| board::GlyphSet::from_name(name.trim())) {
+            return PlainInput::Glyphs(set);
+        }
+        if let Some(position) = parse_chess_notation(input, board) {
+            return PlainInput::Square(position);
         } else {
             println!("Invalid input. Please try again.");
         }
     }
 }
-// function to translate input such as e2 to e4 and convert it to translate to the grid set up
-fn parse_chess_notation(notation: &str) -> Option<(usize, usize)> {
-    if notation.len() == 2 {
-        let col = notation.chars().next().unwrap().to_ascii_lowercase() as usize - 'a' as usize;
-        let row = notation.chars().nth(1).unwrap().to_digit(10).unwrap() as usize - 1;
+//lists the legal moves in SAN, either all of them or just the ones for the piece on 'square'
+//returns the destination squares so they can be highlighted
+fn show_moves(game: &game::Game, square: Option<(usize, usize)>) -> Vec<(usize, usize)> {
+    let moves: Vec<chess_move::Move> = game.generate_moves().into_iter()
+        .filter(|mv| square.is_none_or(|from| mv.from == from))
+        .collect();
+    let names: Vec<String> = moves.iter().map(|mv| notation::san(game, mv)).collect();
+    match square {
+        Some(from) if names.is_empty() => println!("No legal moves for {}.", chess_move::square_name(from)),
+        Some(from) => println!("Legal moves for {}: {}", chess_move::square_name(from), names.join(" ")),
+        None => println!("Legal moves ({}): {}", names.len(), names.join(" ")),
+    }
+    if square.is_some() {
+        moves.iter().map(|mv| mv.to).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+//asks the search for a suggested move
+fn show_hint(game: &game::Game) {
+    let result = search::search(game, HINT_DEPTH);
+    match result.best_move {
+        Some(mv) => println!("Hint: {} (score {:+.2})", notation::san(game, &mv), result.score as f64 / 100.0),
+        None => println!("No legal moves."),
+    }
+}
 
-        if col < 8 && row < 8 {
-            return Some((7 - row, col)); 
+//infinite analysis: the search runs on its own thread and prints every finished depth
+//until `stop` is typed, with the 'lines' best moves ranked and their lines shown in SAN
+fn analyze(game: &game::Game, engine: &mut search::Engine, lines: usize) {
+    println!("Analyzing, type stop to end.");
+    let stop = AtomicBool::new(false);
+    let report = |result: &search::SearchResult| {
+        for (rank, line) in result.lines.iter().enumerate() {
+            let mut position = game.clone();
+            let mut moves = Vec::new();
+            for mv in &line.pv {
+                moves.push(notation::san(&position, mv));
+                position.make_move(mv);
+            }
+            println!("depth {:2}  #{}  score {:>7}  nodes {:>9}  {}", result.depth, rank + 1, score_text(line.score), result.nodes, moves.join(" "));
         }
+    };
+    let control = search::SearchControl { stop: Some(&stop), report: Some(&report), ..Default::default() };
+    let multi_pv = std::mem::replace(&mut engine.multi_pv, lines.clamp(1, search::MAX_MULTI_PV));
+    thread::scope(|scope| {
+        scope.spawn(|| engine.search_controlled(game, search::MAX_DEPTH, &control));
+        loop {
+            let mut input = String::new();
+            //end of input stops the analysis too
+            if io::stdin().read_line(&mut input).unwrap_or(0) == 0 || input.trim() == "stop" {
+                break;
+            }
+        }
+        stop.store(true, Ordering::Relaxed);
+    });
+    engine.multi_pv = multi_pv;
+}
+
+//score from the side to move's point of view in pawns, or the moves until mate
+fn score_text(score: i32) -> String {
+    if score.abs() > search::MATE - 1000 {
+        let moves = (search::MATE - score.abs() + 1) / 2;
+        format!("#{}", if score > 0 { moves } else { -moves })
+    } else {
+        format!("{:+.2}", score as f64 / 100.0)
+    }
+}
+
+//asks which piece a pawn reaching the last row becomes
+fn ask_for_promotion(game: &game::Game) -> piece::PieceType {
+    loop {
+        println!("Promote to (q, r, b, n{}{}): ",
+            if game.can_promote_to(piece::PieceType::Archbishop) { ", a, c" } else { "" },
+            if game.can_promote_to(piece::PieceType::King) { ", k" } else { "" });
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read line");
+
+        let piece_type = match input.trim().to_ascii_lowercase().as_str() {
+            "q" => piece::PieceType::Queen,
+            "r" => piece::PieceType::Rook,
+            "b" => piece::PieceType::Bishop,
+            "n" => piece::PieceType::Knight,
+            "k" => piece::PieceType::King,
+            "a" => piece::PieceType::Archbishop,
+            "c" => piece::PieceType::Chancellor,
+            _ => piece::PieceType::Pawn,
+        };
+        if game.can_promote_to(piece_type) {
+            return piece_type;
+        }
+        println!("Invalid piece. Please try again.");
+    }
+}
+// function to translate input such as e2 to e4 and convert it to translate to the grid set up
+// ranks can have two digits on the larger variant boards, e.g. j10
+fn parse_chess_notation(notation: &str, board: &board::Board) -> Option<(usize, usize)> {
+    let mut chars = notation.chars();
+    let file = chars.next()?.to_ascii_lowercase();
+    if !file.is_ascii_lowercase() {
+        return None;
+    }
+    let col = file as usize - 'a' as usize;
+    let rank: usize = chars.as_str().parse().ok()?;
+
+    if col < board.cols && rank >= 1 && rank <= board.rows {
+        return Some((rank - 1, col));
     }
     None
 }
@@ -0,0 +1,61 @@
+use crate::board::MAX_BOARD_SIZE;
+use core::ops::Deref;
+
+// most squares one piece can reach: an amazon in the middle of the largest board slides along
+// 4 lines of at most MAX_BOARD_SIZE - 1 squares each and has 8 knight jumps,
+// and Game::legal_moves adds two castling squares and the en passant square
+pub const MAX_TARGETS: usize = 4 * (MAX_BOARD_SIZE - 1) + 8 + 3;
+
+// the squares a piece can move to, kept on the stack so move generation never allocates
+// reads like a slice: len, iter, contains and indexing all come from Deref
+#[derive(Clone, Copy)]
+pub struct MoveList {
+    squares: [(usize, usize); MAX_TARGETS],
+    len: usize,
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        MoveList::new()
+    }
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        MoveList { squares: [(0, 0); MAX_TARGETS], len: 0 }
+    }
+    // panics when the list is full, which MAX_TARGETS rules out for any piece on a supported board
+    pub fn push(&mut self, square: (usize, usize)) {
+        self.squares[self.len] = square;
+        self.len += 1;
+    }
+    pub fn as_slice(&self) -> &[(usize, usize)] {
+        &self.squares[..self.len]
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [(usize, usize)];
+
+    fn deref(&self) -> &[(usize, usize)] {
+        self.as_slice()
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = (usize, usize);
+    type IntoIter = core::iter::Take<core::array::IntoIter<(usize, usize), MAX_TARGETS>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.squares.into_iter().take(self.len)
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a (usize, usize);
+    type IntoIter = core::slice::Iter<'a, (usize, usize)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
@@ -0,0 +1,337 @@
+use crate::game::{Game, Variant};
+use crate::notation;
+use crate::piece::Color;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_PORT: u16 = 7878;
+// bumped whenever the messages below change, both ends must agree
+const PROTOCOL_VERSION: u32 = 1;
+// how often and how long a guest tries to get back in after losing the connection
+const RECONNECT_ATTEMPTS: u32 = 30;
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+// the line protocol, one message per line:
+//   HELLO <version>                      sent by both ends when they connect
+//   SYNC <variant> <guest color> <moves> the host's game, every move so far in UCI notation
+//   MOVE <ply> <move>                    a move, ply counts the moves played before it
+//   RESYNC                               the guest asks for a SYNC because it lost track
+//   ERROR <text>                         the last message was rejected
+//   BYE                                  the player left
+// the host's game is the one that counts: the guest replaces its own with every SYNC, and the
+// host answers anything that doesn't fit its game with one
+enum Role {
+    Host(TcpListener),
+    Guest(String),
+}
+
+// the player at the other desk
+pub struct Peer {
+    // the side they play
+    pub color: Color,
+    role: Role,
+    stream: TcpStream,
+    // None once the connection is gone
+    lines: Receiver<Option<String>>,
+}
+
+impl Peer {
+    // waits on 'port' for a guest, who plays the other side of 'game'
+    pub fn host(port: u16, color: Color, game: &Game) -> Result<Peer, String> {
+        let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|error| format!("port {}: {}", port, error))?;
+        println!("Waiting for an opponent on port {}...", port);
+        Peer::host_on(listener, color, game)
+    }
+
+    fn host_on(listener: TcpListener, color: Color, game: &Game) -> Result<Peer, String> {
+        let (stream, lines) = accept(&listener)?;
+        let mut peer = Peer { color: color.opponent(), role: Role::Host(listener), stream, lines };
+        peer.handshake(&mut game.clone())?;
+        Ok(peer)
+    }
+
+    // joins a hosted game at 'address' (host:port), the game comes from the host
+    pub fn join(address: &str) -> Result<(Peer, Game), String> {
+        let (stream, lines) = connect(address)?;
+        let mut peer = Peer { color: Color::White, role: Role::Guest(address.to_string()), stream, lines };
+        let mut game = Game::new();
+        peer.handshake(&mut game)?;
+        Ok((peer, game))
+    }
+
+    fn is_host(&self) -> bool {
+        matches!(self.role, Role::Host(_))
+    }
+
+    // HELLO both ways, then the host sends its game and the guest takes it over
+    fn handshake(&mut self, game: &mut Game) -> Result<(), String> {
+        self.send(&format!("HELLO {}", PROTOCOL_VERSION));
+        match self.lines.recv().ok().flatten() {
+            Some(line) if line.trim() == format!("HELLO {}", PROTOCOL_VERSION) => {}
+            Some(line) => return Err(format!("the other side doesn't speak this protocol: '{}'", line.trim())),
+            None => return Err(String::from("the connection closed during the handshake")),
+        }
+        if self.is_host() {
+            self.send_sync(game);
+            return Ok(());
+        }
+        loop {
+            match self.lines.recv().ok().flatten() {
+                Some(line) if line.starts_with("SYNC ") => return self.apply_sync(&line, game),
+                Some(_) => {}
+                None => return Err(String::from("the connection closed before the game arrived")),
+            }
+        }
+    }
+
+    // writes one message, a failed write shows up as a closed connection on the next read
+    fn send(&mut self, message: &str) {
+        writeln!(self.stream, "{}", message).and_then(|_| self.stream.flush()).ok();
+    }
+
+    fn send_sync(&mut self, game: &Game) {
+        let moves: Vec<String> = game.history.iter().map(|mv| mv.notation()).collect();
+        let guest = if self.color == Color::White { "white" } else { "black" };
+        self.send(format!("SYNC {} {} {}", game.variant.name(), guest, moves.join(" ")).trim_end());
+    }
+
+    // replays the host's moves on a new game, each one checked like a move received on its own
+    fn apply_sync(&mut self, line: &str, game: &mut Game) -> Result<(), String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let variant = words.get(1).and_then(|name| Variant::from_name(name)).ok_or(format!("bad SYNC message '{}'", line))?;
+        // the guest plays the colour the host named, so this end's peer is the other one
+        self.color = match words.get(2).copied() {
+            Some("white") => Color::Black,
+            Some("black") => Color::White,
+            _ => return Err(format!("bad SYNC message '{}'", line)),
+        };
+        let mut synced = Game::with_variant(variant);
+        for text in &words[3..] {
+            if !play_checked(&mut synced, text) {
+                return Err(format!("the host's game has an illegal move {}", text));
+            }
+        }
+        *game = synced;
+        Ok(())
+    }
+
+    // sends the move just played on 'game'
+    // if the connection is gone it is restored first, and a guest then continues from the host's game
+    pub fn send_last_move(&mut self, game: &mut Game) -> Result<(), String> {
+        let Some(mv) = game.history.last() else { return Ok(()) };
+        let (ply, text) = (game.history.len() - 1, mv.notation());
+        let message = format!("MOVE {} {}", ply, text);
+        if writeln!(self.stream, "{}", message).and_then(|_| self.stream.flush()).is_err() {
+            self.reconnect(game)?;
+            // the host's SYNC on reconnecting already has the move, a guest has just been given the
+            // host's game and plays the move again if it still fits
+            if !self.is_host() && game.history.len() == ply && game.current_player != self.color && play_checked(game, &text) {
+                self.send(&message);
+            }
+        }
+        Ok(())
+    }
+
+    // blocks until the opponent's move has arrived, been checked with Game::is_valid_move and been
+    // played on 'game'; also returns after a SYNC has replaced the game, whoever is to move then
+    // Err when the opponent leaves, or can't get back after losing the connection
+    pub fn wait_for_move(&mut self, game: &mut Game) -> Result<(), String> {
+        println!("Waiting for {}...", self.color.name());
+        loop {
+            let line = match self.lines.recv().ok().flatten() {
+                Some(line) => line,
+                None => {
+                    println!("Lost the connection to {}.", self.color.name());
+                    self.reconnect(game)?;
+                    if self.is_host() {
+                        continue;
+                    }
+                    return Ok(());
+                }
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.first().copied() {
+                Some("MOVE") => {
+                    let ply = words.get(1).and_then(|ply| ply.parse::<usize>().ok());
+                    let text = words.get(2).copied().unwrap_or("");
+                    if ply == Some(game.history.len()) && game.current_player == self.color && play_checked(game, text) {
+                        return Ok(());
+                    }
+                    self.send(&format!("ERROR rejected move {} at ply {}", text, game.history.len()));
+                    self.resync(game);
+                }
+                Some("SYNC") if !self.is_host() => return self.apply_sync(&line, game),
+                Some("RESYNC") if self.is_host() => self.send_sync(game),
+                Some("ERROR") => println!("{} rejected a message: {}", self.color.name(), words[1..].join(" ")),
+                Some("BYE") => return Err(format!("{} left the game.", self.color.name())),
+                _ => {}
+            }
+        }
+    }
+
+    // gets both ends back to the host's game
+    fn resync(&mut self, game: &Game) {
+        if self.is_host() {
+            self.send_sync(game);
+        } else {
+            self.send("RESYNC");
+        }
+    }
+
+    // a host waits for the guest to come back, a guest keeps trying to reach the host
+    // either way the handshake then brings both ends back to the host's game
+    fn reconnect(&mut self, game: &mut Game) -> Result<(), String> {
+        let (stream, lines) = match &self.role {
+            Role::Host(listener) => {
+                println!("Waiting for {} to reconnect...", self.color.name());
+                accept(listener)?
+            }
+            Role::Guest(address) => {
+                let address = address.clone();
+                let mut attempt = 1;
+                loop {
+                    match connect(&address) {
+                        Ok(connection) => break connection,
+                        Err(error) if attempt >= RECONNECT_ATTEMPTS => return Err(error),
+                        Err(_) => {
+                            println!("Reconnecting to {} ({}/{})...", address, attempt, RECONNECT_ATTEMPTS);
+                            attempt += 1;
+                            thread::sleep(RECONNECT_DELAY);
+                        }
+                    }
+                }
+            }
+        };
+        self.stream = stream;
+        self.lines = lines;
+        self.handshake(game)?;
+        println!("Reconnected.");
+        Ok(())
+    }
+}
+
+impl Drop for Peer {
+    fn drop(&mut self) {
+        self.send("BYE");
+    }
+}
+
+// plays a move in UCI notation if Game::is_valid_move allows it
+fn play_checked(game: &mut Game, text: &str) -> bool {
+    match notation::parse_uci_move(game, text) {
+        Some(mv) if game.is_valid_move(mv.from, mv.to) => {
+            game.make_move(&mv);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn accept(listener: &TcpListener) -> Result<(TcpStream, Receiver<Option<String>>), String> {
+    let (stream, address) = listener.accept().map_err(|error| error.to_string())?;
+    println!("{} connected.", address);
+    read_lines(stream)
+}
+
+fn connect(address: &str) -> Result<(TcpStream, Receiver<Option<String>>), String> {
+    let stream = TcpStream::connect(address).map_err(|error| format!("{}: {}", address, error))?;
+    read_lines(stream)
+}
+
+// messages are read on their own thread, which sends None once the connection is gone
+fn read_lines(stream: TcpStream) -> Result<(TcpStream, Receiver<Option<String>>), String> {
+    let reader = stream.try_clone().map_err(|error| error.to_string())?;
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else { break };
+            if sender.send(Some(line)).is_err() {
+                return;
+            }
+        }
+        sender.send(None).ok();
+    });
+    Ok((stream, lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the other end of the connection, played by hand: sends 'script' line by line, and after each
+    // line waits for as many lines as the number next to it, returning every line it got
+    fn scripted(stream: TcpStream, script: &[(&str, usize)]) -> Vec<String> {
+        let mut reader = BufReader::new(stream.try_clone().unwrap()).lines();
+        let mut writer = stream;
+        let mut received = Vec::new();
+        for (line, replies) in script {
+            if !line.is_empty() {
+                writeln!(writer, "{}", line).unwrap();
+            }
+            for _ in 0..*replies {
+                received.push(reader.next().unwrap().unwrap());
+            }
+        }
+        received
+    }
+
+    #[test]
+    fn guest_follows_the_hosts_game() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let host = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            scripted(stream, &[
+                ("HELLO 1", 1),
+                ("SYNC standard black e2e4", 1),
+                ("MOVE 2 g1f3", 0),
+                // a move for the wrong ply is turned down and the guest asks for the game again
+                ("MOVE 5 a2a3", 2),
+                ("SYNC standard black e2e4 e7e5 g1f3 b8c6 f1c4", 1),
+            ])
+        });
+        let (mut peer, mut game) = Peer::join(&address).unwrap();
+        assert_eq!(peer.color, Color::White);
+        assert_eq!(game.history.len(), 1);
+        let reply = notation::parse_uci_move(&game, "e7e5").unwrap();
+        game.make_move(&reply);
+        peer.send_last_move(&mut game).unwrap();
+        peer.wait_for_move(&mut game).unwrap();
+        assert_eq!(game.history.last().unwrap().notation(), "g1f3");
+        peer.wait_for_move(&mut game).unwrap();
+        assert_eq!(game.history.len(), 5);
+        assert_eq!(game.current_player, Color::Black);
+        drop(peer);
+        assert_eq!(host.join().unwrap(), ["HELLO 1", "MOVE 1 e7e5", "ERROR rejected move a2a3 at ply 3", "RESYNC", "BYE"]);
+    }
+
+    #[test]
+    fn host_checks_the_guests_moves() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let guest = thread::spawn(move || {
+            let stream = TcpStream::connect(address).unwrap();
+            scripted(stream, &[
+                ("HELLO 1", 2),
+                ("MOVE 0 e2e4", 0),
+                // played again from an old position, the host sends its game back
+                ("MOVE 0 d2d4", 2),
+                ("RESYNC", 1),
+                ("BYE", 0),
+            ])
+        });
+        let mut peer = Peer::host_on(listener, Color::Black, &Game::new()).unwrap();
+        assert_eq!(peer.color, Color::White);
+        let mut game = Game::new();
+        peer.wait_for_move(&mut game).unwrap();
+        assert_eq!(game.history.last().unwrap().notation(), "e2e4");
+        assert_eq!(peer.wait_for_move(&mut game), Err(String::from("White left the game.")));
+        assert_eq!(game.history.len(), 1);
+        assert_eq!(guest.join().unwrap(), [
+            "HELLO 1", "SYNC standard white", "ERROR rejected move d2d4 at ply 1", "SYNC standard white e2e4", "SYNC standard white e2e4",
+        ]);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::chess_move::{square_name, Move};
+use crate::game::Game;
+use crate::piece::PieceType;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+// upper case letter used for a piece in SAN, pawns don't get one
+pub fn piece_letter(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::Pawn => "",
+        PieceType::Knight => "N",
+        PieceType::Bishop => "B",
+        PieceType::Rook => "R",
+        PieceType::Queen => "Q",
+        PieceType::King => "K",
+        PieceType::Archbishop => "A",
+        PieceType::Chancellor => "C",
+        PieceType::Amazon => "Z",
+    }
+}
+
+// standard algebraic notation for a legal move in 'game', e.g. Nbd7, exd6, e8=Q+, O-O-O
+pub fn san(game: &Game, mv: &Move) -> String {
+    let mut notation = san_without_check(game, mv);
+
+    // play the move to see if it gives check or mate
+    let mut after = game.without_history();
+    after.make_move_without_state(mv);
+    if after.is_in_checkmate() {
+        notation.push('#');
+    } else if after.is_in_check() {
+        notation.push('+');
+    }
+    notation
+}
+
+fn san_without_check(game: &Game, mv: &Move) -> String {
+    if mv.is_castling_move {
+        String::from(if mv.to.1 > mv.from.1 { "O-O" } else { "O-O-O" })
+    } else {
+        let piece_type = mv.piece_moved.piece_type;
+        let capture = mv.piece_captured.is_some();
+        let mut notation = String::from(piece_letter(piece_type));
+        if piece_type == PieceType::Pawn {
+            if capture {
+                notation.push_str(&square_name(mv.from)[..1]);
+            }
+        } else {
+            notation.push_str(&disambiguation(game, mv));
+        }
+        if capture {
+            notation.push('x');
+        }
+        notation.push_str(&square_name(mv.to));
+        if let Some(piece_type) = mv.promoted_to {
+            notation.push('=');
+            notation.push_str(piece_letter(piece_type));
+        }
+        notation
+    }
+}
+
+// file, rank or both when another piece of the same type can reach the same square
+fn disambiguation(game: &Game, mv: &Move) -> String {
+    let others: Vec<(usize, usize)> = game.legal_moves().into_iter()
+        .filter(|&(from, to)| to == mv.to && from != mv.from)
+        .filter(|&(from, _)| game.board.get_piece_at(from).is_some_and(|piece| piece.piece_type == mv.piece_moved.piece_type))
+        .map(|(from, _)| from)
+        .collect();
+    if others.is_empty() {
+        return String::new();
+    }
+    let name = square_name(mv.from);
+    let (file, rank) = name.split_at(1);
+    if others.iter().all(|other| other.1 != mv.from.1) {
+        file.to_string()
+    } else if others.iter().all(|other| other.0 != mv.from.0) {
+        rank.to_string()
+    } else {
+        name
+    }
+}
+
+// finds the legal move written in coordinate notation like e2e4 or e7e8q, as used by UCI
+pub fn parse_uci_move(game: &Game, text: &str) -> Option<Move> {
+    let text = text.trim().to_ascii_lowercase();
+    let mut moves = game.generate_moves();
+    // a move to the last rank without a letter is taken as a queen promotion
+    moves.sort_by_key(|mv| mv.promoted_to != Some(PieceType::Queen));
+    moves.into_iter().find(|mv| {
+        let notation = mv.notation();
+        notation == text || (mv.promoted_to.is_some() && notation[..notation.len() - 1] == text)
+    })
+}
+
+// finds the legal move written in SAN, as found in PGN files
+// check marks and annotations are ignored, and 0-0 or a promotion without '=' are accepted too
+pub fn parse_san(game: &Game, text: &str) -> Option<Move> {
+    let mut text = text.trim_end_matches(['+', '#', '!', '?']).replace('=', "");
+    if text.starts_with("0-0") {
+        text = text.replace('0', "O");
+    }
+    game.generate_moves().into_iter()
+        // working out the SAN of a move is slow, so only moves to the right square get that far
+        .filter(|mv| mv.is_castling_move || text.contains(&square_name(mv.to)))
+        .find(|mv| san_without_check(game, mv).replace('=', "") == text)
+}
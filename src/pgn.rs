@@ -0,0 +1,157 @@
+use crate::chess_move::Move;
+use crate::fen;
+use crate::game::Game;
+use crate::notation;
+
+// one game from a PGN file, moves are kept as written until the game is replayed
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    fn start_position(&self) -> Result<Game, String> {
+        match self.tag("FEN") {
+            Some(text) => fen::parse(text),
+            None => Ok(Game::new()),
+        }
+    }
+
+    // plays the moves from the start position, or from the FEN tag when there is one
+    // returns the position each move was played in together with the move
+    pub fn replay(&self) -> Result<Vec<(Game, Move)>, String> {
+        let mut game = self.start_position()?;
+        let mut played = Vec::new();
+        for text in &self.moves {
+            let mv = notation::parse_san(&game, text).ok_or(format!("illegal move '{}'", text))?;
+            let before = game.without_history();
+            game.make_move_without_state(&mv);
+            played.push((before, mv));
+        }
+        Ok(played)
+    }
+
+    // the position after the last move, with its state worked out
+    pub fn final_position(&self) -> Result<Game, String> {
+        let mut game = match self.replay()?.pop() {
+            Some((mut before, mv)) => {
+                before.make_move(&mv);
+                before
+            }
+            None => self.start_position()?,
+        };
+        game.update_state();
+        Ok(game)
+    }
+
+    // the game as PGN text, tags in the order given and the moves wrapped at 80 columns
+    pub fn to_pgn(&self) -> String {
+        let mut text = String::new();
+        for (name, value) in &self.tags {
+            text.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+        }
+        text.push('\n');
+        // a game set up with Black to move starts with a number followed by ...
+        let black_first = self.tag("FEN").is_some_and(|fen| fen.split_whitespace().nth(1) == Some("b"));
+        let first_number: usize = self.tag("FEN")
+            .and_then(|fen| fen.split_whitespace().nth(5))
+            .and_then(|number| number.parse().ok())
+            .unwrap_or(1);
+        let mut tokens = Vec::new();
+        for (index, mv) in self.moves.iter().enumerate() {
+            let ply = index + black_first as usize;
+            let number = first_number + ply / 2;
+            if ply.is_multiple_of(2) {
+                tokens.push(format!("{}.", number));
+            } else if index == 0 {
+                tokens.push(format!("{}...", number));
+            }
+            tokens.push(mv.clone());
+        }
+        tokens.push(self.result.clone());
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+                text.push_str(&line);
+                text.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        text.push_str(&line);
+        text.push_str("\n\n");
+        text
+    }
+}
+
+// splits a PGN file into games, comments, variations and numeric annotations are skipped
+pub fn parse(text: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut current = PgnGame { tags: Vec::new(), moves: Vec::new(), result: String::from("*") };
+    let mut in_movetext = false;
+    let mut comment_depth = 0;
+    let mut variation_depth = 0;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if comment_depth == 0 && variation_depth == 0 && trimmed.starts_with('[') {
+            // a tag after some moves starts the next game
+            if in_movetext {
+                games.push(current);
+                current = PgnGame { tags: Vec::new(), moves: Vec::new(), result: String::from("*") };
+                in_movetext = false;
+            }
+            if let Some((name, value)) = trimmed.trim_matches(['[', ']']).split_once(' ') {
+                current.tags.push((name.to_string(), value.trim().trim_matches('"').to_string()));
+            }
+            continue;
+        }
+        // ; comments run to the end of the line
+        let line = if comment_depth == 0 { line.split(';').next().unwrap_or("") } else { line };
+        let mut token = String::new();
+        for symbol in line.chars().chain([' ']) {
+            match symbol {
+                '{' => comment_depth += 1,
+                '}' => comment_depth = 0,
+                _ if comment_depth > 0 => {}
+                '(' => variation_depth += 1,
+                ')' => variation_depth = variation_depth.max(1) - 1,
+                _ if variation_depth > 0 => {}
+                ' ' | '\t' => {
+                    if !token.is_empty() {
+                        in_movetext = true;
+                        add_token(&mut current, &token);
+                        token.clear();
+                    }
+                }
+                _ => token.push(symbol),
+            }
+        }
+    }
+    if in_movetext || !current.tags.is_empty() {
+        games.push(current);
+    }
+    games
+}
+
+fn add_token(game: &mut PgnGame, token: &str) {
+    if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token) {
+        game.result = token.to_string();
+        return;
+    }
+    if token.starts_with('$') {
+        return;
+    }
+    // move numbers like 12. or 12... may be glued to the move
+    let mv = token.rsplit('.').next().unwrap_or("");
+    if !mv.is_empty() {
+        game.moves.push(mv.to_string());
+    }
+}
@@ -2,9 +2,10 @@
 
 use crate::board::Cell;
 use crate::board::Board;
-use crate::board::BOARD_SIZE;
+use crate::move_list::MoveList;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Piece {
     pub piece_type: PieceType,
     pub color: Color,
@@ -12,6 +13,8 @@ pub struct Piece {
 
 }
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum PieceType {
     Pawn,
     Rook,
@@ -19,45 +22,98 @@ pub enum PieceType {
     Bishop,
     Queen,
     King,
+    // fairy pieces, each one combines the movement of two standard pieces
+    Archbishop, // bishop + knight
+    Chancellor, // rook + knight
+    Amazon,     // queen + knight
+}
+pub const PIECE_TYPE_COUNT: usize = 9;
+impl PieceType {
+    //position of the type in tables that have one entry per piece type
+    pub fn index(&self) -> usize {
+        match self {
+            PieceType::Pawn => 0,
+            PieceType::Knight => 1,
+            PieceType::Bishop => 2,
+            PieceType::Rook => 3,
+            PieceType::Queen => 4,
+            PieceType::King => 5,
+            PieceType::Archbishop => 6,
+            PieceType::Chancellor => 7,
+            PieceType::Amazon => 8,
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Color {
     White,
     Black,
 }
+impl Color {
+    pub fn opponent(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Color::White => "White",
+            Color::Black => "Black",
+        }
+    }
+}
 impl Piece {
-    pub fn valid_moves(&self, position: (usize, usize), board: &Board) -> Vec<(usize, usize)> {
+    pub fn valid_moves(&self, position: (usize, usize), board: &Board) -> MoveList {
+        let mut moves = MoveList::new();
         match self.piece_type {
-            PieceType::Pawn => self.pawn_moves(position, board),
-            PieceType::Rook => self.rook_moves(position, board),
-            PieceType::Knight => self.knight_moves(position, board),
-            PieceType::Bishop => self.bishop_moves(position, board),
-            PieceType::Queen => self.queen_moves(position, board),
-            PieceType::King => self.king_moves(position, board),
+            PieceType::Pawn => self.pawn_moves(position, board, &mut moves),
+            PieceType::Rook => self.rook_moves(position, board, &mut moves),
+            PieceType::Knight => self.knight_moves(position, board, &mut moves),
+            PieceType::Bishop => self.bishop_moves(position, board, &mut moves),
+            PieceType::Queen => self.queen_moves(position, board, &mut moves),
+            PieceType::King => self.king_moves(position, board, &mut moves),
+            //compound pieces can go anywhere either of their parts can
+            PieceType::Archbishop => {
+                self.bishop_moves(position, board, &mut moves);
+                self.knight_moves(position, board, &mut moves);
+            }
+            PieceType::Chancellor => {
+                self.rook_moves(position, board, &mut moves);
+                self.knight_moves(position, board, &mut moves);
+            }
+            PieceType::Amazon => {
+                self.queen_moves(position, board, &mut moves);
+                self.knight_moves(position, board, &mut moves);
+            }
         }
+        moves
     }
     //rules for moving pawns, does not handle pawn promotion
-    fn pawn_moves(&self, position:(usize, usize), board: &Board) -> Vec<(usize, usize)> {
-        let mut moves = Vec::new();
+    fn pawn_moves(&self, position:(usize, usize), board: &Board, moves: &mut MoveList) {
         let (x, y) = position;
+        let rows = board.rows;
+        let cols = board.cols;
         match self.color {
             //White moves "up" on the board so all logic is addition/forward based
             Color::White => {
-                if x < BOARD_SIZE - 1 && board.grid[x + 1][y] == Cell::Empty {
+                if x < rows - 1 && board.grid[x + 1][y] == Cell::Empty {
                     moves.push((x + 1, y));
                 }
-                if x == 1 && board.grid[x + 2][y] == Cell::Empty && board.grid[x + 1][y] == Cell::Empty {
+                if x == board.pawn_row && board.grid[x + 2][y] == Cell::Empty && board.grid[x + 1][y] == Cell::Empty {
                     moves.push((x + 2, y));
                 }
-                if x < BOARD_SIZE - 1 && y < BOARD_SIZE - 1 {
+                if x < rows - 1 && y < cols - 1 {
                     if let Cell::Occupied(piece) = board.grid[x + 1][y + 1] {
                         if piece.color != self.color {
                             moves.push((x + 1, y + 1));
                         }
                     }
                 }
-                if x < BOARD_SIZE - 1 && y > 0 {
+                if x < rows - 1 && y > 0 {
                     if let Cell::Occupied(piece) = board.grid[x + 1][y - 1] {
                         if piece.color != self.color {
                             moves.push((x + 1, y - 1));
@@ -70,10 +126,10 @@ impl Piece {
                 if x > 0 && board.grid[x - 1][y] == Cell::Empty {
                     moves.push((x - 1, y));
                 }
-                if x == 6 && board.grid[x - 2][y] == Cell::Empty && board.grid[x - 1][y] == Cell::Empty {
+                if x == rows - 1 - board.pawn_row && board.grid[x - 2][y] == Cell::Empty && board.grid[x - 1][y] == Cell::Empty {
                     moves.push((x - 2, y));
                 }
-                if x > 0 && y < BOARD_SIZE - 1 {
+                if x > 0 && y < cols - 1 {
                     if let Cell::Occupied(piece) = board.grid[x - 1][y + 1] {
                         if piece.color != self.color {
                             moves.push((x - 1, y + 1));
@@ -89,11 +145,9 @@ impl Piece {
                 }
             },
         }
-        moves    
     }
     //rook moves checks to see if the path for the rook is clear and does not allow for jumping opponents like knight
-    fn rook_moves(&self, position: (usize, usize), board: &Board) -> Vec<(usize, usize)> {
-        let mut moves = Vec::new();
+    fn rook_moves(&self, position: (usize, usize), board: &Board, moves: &mut MoveList) {
         let (x, y) = position;
         let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
 
@@ -104,7 +158,7 @@ impl Piece {
             loop {
                 current_x += dx;
                 current_y += dy;
-                if current_x < 0 || current_x >= BOARD_SIZE as i32 || current_y < 0 || current_y >= BOARD_SIZE as i32 {
+                if current_x < 0 || current_x >= board.rows as i32 || current_y < 0 || current_y >= board.cols as i32 {
                     break;
                 }
                 let new_position = (current_x as usize, current_y as usize);
@@ -121,11 +175,9 @@ impl Piece {
                 }
             }
         }
-        moves
     }
     //knight moves checks to make sure the piece will not go off the board but does not need to check for pieces in the way 
-    fn knight_moves(&self, position: (usize, usize), board: &Board) -> Vec<(usize, usize)> {
-        let mut moves = Vec::new();
+    fn knight_moves(&self, position: (usize, usize), board: &Board, moves: &mut MoveList) {
         let (x, y) = position;
         let possible_moves = [
             (x.wrapping_add(2), y.wrapping_add(1)),
@@ -139,7 +191,7 @@ impl Piece {
         ];
 
         for &new_position in &possible_moves {
-            if new_position.0 < BOARD_SIZE && new_position.1 < BOARD_SIZE {
+            if new_position.0 < board.rows && new_position.1 < board.cols {
                 match board.grid[new_position.0][new_position.1] {
                     Cell::Empty => {
                         moves.push(new_position);
@@ -152,10 +204,8 @@ impl Piece {
                 }
             }
         }
-        moves
     }
-    fn bishop_moves(&self, position: (usize, usize), board: &Board) -> Vec<(usize, usize)> {
-        let mut moves = Vec::new();
+    fn bishop_moves(&self, position: (usize, usize), board: &Board, moves: &mut MoveList) {
         let (x, y) = position;
         let directions = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
 
@@ -166,7 +216,7 @@ impl Piece {
             loop {
                 current_x += dx;
                 current_y += dy;
-                if current_x < 0 || current_x >= BOARD_SIZE as i32 || current_y < 0 || current_y >= BOARD_SIZE as i32 {
+                if current_x < 0 || current_x >= board.rows as i32 || current_y < 0 || current_y >= board.cols as i32 {
                     break;
                 }
                 let new_position = (current_x as usize, current_y as usize);
@@ -183,10 +233,8 @@ impl Piece {
                 }
             }
         }
-        moves
     }
-    fn queen_moves(&self, position: (usize, usize), board: &Board) -> Vec<(usize, usize)> {
-        let mut moves = Vec::new();
+    fn queen_moves(&self, position: (usize, usize), board: &Board, moves: &mut MoveList) {
         let (x, y) = position;
         let directions = [(1, 0), (0, 1), (-1, 0), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
 
@@ -198,7 +246,7 @@ impl Piece {
                 current_x += dx;
                 current_y += dy;
 
-                if current_x < 0 || current_x >= BOARD_SIZE as i32 || current_y < 0 || current_y >= BOARD_SIZE as i32 {
+                if current_x < 0 || current_x >= board.rows as i32 || current_y < 0 || current_y >= board.cols as i32 {
                     break;
                 }
 
@@ -216,12 +264,10 @@ impl Piece {
                 }
             }
         }
-        moves
     }
     //king moves only handles moving the king and lets check and checkmate functions determine 
     //if the king moves places the player in check or checkmate
-    fn king_moves(&self, position: (usize, usize), board: &Board) -> Vec<(usize, usize)> {
-        let mut moves = Vec::new();
+    fn king_moves(&self, position: (usize, usize), board: &Board, moves: &mut MoveList) {
         let (x, y) = position;
         let possible_moves = [
             (x.wrapping_add(1), y),
@@ -235,7 +281,7 @@ impl Piece {
         ];
 
         for &new_position in &possible_moves {
-            if new_position.0 < BOARD_SIZE && new_position.1 < BOARD_SIZE {
+            if new_position.0 < board.rows && new_position.1 < board.cols {
                 match board.grid[new_position.0][new_position.1] {
                     Cell::Empty => {
                         moves.push(new_position);
@@ -248,7 +294,6 @@ impl Piece {
                 }
             }
         }
-        moves
     }
 
 }
\ No newline at end of file
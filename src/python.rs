@@ -0,0 +1,366 @@
+use crate::board::Board;
+use crate::chess_move::{square_name, Move};
+use crate::fen;
+use crate::game::{Game, GameState, Variant};
+use crate::notation;
+use crate::pgn::{self, PgnGame};
+use crate::search::{self, Engine, SearchResult, MATE, MAX_MULTI_PV};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::BTreeMap;
+
+// the Python module, built with `maturin develop --release` and imported as final_proj
+//   game = final_proj.Game()
+//   game.push("e4")
+//   final_proj.Engine().search(game, 6)["move"]
+// moves come out in UCI notation (e2e4, e7e8q) and go in as UCI or SAN
+#[pymodule]
+fn final_proj(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGame>()?;
+    module.add_class::<PyBoard>()?;
+    module.add_class::<PyPgnGame>()?;
+    module.add_class::<PyEngine>()?;
+    module.add_function(wrap_pyfunction!(read_pgn, module)?)?;
+    module.add_function(wrap_pyfunction!(search_position, module)?)?;
+    Ok(())
+}
+
+#[pyclass(name = "Game", module = "final_proj", skip_from_py_object)]
+#[derive(Clone)]
+pub struct PyGame {
+    game: Game,
+    // the FEN the game was set up from, None when it started from the variant's usual setup
+    start: Option<String>,
+}
+
+#[pymethods]
+impl PyGame {
+    #[new]
+    #[pyo3(signature = (variant = "standard"))]
+    fn new(variant: &str) -> PyResult<PyGame> {
+        Ok(PyGame { game: Game::with_variant(variant_named(variant)?), start: None })
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (fen, variant = "standard"))]
+    fn from_fen(fen: &str, variant: &str) -> PyResult<PyGame> {
+        Ok(PyGame { game: position(fen, variant_named(variant)?)?, start: Some(fen.to_string()) })
+    }
+
+    fn fen(&self) -> String {
+        fen::to_fen(&self.game)
+    }
+
+    #[getter]
+    fn board(&self) -> PyBoard {
+        PyBoard { board: self.game.board.clone() }
+    }
+
+    // "white" or "black"
+    #[getter]
+    fn turn(&self) -> String {
+        self.game.current_player.name().to_lowercase()
+    }
+
+    // "ongoing", "check", "checkmate" or "stalemate"
+    #[getter]
+    fn state(&self) -> &'static str {
+        self.game.state.name()
+    }
+
+    #[getter]
+    fn variant(&self) -> &'static str {
+        self.game.variant.name()
+    }
+
+    fn legal_moves(&self) -> Vec<String> {
+        self.game.generate_moves().iter().map(Move::notation).collect()
+    }
+
+    // the same moves in SAN, in the same order
+    fn legal_moves_san(&self) -> Vec<String> {
+        self.game.generate_moves().iter().map(|mv| notation::san(&self.game, mv)).collect()
+    }
+
+    fn is_legal(&self, mv: &str) -> bool {
+        find_move(&self.game, mv).is_some()
+    }
+
+    // plays the move and returns it in SAN, ValueError when it isn't legal
+    fn push(&mut self, mv: &str) -> PyResult<String> {
+        let found = find_move(&self.game, mv).ok_or_else(|| PyValueError::new_err(format!("'{}' is not a legal move", mv)))?;
+        let san = notation::san(&self.game, &found);
+        self.game.make_move(&found);
+        Ok(san)
+    }
+
+    // the moves played so far in UCI notation
+    fn history(&self) -> Vec<String> {
+        self.game.history.iter().map(Move::notation).collect()
+    }
+
+    fn history_san(&self) -> Vec<String> {
+        let mut game = self.start_position();
+        self.game.history.iter().map(|mv| {
+            let san = notation::san(&game, mv);
+            game.make_move(mv);
+            san
+        }).collect()
+    }
+
+    fn is_over(&self) -> bool {
+        matches!(self.game.state, GameState::Checkmate | GameState::Stalemate | GameState::Draw)
+    }
+
+    // "white" or "black" once someone has won, None while the game goes on or after a draw
+    fn winner(&self) -> Option<String> {
+        let winner = match self.game.state {
+            _ if self.game.variant == Variant::Antichess => self.game.antichess_winner(),
+            GameState::Checkmate => Some(self.game.current_player.opponent()),
+            _ => None,
+        };
+        winner.map(|color| color.name().to_lowercase())
+    }
+
+    // 1-0, 0-1, 1/2-1/2, or * while the game goes on
+    fn result(&self) -> &'static str {
+        match self.winner().as_deref() {
+            Some("white") => "1-0",
+            Some(_) => "0-1",
+            None if self.is_over() => "1/2-1/2",
+            None => "*",
+        }
+    }
+
+    fn copy(&self) -> PyGame {
+        self.clone()
+    }
+
+    // the game as PGN, `tags` are written first and in their order, Result is filled in unless given
+    #[pyo3(signature = (tags = None))]
+    fn to_pgn(&self, tags: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+        let mut written = Vec::new();
+        if let Some(tags) = tags {
+            for (name, value) in tags.iter() {
+                written.push((name.extract::<String>()?, value.str()?.to_string()));
+            }
+        }
+        let result = written.iter().find(|(name, _)| name == "Result").map(|(_, value)| value.clone()).unwrap_or(self.result().to_string());
+        if !written.iter().any(|(name, _)| name == "Result") {
+            written.push((String::from("Result"), result.clone()));
+        }
+        if let Some(start) = &self.start {
+            written.push((String::from("SetUp"), String::from("1")));
+            written.push((String::from("FEN"), start.clone()));
+        }
+        Ok(PgnGame { tags: written, moves: self.history_san(), result }.to_pgn())
+    }
+
+    fn __str__(&self) -> String {
+        self.board().__str__()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Game('{}')", self.fen())
+    }
+}
+
+impl PyGame {
+    fn start_position(&self) -> Game {
+        match &self.start {
+            Some(start) => position(start, self.game.variant).unwrap_or_else(|_| Game::with_variant(self.game.variant)),
+            None => Game::with_variant(self.game.variant),
+        }
+    }
+}
+
+// a snapshot of the pieces, changing the game afterwards doesn't change it
+#[pyclass(name = "Board", module = "final_proj", skip_from_py_object)]
+#[derive(Clone)]
+pub struct PyBoard {
+    board: Board,
+}
+
+#[pymethods]
+impl PyBoard {
+    #[getter]
+    fn rows(&self) -> usize {
+        self.board.rows
+    }
+
+    #[getter]
+    fn cols(&self) -> usize {
+        self.board.cols
+    }
+
+    // the piece as its FEN letter, upper case for White, None for an empty square
+    fn piece_at(&self, square: &str) -> PyResult<Option<String>> {
+        let position = fen::parse_square(square, &self.board).ok_or_else(|| PyValueError::new_err(format!("'{}' is not a square on the board", square)))?;
+        Ok(self.board.get_piece_at(position).map(|piece| fen::letter(&piece).to_string()))
+    }
+
+    // every occupied square with its piece letter, e.g. {"e1": "K", ...}
+    fn pieces(&self) -> BTreeMap<String, String> {
+        let mut pieces = BTreeMap::new();
+        for row in 0..self.board.rows {
+            for col in 0..self.board.cols {
+                if let Some(piece) = self.board.get_piece_at((row, col)) {
+                    pieces.insert(square_name((row, col)), fen::letter(&piece).to_string());
+                }
+            }
+        }
+        pieces
+    }
+
+    // the board from White's side, one rank per line, . for an empty square
+    fn __str__(&self) -> String {
+        (0..self.board.rows).rev().map(|row| {
+            (0..self.board.cols)
+                .map(|col| self.board.get_piece_at((row, col)).map_or('.', |piece| fen::letter(&piece)).to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        }).collect::<Vec<_>>().join("\n")
+    }
+}
+
+#[pyclass(name = "PgnGame", module = "final_proj")]
+pub struct PyPgnGame {
+    pgn: PgnGame,
+}
+
+#[pymethods]
+impl PyPgnGame {
+    #[getter]
+    fn tags(&self) -> Vec<(String, String)> {
+        self.pgn.tags.clone()
+    }
+
+    fn tag(&self, name: &str) -> Option<String> {
+        self.pgn.tag(name).map(str::to_string)
+    }
+
+    // the moves as written in the file, normally SAN
+    #[getter]
+    fn moves(&self) -> Vec<String> {
+        self.pgn.moves.clone()
+    }
+
+    #[getter]
+    fn result(&self) -> String {
+        self.pgn.result.clone()
+    }
+
+    // plays the moves from the start, or from the FEN tag, ValueError on an illegal move
+    #[pyo3(signature = (variant = "standard"))]
+    fn game(&self, variant: &str) -> PyResult<PyGame> {
+        let variant = variant_named(variant)?;
+        let mut game = match self.pgn.tag("FEN") {
+            Some(start) => PyGame { game: position(start, variant)?, start: Some(start.to_string()) },
+            None => PyGame { game: Game::with_variant(variant), start: None },
+        };
+        for mv in &self.pgn.moves {
+            game.push(mv)?;
+        }
+        Ok(game)
+    }
+
+    fn to_pgn(&self) -> String {
+        self.pgn.to_pgn()
+    }
+}
+
+// every game in a PGN text
+#[pyfunction]
+fn read_pgn(text: &str) -> Vec<PyPgnGame> {
+    pgn::parse(text).into_iter().map(|pgn| PyPgnGame { pgn }).collect()
+}
+
+// the search with its transposition table kept between calls
+#[pyclass(name = "Engine", module = "final_proj")]
+pub struct PyEngine {
+    engine: Engine,
+}
+
+#[pymethods]
+impl PyEngine {
+    #[new]
+    #[pyo3(signature = (hash_mb = None, threads = 1))]
+    fn new(hash_mb: Option<usize>, threads: usize) -> PyEngine {
+        let mut engine = Engine::new();
+        if let Some(megabytes) = hash_mb {
+            engine.tt.resize(megabytes);
+        }
+        engine.threads = threads.max(1);
+        PyEngine { engine }
+    }
+
+    // searches to 'depth' and returns a dict with the best move, its score in centipawns from the
+    // side to move's point of view, mate in moves when it sees one, the depth, nodes, time and line
+    // other Python threads keep running while it searches
+    fn search(&mut self, py: Python<'_>, game: &PyGame, depth: u32) -> PyResult<Py<PyDict>> {
+        let (engine, game) = (&mut self.engine, game.game.clone());
+        let result = py.detach(move || engine.search(&game, depth));
+        result_dict(py, &result)
+    }
+
+    // the 'count' best moves, each a dict with its score, mate and line
+    fn best_lines(&mut self, py: Python<'_>, game: &PyGame, depth: u32, count: usize) -> PyResult<Vec<Py<PyDict>>> {
+        let (engine, game) = (&mut self.engine, game.game.clone());
+        let lines = py.detach(move || engine.best_lines(&game, depth, count.clamp(1, MAX_MULTI_PV)));
+        lines.iter().map(|line| {
+            let dict = PyDict::new(py);
+            dict.set_item("score", line.score)?;
+            dict.set_item("mate", mate_in(line.score))?;
+            dict.set_item("pv", line.pv.iter().map(Move::notation).collect::<Vec<_>>())?;
+            Ok(dict.unbind())
+        }).collect()
+    }
+
+    // forgets everything the transposition table learned, e.g. between unrelated positions
+    fn clear(&mut self) {
+        self.engine.tt.clear();
+    }
+}
+
+// a one-off search with a fresh engine
+#[pyfunction(name = "search")]
+fn search_position(py: Python<'_>, game: &PyGame, depth: u32) -> PyResult<Py<PyDict>> {
+    let game = game.game.clone();
+    let result = py.detach(move || search::search(&game, depth));
+    result_dict(py, &result)
+}
+
+fn result_dict(py: Python<'_>, result: &SearchResult) -> PyResult<Py<PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("move", result.best_move.map(|mv| mv.notation()))?;
+    dict.set_item("score", result.score)?;
+    dict.set_item("mate", mate_in(result.score))?;
+    dict.set_item("depth", result.depth)?;
+    dict.set_item("nodes", result.nodes)?;
+    dict.set_item("time", result.elapsed.as_secs_f64())?;
+    dict.set_item("pv", result.pv.iter().map(Move::notation).collect::<Vec<_>>())?;
+    Ok(dict.unbind())
+}
+
+// moves to mate, negative when the side to move is getting mated, None for an ordinary score
+fn mate_in(score: i32) -> Option<i32> {
+    (score.abs() > MATE - 1000).then(|| {
+        let moves = (MATE - score.abs() + 1) / 2;
+        if score > 0 { moves } else { -moves }
+    })
+}
+
+fn find_move(game: &Game, text: &str) -> Option<Move> {
+    notation::parse_uci_move(game, text)
+        .or_else(|| notation::parse_san(game, text))
+        .filter(|mv| game.is_valid_move(mv.from, mv.to))
+}
+
+fn variant_named(name: &str) -> PyResult<Variant> {
+    Variant::from_name(name).ok_or_else(|| PyValueError::new_err(format!("unknown variant '{}'", name)))
+}
+
+fn position(text: &str, variant: Variant) -> PyResult<Game> {
+    fen::parse_for(variant, text).map_err(PyValueError::new_err)
+}
@@ -0,0 +1,302 @@
+use crate::game::{Game, GameState, Variant};
+use crate::piece::Color;
+use std::f64::consts::PI;
+use std::fs;
+use std::io::ErrorKind;
+
+// every player starts here in both systems
+const START_RATING: f64 = 1500.0;
+// how far one Elo game can move a rating
+const ELO_K: f64 = 20.0;
+// Glicko-2 starting deviation and volatility, and tau, which limits how fast volatility changes
+const START_DEVIATION: f64 = 350.0;
+const START_VOLATILITY: f64 = 0.06;
+const TAU: f64 = 0.5;
+// converts between the Glicko and Glicko-2 scales
+const GLICKO2_SCALE: f64 = 173.7178;
+const VOLATILITY_TOLERANCE: f64 = 0.000001;
+
+// how a rated game ended, from White's point of view
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl Outcome {
+    // from the state a finished game was left in, None while it is still going
+    // the side to move is the one that was mated, or in antichess the one that ran out of moves and won
+    pub fn from_game(game: &Game) -> Option<Outcome> {
+        let win_for = |color: Color| if color == Color::White { Outcome::WhiteWins } else { Outcome::BlackWins };
+        match game.state {
+            GameState::Checkmate => Some(win_for(game.current_player.opponent())),
+            GameState::Stalemate if game.variant == Variant::Antichess => Some(win_for(game.current_player)),
+            GameState::Stalemate | GameState::Draw => Some(Outcome::Draw),
+            GameState::Ongoing | GameState::Check => None,
+        }
+    }
+
+    // a PGN style result, * and anything else is None
+    pub fn from_result(text: &str) -> Option<Outcome> {
+        match text {
+            "1-0" => Some(Outcome::WhiteWins),
+            "0-1" => Some(Outcome::BlackWins),
+            "1/2-1/2" => Some(Outcome::Draw),
+            _ => None,
+        }
+    }
+
+    pub fn result(&self) -> &'static str {
+        match self {
+            Outcome::WhiteWins => "1-0",
+            Outcome::BlackWins => "0-1",
+            Outcome::Draw => "1/2-1/2",
+        }
+    }
+
+    fn white_points(&self) -> f64 {
+        match self {
+            Outcome::WhiteWins => 1.0,
+            Outcome::BlackWins => 0.0,
+            Outcome::Draw => 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RatedGame {
+    pub white: String,
+    pub black: String,
+    pub outcome: Outcome,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Glicko {
+    pub rating: f64,
+    // rating deviation, the uncertainty of the rating
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Glicko {
+    fn default() -> Self {
+        Glicko { rating: START_RATING, deviation: START_DEVIATION, volatility: START_VOLATILITY }
+    }
+}
+
+// a player's ratings after one of their games
+#[derive(Clone, Debug)]
+pub struct HistoryPoint {
+    // counts from 1 in the order games were recorded
+    pub game: usize,
+    pub opponent: String,
+    pub points: f64,
+    pub elo: f64,
+    pub glicko: Glicko,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayerRating {
+    pub name: String,
+    pub elo: f64,
+    pub glicko: Glicko,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub history: Vec<HistoryPoint>,
+}
+
+impl PlayerRating {
+    fn new(name: &str) -> PlayerRating {
+        PlayerRating { name: name.to_string(), elo: START_RATING, glicko: Glicko::default(), wins: 0, draws: 0, losses: 0, history: Vec::new() }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+}
+
+// the recorded games, oldest first
+// only the games are stored: ratings and histories are worked out by replaying them, so the two
+// systems can never disagree about which games count
+#[derive(Default)]
+pub struct RatingList {
+    pub games: Vec<RatedGame>,
+}
+
+impl RatingList {
+    // a missing file is an empty list, so the first `record` creates it
+    pub fn load(path: &str) -> Result<RatingList, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(RatingList::default()),
+            Err(error) => return Err(format!("{}: {}", path, error)),
+        };
+        let mut list = RatingList::default();
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            // tab separated so names can have spaces: white, black, result
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                [white, black, result] => {
+                    let outcome = Outcome::from_result(result).ok_or(format!("{}:{}: unknown result '{}'", path, number + 1, result))?;
+                    list.games.push(RatedGame { white: white.to_string(), black: black.to_string(), outcome });
+                }
+                _ => return Err(format!("{}:{}: expected WHITE, BLACK and RESULT separated by tabs", path, number + 1)),
+            }
+        }
+        Ok(list)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text: String = self.games.iter().map(|game| format!("{}\t{}\t{}\n", game.white, game.black, game.outcome.result())).collect();
+        fs::write(path, text).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    pub fn record(&mut self, white: &str, black: &str, outcome: Outcome) -> Result<(), String> {
+        if white == black {
+            return Err(format!("{} can't play themselves", white));
+        }
+        self.games.push(RatedGame { white: white.to_string(), black: black.to_string(), outcome });
+        Ok(())
+    }
+
+    // every player's current ratings and history, in order of first appearance
+    // each game is its own Glicko-2 rating period, so ratings move after every game as with Elo,
+    // Glickman instead rates all the games of a period together against the ratings from before it,
+    // which gives slightly different numbers for the same games
+    pub fn players(&self) -> Vec<PlayerRating> {
+        let mut players: Vec<PlayerRating> = Vec::new();
+        for (number, game) in self.games.iter().enumerate() {
+            let mut index = |name: &str| match players.iter().position(|player| player.name == name) {
+                Some(index) => index,
+                None => {
+                    players.push(PlayerRating::new(name));
+                    players.len() - 1
+                }
+            };
+            let (white, black) = (index(&game.white), index(&game.black));
+            let white_points = game.outcome.white_points();
+            // both updates use the ratings from before the game
+            let before = |index: usize| (players[index].elo, players[index].glicko);
+            let (white_before, black_before) = (before(white), before(black));
+            for (player, opponent, (opponent_elo, opponent_glicko), points) in [
+                (white, &game.black, black_before, white_points),
+                (black, &game.white, white_before, 1.0 - white_points),
+            ] {
+                let player = &mut players[player];
+                player.elo = elo_update(player.elo, opponent_elo, points);
+                player.glicko = glicko2_update(player.glicko, &[(opponent_glicko, points)]);
+                if points == 1.0 {
+                    player.wins += 1;
+                } else if points == 0.0 {
+                    player.losses += 1;
+                } else {
+                    player.draws += 1;
+                }
+                player.history.push(HistoryPoint { game: number + 1, opponent: opponent.clone(), points, elo: player.elo, glicko: player.glicko });
+            }
+        }
+        players
+    }
+}
+
+fn elo_update(rating: f64, opponent: f64, points: f64) -> f64 {
+    let expected = 1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0));
+    rating + ELO_K * (points - expected)
+}
+
+// Glicko-2 for one rating period with the given games as (opponent, points scored), following
+// Glickman's description of the system
+fn glicko2_update(player: Glicko, games: &[(Glicko, f64)]) -> Glicko {
+    let (mu, phi) = ((player.rating - START_RATING) / GLICKO2_SCALE, player.deviation / GLICKO2_SCALE);
+    // each game as its g factor, the expected score and the points scored
+    let games: Vec<(f64, f64, f64)> = games.iter()
+        .map(|(opponent, points)| {
+            let (opponent_mu, opponent_phi) = ((opponent.rating - START_RATING) / GLICKO2_SCALE, opponent.deviation / GLICKO2_SCALE);
+            let g = 1.0 / (1.0 + 3.0 * opponent_phi.powi(2) / PI.powi(2)).sqrt();
+            (g, 1.0 / (1.0 + (-g * (mu - opponent_mu)).exp()), *points)
+        })
+        .collect();
+    // estimated variance of the rating from these games, and the improvement they suggest
+    let variance = 1.0 / games.iter().map(|(g, expected, _)| g.powi(2) * expected * (1.0 - expected)).sum::<f64>();
+    let improvement: f64 = games.iter().map(|(g, expected, points)| g * (points - expected)).sum();
+    let delta = variance * improvement;
+
+    // the new volatility solves f(x) = 0, found with the Illinois variant of regula falsi
+    let a = player.volatility.powi(2).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        ex * (delta.powi(2) - phi.powi(2) - variance - ex) / (2.0 * (phi.powi(2) + variance + ex).powi(2)) - (x - a) / TAU.powi(2)
+    };
+    let mut low = a;
+    let mut high = if delta.powi(2) > phi.powi(2) + variance {
+        (delta.powi(2) - phi.powi(2) - variance).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * TAU
+    };
+    let (mut f_low, mut f_high) = (f(low), f(high));
+    while (high - low).abs() > VOLATILITY_TOLERANCE {
+        let middle = low + (low - high) * f_low / (f_high - f_low);
+        let f_middle = f(middle);
+        if f_middle * f_high <= 0.0 {
+            low = high;
+            f_low = f_high;
+        } else {
+            f_low /= 2.0;
+        }
+        high = middle;
+        f_high = f_middle;
+    }
+    let volatility = (low / 2.0).exp();
+
+    let pre_period_phi = (phi.powi(2) + volatility.powi(2)).sqrt();
+    let new_phi = 1.0 / (1.0 / pre_period_phi.powi(2) + 1.0 / variance).sqrt();
+    let new_mu = mu + new_phi.powi(2) * improvement;
+    Glicko { rating: new_mu * GLICKO2_SCALE + START_RATING, deviation: new_phi * GLICKO2_SCALE, volatility }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elo_moves_by_k_times_the_surprise() {
+        // level players expect half a point each
+        assert_eq!(elo_update(1500.0, 1500.0, 1.0), 1510.0);
+        assert_eq!(elo_update(1500.0, 1500.0, 0.5), 1500.0);
+        // 400 points below expects 1/11 of a point
+        let upset = elo_update(1400.0, 1800.0, 1.0);
+        assert!((upset - (1400.0 + ELO_K * 10.0 / 11.0)).abs() < 1e-9);
+
+        let mut list = RatingList::default();
+        list.record("A", "B", Outcome::WhiteWins).unwrap();
+        let players = list.players();
+        assert_eq!((players[0].elo, players[1].elo), (1510.0, 1490.0));
+        assert_eq!((players[0].wins, players[1].losses), (1, 1));
+        assert!(list.record("A", "A", Outcome::Draw).is_err());
+    }
+
+    #[test]
+    fn glicko2_matches_glickmans_example() {
+        let player = Glicko { rating: 1500.0, deviation: 200.0, volatility: 0.06 };
+        let opponent = |rating: f64, deviation: f64| Glicko { rating, deviation, volatility: 0.06 };
+        let games = [(opponent(1400.0, 30.0), 1.0), (opponent(1550.0, 100.0), 0.0), (opponent(1700.0, 300.0), 0.0)];
+        let after = glicko2_update(player, &games);
+        assert!((after.rating - 1464.06).abs() < 0.01, "{}", after.rating);
+        assert!((after.deviation - 151.52).abs() < 0.01, "{}", after.deviation);
+        assert!((after.volatility - 0.05999).abs() < 0.00001, "{}", after.volatility);
+
+        // played one period at a time the same games end up close to that, but not on it
+        let one_by_one = games.iter().fold(player, |player, game| glicko2_update(player, &[*game]));
+        assert!((one_by_one.rating - after.rating).abs() < 10.0);
+        assert!(one_by_one.rating != after.rating);
+    }
+}
@@ -0,0 +1,691 @@
+use crate::chess_move::Move;
+use crate::eval::{self, EvalParams};
+use crate::game::{Game, Variant};
+use crate::notation;
+use crate::piece::{Color, PieceType};
+use crate::polyglot::{Book, BookMode};
+use crate::see::see;
+use crate::tablebase::{Tablebases, Wdl};
+use crate::time_manager::{Iteration, TimeManager};
+use crate::tt::{encode_move, Bound, TranspositionTable, DEFAULT_HASH_MB};
+use crate::zobrist;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// scores are in centipawns from the point of view of the side to move
+// a mate is worth MATE minus the number of plies it takes, so faster mates score higher
+pub const MATE: i32 = 100_000;
+const INFINITY: i32 = MATE + 1;
+// quiescence stops here even if captures remain, check evasions could otherwise go on for a long time
+const MAX_PLY: i32 = 64;
+// deepest iteration a timed search can reach
+pub const MAX_DEPTH: u32 = MAX_PLY as u32;
+// how often the clock is read, in nodes
+const TIME_CHECK_NODES: u64 = 256;
+// most lines a MultiPV search reports
+pub const MAX_MULTI_PV: usize = 256;
+// a win the tablebases promise, below the mates but above any evaluation, less the plies to reach it
+const TB_WIN: i32 = MATE / 2;
+
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64, // summed over all threads, while the search runs the helpers' last few hundred are missing
+    pub elapsed: Duration,
+    // the expected line of play starting with best_move, read back from the transposition table
+    pub pv: Vec<Move>,
+    // permille of the transposition table in use, see TranspositionTable::hashfull
+    pub hashfull: usize,
+    // the best moves ranked with their own scores and lines, the first one is the one above
+    // there are as many as the engine's multi_pv asks for, or fewer when there aren't that many legal moves
+    pub lines: Vec<PvLine>,
+}
+
+// one ranked candidate of a MultiPV search, the line starts with the candidate move
+#[derive(Clone, Debug)]
+pub struct PvLine {
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
+
+impl SearchResult {
+    pub fn nodes_per_second(&self) -> u64 {
+        (self.nodes as f64 / self.elapsed.as_secs_f64().max(0.001)) as u64
+    }
+}
+
+// how much each piece is worth, the king only counts in antichess where it can be captured
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::Archbishop => 850,
+        PieceType::Chancellor => 900,
+        PieceType::Amazon => 1200,
+        PieceType::King => 0,
+    }
+}
+
+// score for the side to move
+// antichess only counts material, and having less of it is better
+pub fn evaluate(game: &Game, params: &EvalParams) -> i32 {
+    let mut score = 0;
+    if game.variant == Variant::Antichess {
+        for row in 0..game.board.rows {
+            for col in 0..game.board.cols {
+                if let Some(piece) = game.board.get_piece_at((row, col)) {
+                    let value = piece_value(piece.piece_type);
+                    score -= if piece.color == Color::White { value } else { -value };
+                }
+            }
+        }
+    } else {
+        score = eval::evaluate(&game.board, params);
+    }
+    if game.current_player == Color::Black {
+        score = -score;
+    }
+    score
+}
+
+// finds the best move for the side to move with a throwaway engine
+pub fn search(game: &Game, depth: u32) -> SearchResult {
+    Engine::new().search(game, depth)
+}
+
+// search state that lives between moves, mostly the transposition table
+pub struct Engine {
+    pub tt: TranspositionTable,
+    pub params: EvalParams,
+    // opening book consulted by choose_move before searching
+    pub book: Option<Book>,
+    pub book_mode: BookMode,
+    // 1 searches on the calling thread only, which keeps results repeatable
+    pub threads: usize,
+    // how many of the best moves get an exact score and a line, more than one costs a search per move
+    pub multi_pv: usize,
+    // endgame tables probed after every capture or pawn move, and at the root to keep only the moves
+    // that hold their result
+    pub tablebases: Option<Tablebases>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Engine { tt: TranspositionTable::new(DEFAULT_HASH_MB), params: EvalParams::default(), book: None, book_mode: BookMode::Random, threads: 1, multi_pv: 1, tablebases: None }
+    }
+
+    pub fn book_move(&self, game: &Game) -> Option<Move> {
+        self.book.as_ref()?.pick(game, self.book_mode)
+    }
+
+    // the move to play: from the book while the position is in it, otherwise searched to 'depth'
+    // the flag says whether it came from the book
+    pub fn choose_move(&mut self, game: &Game, depth: u32) -> Option<(Move, bool)> {
+        if let Some(mv) = self.book_move(game) {
+            return Some((mv, true));
+        }
+        self.search(game, depth).best_move.map(|mv| (mv, false))
+    }
+
+    // alpha-beta search to a fixed depth
+    // with more than one thread the extra threads search the same position (Lazy SMP): they only
+    // help through what they leave in the shared transposition table, the calling thread's result
+    // is the one returned
+    pub fn search(&mut self, game: &Game, depth: u32) -> SearchResult {
+        self.search_controlled(game, depth, &SearchControl::default())
+    }
+
+    // the 'count' best moves with their scores and lines, best first
+    pub fn best_lines(&mut self, game: &Game, depth: u32, count: usize) -> Vec<PvLine> {
+        let multi_pv = std::mem::replace(&mut self.multi_pv, count.clamp(1, MAX_MULTI_PV));
+        let result = self.search(game, depth);
+        self.multi_pv = multi_pv;
+        result.lines
+    }
+
+    // the same, but the search can also be ended by the clock or from another thread
+    // at least one iteration always finishes so there is a move to play
+    pub fn search_controlled(&mut self, game: &Game, depth: u32, control: &SearchControl) -> SearchResult {
+        self.tt.new_search();
+        let started = Instant::now();
+        let root = game.without_history();
+        let history = history_keys(game);
+        let tablebases = self.tablebases.as_ref();
+        let skipped = tablebases.map(|tables| skipped_moves(tables, &root)).unwrap_or_default();
+        let stop = AtomicBool::new(false);
+        let nodes = AtomicU64::new(0);
+        let (tt, params, nodes) = (&self.tt, &self.params, &nodes);
+        let mut result = thread::scope(|scope| {
+            let helpers: Vec<_> = (1..self.threads.max(1))
+                .map(|id| {
+                    let (root, stop, path, skipped) = (&root, &stop, history.clone(), skipped.clone());
+                    scope.spawn(move || {
+                        let mut helper = Searcher { nodes: 0, shared_nodes: Some(nodes), tt, params, stop: Some(stop), abort: control.stop, time: None, can_stop: true, started, multi_pv: 1, path, tablebases, skipped };
+                        // every other helper skips a depth so they don't all search the same tree in step
+                        helper.iterative_deepening(root, 1 + id as u32 % 2, MAX_DEPTH, None);
+                        helper.nodes
+                    })
+                })
+                .collect();
+            let mut main = Searcher { nodes: 0, shared_nodes: Some(nodes), tt, params, stop: Some(&stop), abort: control.stop, time: control.time, can_stop: false, started, multi_pv: self.multi_pv, path: history, tablebases, skipped };
+            let mut result = main.iterative_deepening(&root, 1, depth.max(1), control.report);
+            stop.store(true, Ordering::Relaxed);
+            result.nodes += helpers.into_iter().map(|helper| helper.join().unwrap_or(0)).sum::<u64>();
+            result
+        });
+        result.elapsed = started.elapsed();
+        result
+    }
+}
+
+// what can end a search besides reaching its depth, and where its progress goes
+#[derive(Default)]
+pub struct SearchControl<'a> {
+    pub time: Option<&'a TimeManager>,
+    // set from another thread to end the search, e.g. by the UCI stop command
+    pub stop: Option<&'a AtomicBool>,
+    // called with the result of every finished iteration
+    pub report: Option<&'a (dyn Fn(&SearchResult) + Sync)>,
+}
+
+// the engine playing one side of a game in the CLI
+pub struct Computer {
+    pub color: Color,
+    pub depth: u32,
+    pub engine: Engine,
+}
+
+struct Searcher<'a> {
+    nodes: u64,
+    // every thread's nodes, each adds its own every TIME_CHECK_NODES so progress reports count them all
+    shared_nodes: Option<&'a AtomicU64>,
+    tt: &'a TranspositionTable,
+    params: &'a EvalParams,
+    // set when the search should give up, unfinished results are thrown away
+    stop: Option<&'a AtomicBool>,
+    // the same, but set from outside the search
+    abort: Option<&'a AtomicBool>,
+    // only the main thread watches the clock, and only once it has a move to fall back on
+    time: Option<&'a TimeManager>,
+    can_stop: bool,
+    started: Instant,
+    // helper threads only ever look for the best move
+    multi_pv: usize,
+    // hashes of the positions before the current one, from the game's last capture or pawn move on
+    path: Vec<u64>,
+    tablebases: Option<&'a Tablebases>,
+    // root moves the tablebases rank below the best, never searched
+    skipped: Vec<Move>,
+}
+
+impl Searcher<'_> {
+    // each depth is searched in turn so the best move so far is tried first on the next one
+    fn iterative_deepening(&mut self, root: &Game, first_depth: u32, last_depth: u32, report: Option<&(dyn Fn(&SearchResult) + Sync)>) -> SearchResult {
+        let mut result = SearchResult { best_move: None, score: 0, depth: 0, nodes: 0, elapsed: Duration::ZERO, pv: Vec::new(), hashfull: 0, lines: Vec::new() };
+        // only the moves the tablebases leave are searched
+        let legal_moves = root.generate_moves().len() - self.skipped.len();
+        let mut iteration = Iteration { legal_moves, ..Iteration::default() };
+        for current_depth in first_depth..=last_depth {
+            let (score, best_move) = self.root(root, current_depth, result.best_move, &[]);
+            let mut lines: Vec<PvLine> = best_move.iter()
+                .map(|mv| PvLine { score, pv: principal_variation(self.tt, root, *mv, current_depth) })
+                .collect();
+            // each further line is the best move left once the ones ranked above it are taken out
+            while !lines.is_empty() && lines.len() < self.multi_pv.min(legal_moves) && !self.stopped() {
+                let ranked: Vec<Move> = lines.iter().map(|line| line.pv[0]).collect();
+                let previous = result.lines.get(lines.len()).map(|line| line.pv[0]);
+                match self.root(root, current_depth, previous, &ranked) {
+                    (score, Some(mv)) => lines.push(PvLine { score, pv: principal_variation(self.tt, root, mv, current_depth) }),
+                    _ => break,
+                }
+            }
+            if self.stopped() {
+                break;
+            }
+            if result.best_move.is_some() {
+                iteration.best_move_changed = best_move != result.best_move;
+                iteration.stable_iterations = if iteration.best_move_changed { 0 } else { iteration.stable_iterations + 1 };
+                iteration.score_drop = (result.score - score).max(0);
+            }
+            iteration.mate_found = score.abs() > MATE - MAX_PLY;
+            let pv = lines.first().map(|line| line.pv.clone()).unwrap_or_default();
+            result = SearchResult { best_move, score, depth: current_depth, nodes: self.all_nodes(), elapsed: self.started.elapsed(), pv, hashfull: self.tt.hashfull(), lines };
+            if let Some(report) = report {
+                report(&result);
+            }
+            self.can_stop = true;
+            if best_move.is_none() || self.time.is_some_and(|time| !time.keep_searching(&iteration)) {
+                break;
+            }
+        }
+        result.nodes = self.nodes;
+        result
+    }
+
+    fn stopped(&self) -> bool {
+        let set = |flag: Option<&AtomicBool>| flag.is_some_and(|flag| flag.load(Ordering::Relaxed));
+        set(self.stop) || (self.can_stop && set(self.abort))
+    }
+
+    // the nodes of all threads so far, as far as they have been added to the shared count
+    fn all_nodes(&self) -> u64 {
+        match self.shared_nodes {
+            Some(shared) => shared.load(Ordering::Relaxed) + self.nodes % TIME_CHECK_NODES,
+            None => self.nodes,
+        }
+    }
+
+    // every few nodes adds them to the shared count, then looks at the clock and stops the search
+    // once the hard limit is up
+    fn check_time(&self) {
+        if !self.nodes.is_multiple_of(TIME_CHECK_NODES) {
+            return;
+        }
+        if let Some(shared) = self.shared_nodes {
+            shared.fetch_add(TIME_CHECK_NODES, Ordering::Relaxed);
+        }
+        if !self.can_stop {
+            return;
+        }
+        if let (Some(time), Some(stop)) = (self.time, self.stop) {
+            if time.hard_limit_reached() {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // the best move apart from the 'excluded' ones, which MultiPV has already ranked
+    fn root(&mut self, game: &Game, depth: u32, previous_best: Option<Move>, excluded: &[Move]) -> (i32, Option<Move>) {
+        self.nodes += 1;
+        self.check_time();
+        let moves = ordered_moves(game, previous_best.as_ref().map(encode_move).unwrap_or(0));
+        if moves.is_empty() {
+            return (self.no_moves_score(game, 0), None);
+        }
+        let moves: Vec<Move> = moves.into_iter().filter(|mv| !excluded.contains(mv) && !self.skipped.contains(mv)).collect();
+        let key = zobrist::hash(game);
+        let mut alpha = -INFINITY;
+        let mut best_move = None;
+        self.path.push(key);
+        for mv in moves {
+            let mut child = game.without_history();
+            child.make_move_without_state(&mv);
+            let score = match self.tablebase_score(&child, &mv, 1) {
+                Some(score) => -score,
+                None => -self.negamax(&child, zobrist::update(key, game, &mv, &child), depth - 1, -INFINITY, -alpha, 1),
+            };
+            if self.stopped() {
+                self.path.pop();
+                return (0, None);
+            }
+            if score > alpha || best_move.is_none() {
+                alpha = score;
+                best_move = Some(mv);
+            }
+        }
+        self.path.pop();
+        // with moves left out the score isn't the position's
+        if excluded.is_empty() && self.skipped.is_empty() {
+            self.tt.store(key, depth, alpha, Bound::Exact, best_move.as_ref(), 0);
+        }
+        (alpha, best_move)
+    }
+
+    // 'key' is the position's hash, kept up to date move by move
+    fn negamax(&mut self, game: &Game, key: u64, depth: u32, mut alpha: i32, beta: i32, ply: i32) -> i32 {
+        self.nodes += 1;
+        self.check_time();
+        if self.is_repetition(key) {
+            return 0;
+        }
+        let original_alpha = alpha;
+        let mut tt_move = 0;
+        if let Some(entry) = self.tt.probe(key, ply) {
+            tt_move = entry.best_move;
+            if entry.depth as u32 >= depth {
+                match entry.bound {
+                    Bound::Exact => return entry.score,
+                    Bound::Lower if entry.score >= beta => return entry.score,
+                    Bound::Upper if entry.score <= alpha => return entry.score,
+                    _ => {}
+                }
+            }
+        }
+        if depth == 0 {
+            return self.quiescence(game, alpha, beta, ply);
+        }
+        let moves = ordered_moves(game, tt_move);
+        if moves.is_empty() {
+            return self.no_moves_score(game, ply);
+        }
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        self.path.push(key);
+        for mv in moves {
+            let mut child = game.without_history();
+            child.make_move_without_state(&mv);
+            let score = match self.tablebase_score(&child, &mv, ply + 1) {
+                Some(score) => -score,
+                None => -self.negamax(&child, zobrist::update(key, game, &mv, &child), depth - 1, -beta, -alpha, ply + 1),
+            };
+            // the score of an unfinished search can't go in the table
+            if self.stopped() {
+                self.path.pop();
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        self.path.pop();
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(key, depth, best_score, bound, best_move.as_ref(), ply);
+        best_score
+    }
+
+    // keeps searching captures and promotions past the depth limit so the score isn't taken
+    // in the middle of an exchange (the horizon effect)
+    // captures that lose material according to the static exchange evaluation are skipped
+    fn quiescence(&mut self, game: &Game, mut alpha: i32, beta: i32, ply: i32) -> i32 {
+        self.nodes += 1;
+        self.check_time();
+        let in_check = game.is_in_check();
+        // standing pat: the side to move can usually do at least as well as the static score by not capturing
+        if !in_check {
+            let stand_pat = evaluate(game, self.params);
+            if stand_pat >= beta || ply >= MAX_PLY {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+        }
+        let moves = ordered_moves(game, 0);
+        if moves.is_empty() {
+            return self.no_moves_score(game, ply);
+        }
+        let mut best_score = if in_check { -INFINITY } else { alpha };
+        for mv in moves {
+            // when in check every evasion is searched, otherwise only winning or even captures and promotions
+            if !in_check {
+                if mv.piece_captured.is_none() && mv.promoted_to.is_none() {
+                    continue;
+                }
+                if game.variant != Variant::Antichess && see(game, &mv) < 0 {
+                    continue;
+                }
+            }
+            let mut child = game.without_history();
+            child.make_move_without_state(&mv);
+            let score = -self.quiescence(&child, -beta, -alpha, ply + 1);
+            if score > best_score {
+                best_score = score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        best_score
+    }
+
+    // right after a capture or pawn move the tables know the result, and so what it is worth
+    // 'game' is the position 'mv' led to
+    fn tablebase_score(&self, game: &Game, mv: &Move, ply: i32) -> Option<i32> {
+        if mv.piece_captured.is_none() && mv.piece_moved.piece_type != PieceType::Pawn {
+            return None;
+        }
+        Some(match self.tablebases?.probe_wdl(game).ok()? {
+            Wdl::Win => TB_WIN - ply,
+            Wdl::CursedWin => 1,
+            Wdl::Draw => 0,
+            Wdl::BlessedLoss => -1,
+            Wdl::Loss => -TB_WIN + ply,
+        })
+    }
+
+    // a position seen before, in the game or on the way here, with the same side to move is scored
+    // as a draw: if repeating was best then it can be repeated again
+    // the path starts after the last capture or pawn move, so nothing before it can come back
+    fn is_repetition(&self, key: u64) -> bool {
+        self.path.iter().rev().skip(1).step_by(2).any(|&earlier| earlier == key)
+    }
+
+    // checkmate loses, stalemate is a draw, and in antichess running out of moves wins
+    fn no_moves_score(&self, game: &Game, ply: i32) -> i32 {
+        if game.variant == Variant::Antichess {
+            MATE - ply
+        } else if game.is_in_check() {
+            -MATE + ply
+        } else {
+            0
+        }
+    }
+}
+
+// the legal moves at the root that the tablebases rank below the best, none when they don't cover it
+fn skipped_moves(tables: &Tablebases, root: &Game) -> Vec<Move> {
+    match tables.best_moves(root) {
+        Ok(best) if !best.is_empty() => root.generate_moves().into_iter().filter(|mv| !best.contains(mv)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// hashes of the positions the game went through since its last capture or pawn move, oldest first,
+// without the current one
+fn history_keys(game: &Game) -> Vec<u64> {
+    let zeroing = |mv: &Move| mv.piece_moved.piece_type == PieceType::Pawn || mv.piece_captured.is_some();
+    let mut previous = game.clone();
+    let mut keys = Vec::new();
+    while let Some(mv) = previous.undo_move() {
+        if zeroing(&mv) {
+            break;
+        }
+        keys.push(zobrist::hash(&previous));
+    }
+    keys.reverse();
+    keys
+}
+
+// follows the best moves stored in the table from the root, stopping at a position it
+// doesn't have, a move that isn't legal (another position with the same slot) or 'length' moves
+fn principal_variation(tt: &TranspositionTable, root: &Game, best_move: Move, length: u32) -> Vec<Move> {
+    let mut pv = vec![best_move];
+    let mut game = root.without_history();
+    game.make_move_without_state(&best_move);
+    while pv.len() < length as usize {
+        let stored = match tt.probe(zobrist::hash(&game), 0) {
+            Some(entry) if entry.best_move != 0 => entry.best_move,
+            _ => break,
+        };
+        let mv = match game.generate_moves().into_iter().find(|mv| encode_move(mv) == stored) {
+            Some(mv) => mv,
+            None => break,
+        };
+        game.make_move_without_state(&mv);
+        pv.push(mv);
+    }
+    pv
+}
+
+// the table's move first, then captures of the most valuable pieces, then promotions, then everything else
+fn ordered_moves(game: &Game, tt_move: u32) -> Vec<Move> {
+    let mut moves = game.generate_moves();
+    moves.sort_by_key(|mv| {
+        if tt_move != 0 && encode_move(mv) == tt_move {
+            return i32::MIN;
+        }
+        let victim = mv.piece_captured.map(|piece| piece_value(piece.piece_type)).unwrap_or(0);
+        let promotion = mv.promoted_to.map(piece_value).unwrap_or(0);
+        -(victim * 10 + promotion - piece_value(mv.piece_moved.piece_type) / 10)
+    });
+    moves
+}
+
+// compares node counts at a fixed depth with the transposition table off and on
+pub fn bench(depth: u32) {
+    // a few openings given as moves from the start position
+    let lines = [
+        "",
+        "e2e4 e7e5 g1f3 b8c6 f1b5",
+        "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3",
+    ];
+    println!("{:<48} {:>12} {:>12}", "position", "nodes no TT", "nodes TT");
+    let mut totals = (0, 0);
+    for line in lines {
+        let mut game = Game::new();
+        for text in line.split_whitespace() {
+            match notation::parse_uci_move(&game, text) {
+                Some(mv) => game.make_move(&mv),
+                None => break,
+            }
+        }
+        let without = Engine { tt: TranspositionTable::new(0), ..Engine::new() }.search(&game, depth).nodes;
+        let with = Engine::new().search(&game, depth).nodes;
+        totals = (totals.0 + without, totals.1 + with);
+        println!("{:<48} {:>12} {:>12}", if line.is_empty() { "startpos" } else { line }, without, with);
+    }
+    println!("{:<48} {:>12} {:>12}", format!("total (depth {})", depth), totals.0, totals.1);
+    if totals.0 > 0 {
+        println!("the table saves {:.1}% of the nodes", 100.0 * (totals.0 as f64 - totals.1 as f64) / totals.0 as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn played(moves: &[&str]) -> Game {
+        let mut game = Game::new();
+        for text in moves {
+            let mv = notation::parse_uci_move(&game, text).unwrap();
+            game.make_move(&mv);
+        }
+        game
+    }
+
+    #[test]
+    fn history_keys_go_back_to_the_last_pawn_move() {
+        let game = played(&["e2e4", "g8f6", "g1f3", "f6g8"]);
+        let keys = history_keys(&game);
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[0], zobrist::hash(&played(&["e2e4"])));
+        assert_eq!(keys[2], zobrist::hash(&played(&["e2e4", "g8f6", "g1f3"])));
+    }
+
+    #[test]
+    fn repeated_positions_are_draws() {
+        let game = played(&["e2e4", "d7d5", "e4d5", "g8f6", "g1f3", "f6g8", "f3g1", "g8f6"]);
+        let (tt, params) = (TranspositionTable::new(1), EvalParams::default());
+        let mut searcher = Searcher { nodes: 0, shared_nodes: None, tt: &tt, params: &params, stop: None, abort: None, time: None, can_stop: true, started: Instant::now(), multi_pv: 1, path: history_keys(&game), tablebases: None, skipped: Vec::new() };
+        let key = zobrist::hash(&game);
+        searcher.path.push(key);
+        // Nf3 goes back to the position after White's 3rd move, where White is a pawn up
+        let mv = notation::parse_uci_move(&game, "g1f3").unwrap();
+        let mut child = game.without_history();
+        child.make_move(&mv);
+        assert_ne!(evaluate(&child, &params), 0);
+        assert_eq!(searcher.negamax(&child, zobrist::update(key, &game, &mv, &child), 2, -INFINITY, INFINITY, 1), 0);
+        // Nc3 is new
+        let mv = notation::parse_uci_move(&game, "b1c3").unwrap();
+        let mut child = game.without_history();
+        child.make_move(&mv);
+        assert!(!searcher.is_repetition(zobrist::update(key, &game, &mv, &child)));
+    }
+
+    #[test]
+    fn table_hits_keep_mate_scores() {
+        let mut engine = Engine::new();
+        // a mate 4 plies after a node at ply 3 is found again from a node at ply 5
+        engine.tt.store(1, 4, MATE - 7, Bound::Exact, None, 3);
+        assert_eq!(engine.tt.probe(1, 5).unwrap().score, MATE - 9);
+        engine.tt.store(2, 4, -MATE + 6, Bound::Exact, None, 2);
+        assert_eq!(engine.tt.probe(2, 1).unwrap().score, -MATE + 5);
+        // the fool's mate is found the same way with the table already full of this position
+        let game = played(&["f2f3", "e7e5", "g2g4"]);
+        for _ in 0..2 {
+            let result = engine.search(&game, 3);
+            assert_eq!(result.score, MATE - 1);
+            assert_eq!(result.best_move.unwrap().notation(), "d8h4");
+        }
+    }
+
+    #[test]
+    fn tablebases_pick_the_root_move_and_score_captures() {
+        let mut engine = Engine::new();
+        engine.tablebases = Some(Tablebases::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy")).unwrap());
+        // the rook mates eventually, but only the moves the tables rank best are searched
+        let game = crate::fen::parse("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").unwrap();
+        let best = engine.tablebases.as_ref().unwrap().best_moves(&game).unwrap();
+        assert!(best.contains(&engine.search(&game, 1).best_move.unwrap()));
+        // taking the rook leaves a won KQvK, scored as such without searching it
+        let game = crate::fen::parse("8/8/8/8/3k4/8/3r4/3QK3 w - - 0 1").unwrap();
+        let result = engine.search(&game, 1);
+        assert_eq!(result.score, TB_WIN - 1);
+        assert!(result.best_move.unwrap().piece_captured.is_some());
+    }
+
+    #[test]
+    fn helper_threads_still_give_a_legal_move() {
+        let game = played(&["e2e4", "c7c5", "g1f3"]);
+        let mut engine = Engine { threads: 4, ..Engine::new() };
+        let reported = std::sync::Mutex::new(Vec::new());
+        let report = |result: &SearchResult| reported.lock().unwrap().push(result.nodes);
+        let result = engine.search_controlled(&game, 4, &SearchControl { report: Some(&report), ..SearchControl::default() });
+        assert!(game.generate_moves().contains(&result.best_move.unwrap()));
+        // the reports count the helpers' nodes as well, and never go past the final count
+        let reported = reported.into_inner().unwrap();
+        assert!(reported.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(*reported.last().unwrap() <= result.nodes);
+    }
+
+    #[test]
+    fn one_thread_searches_the_same_way_every_time() {
+        let game = played(&["d2d4", "g8f6", "c2c4", "e7e6"]);
+        let first = Engine::new().search(&game, 3);
+        let second = Engine::new().search(&game, 3);
+        assert_eq!((first.best_move, first.score, first.nodes, first.pv), (second.best_move, second.score, second.nodes, second.pv));
+    }
+
+    #[test]
+    fn multi_pv_ranks_different_moves() {
+        let game = played(&["e2e4", "e7e5", "g1f3", "b8c6"]);
+        let lines = Engine::new().best_lines(&game, 3, 4);
+        assert_eq!(lines.len(), 4);
+        let firsts: Vec<Move> = lines.iter().map(|line| line.pv[0]).collect();
+        assert!(firsts.iter().all(|mv| game.generate_moves().contains(mv)));
+        assert!(firsts.iter().enumerate().all(|(i, mv)| !firsts[..i].contains(mv)));
+        assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        // the first line is the one a normal search finds
+        assert_eq!(lines[0].score, Engine::new().search(&game, 3).score);
+        // there can't be more lines than legal moves, here the king can only take the rook
+        let game = crate::fen::parse("k7/8/8/8/8/8/1r6/K7 w - - 0 1").unwrap();
+        assert_eq!(game.generate_moves().len(), 1);
+        assert_eq!(Engine::new().best_lines(&game, 2, 5).len(), 1);
+    }
+}
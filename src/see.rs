@@ -0,0 +1,103 @@
+use crate::board::Board;
+use crate::chess_move::Move;
+use crate::game::Game;
+use crate::piece::{Color, Piece, PieceType};
+use crate::search::piece_value;
+
+// the king can't really be traded off, so capturing with it only works when nothing can take back
+const KING_VALUE: i32 = 20_000;
+
+fn exchange_value(piece_type: PieceType) -> i32 {
+    if piece_type == PieceType::King {
+        KING_VALUE
+    } else {
+        piece_value(piece_type)
+    }
+}
+
+// static exchange evaluation: the material the side making 'mv' wins (or loses) if both sides
+// keep recapturing on the destination square with their least valuable attacker
+// each side can stop capturing whenever continuing would lose material
+pub fn see(game: &Game, mv: &Move) -> i32 {
+    let mut board = game.board.clone();
+    let target = mv.to;
+    let mut gains = vec![mv.piece_captured.map(|piece| exchange_value(piece.piece_type)).unwrap_or(0)];
+    let mut moving = mv.piece_moved;
+    if let Some(promotion) = mv.promoted_to {
+        gains[0] += exchange_value(promotion) - exchange_value(PieceType::Pawn);
+        moving.piece_type = promotion;
+    }
+    if mv.is_en_passant_move {
+        board.remove_piece_at((mv.from.0, mv.to.1));
+    }
+    board.remove_piece_at(mv.from);
+    board.set_piece_at(target, moving);
+
+    let mut side = game.current_player.opponent();
+    let mut on_square = exchange_value(moving.piece_type);
+    while let Some((from, attacker)) = least_valuable_attacker(&board, target, side) {
+        // what this side has gained if it takes now and the exchange stops there
+        gains.push(on_square - gains[gains.len() - 1]);
+        on_square = exchange_value(attacker.piece_type);
+        board.remove_piece_at(from);
+        board.set_piece_at(target, attacker);
+        side = side.opponent();
+    }
+    // walk back through the exchange letting each side stop when taking would lose
+    while gains.len() > 1 {
+        let last = gains.pop().unwrap();
+        let previous = gains.len() - 1;
+        gains[previous] = -(-gains[previous]).max(last);
+    }
+    gains[0]
+}
+
+// pieces are removed from the board as they capture, so pieces lined up behind them
+// (x-rays) are found on the next pass
+fn least_valuable_attacker(board: &Board, target: (usize, usize), side: Color) -> Option<((usize, usize), Piece)> {
+    let mut best: Option<((usize, usize), Piece)> = None;
+    for row in 0..board.rows {
+        for col in 0..board.cols {
+            if let Some(piece) = board.get_piece_at((row, col)) {
+                if piece.color != side || !Game::attacks(&piece, (row, col), target, board) {
+                    continue;
+                }
+                if best.is_none_or(|(_, current)| exchange_value(piece.piece_type) < exchange_value(current.piece_type)) {
+                    best = Some(((row, col), piece));
+                }
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::see;
+    use crate::fen;
+    use crate::notation;
+
+    fn see_of(text: &str, mv: &str) -> i32 {
+        let game = fen::parse(text).unwrap();
+        see(&game, &notation::parse_uci_move(&game, mv).unwrap())
+    }
+
+    #[test]
+    fn exchanges_are_counted_to_the_end() {
+        // a free knight, and a pawn the rook can't keep
+        assert_eq!(see_of("4k3/8/8/4n3/3P4/8/8/4K3 w - - 0 1", "d4e5"), 320);
+        assert_eq!(see_of("4k3/8/3p4/4p3/8/8/8/4RK2 w - - 0 1", "e1e5"), 100 - 500);
+        // the second rook behind the first wins the pawn back after Rxe5 Rxe5 Rxe5
+        assert_eq!(see_of("4r1k1/8/8/4p3/8/8/4R3/4RK2 w - - 0 1", "e2e5"), 100);
+        // the queen takes a pawn defended by a pawn, Black takes back
+        assert_eq!(see_of("4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1", "e1e5"), 100 - 900);
+        // the king may only take what nothing defends
+        assert_eq!(see_of("4k3/8/8/8/8/8/4p3/4K3 w - - 0 1", "e1e2"), 100);
+    }
+
+    #[test]
+    fn quiet_moves_to_attacked_squares_lose_the_piece() {
+        assert_eq!(see_of("4k3/8/8/3p4/8/8/8/2B1K3 w - - 0 1", "c1e3"), 0);
+        assert_eq!(see_of("4k3/8/8/8/3p4/8/8/2B1K3 w - - 0 1", "c1e3"), -330);
+    }
+}
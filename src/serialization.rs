@@ -0,0 +1,286 @@
+use crate::board::{Board, MAX_BOARD_SIZE};
+use crate::chess_move::{square_name, Move};
+use crate::fen;
+use crate::game::{Game, GameState, Variant};
+use crate::notation;
+use crate::piece::{Color, Piece};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+
+// the two JSON forms of a game
+// compact: the position as FEN plus the moves in UCI notation, which is what Game's own Serialize writes
+//   {"variant":"standard","fen":"...","moves":["e2e4","e7e5"]}
+//   a game set up from a position also has "start", the FEN it started from
+// verbose: every piece on its named square and every move with its squares spelled out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schema {
+    Compact,
+    Verbose,
+}
+
+pub fn to_json(game: &Game, schema: Schema) -> Result<String, String> {
+    let mut json = Vec::new();
+    let mut serializer = serde_json::Serializer::new(&mut json);
+    match schema {
+        Schema::Compact => game.serialize(&mut serializer),
+        Schema::Verbose => verbose::serialize(game, &mut serializer),
+    }
+    .map_err(|error| error.to_string())?;
+    String::from_utf8(json).map_err(|error| error.to_string())
+}
+
+// reads either form, a verbose game is the one with a "board"
+pub fn from_json(text: &str) -> Result<Game, String> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|error| error.to_string())?;
+    if value.get("board").is_some() {
+        verbose::deserialize(value).map_err(|error| error.to_string())
+    } else {
+        Game::deserialize(value).map_err(|error| error.to_string())
+    }
+}
+
+// squares as names like e4, for fields written `#[serde(with = "square")]`
+pub mod square {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(position: &(usize, usize), serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&square_name(*position))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(usize, usize), D::Error> {
+        let name = String::deserialize(deserializer)?;
+        parse(&name).ok_or_else(|| D::Error::custom(format!("'{}' is not a square", name)))
+    }
+
+    pub fn parse(name: &str) -> Option<(usize, usize)> {
+        let mut chars = name.chars();
+        let col = (chars.next()? as usize).checked_sub('a' as usize)?;
+        let row = chars.as_str().parse::<usize>().ok()?.checked_sub(1)?;
+        (row < MAX_BOARD_SIZE && col < MAX_BOARD_SIZE).then_some((row, col))
+    }
+}
+
+// the verbose form for fields written `#[serde(with = "verbose")]`
+pub mod verbose {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(game: &Game, serializer: S) -> Result<S::Ok, S::Error> {
+        VerboseGame::from(game).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Game, D::Error> {
+        VerboseGame::deserialize(deserializer)?.into_game().map_err(D::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CompactGame {
+    #[serde(default = "standard")]
+    variant: Variant,
+    // where the moves start, left out when that is the variant's usual setup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start: Option<String>,
+    fen: String,
+    #[serde(default)]
+    moves: Vec<String>,
+}
+
+fn standard() -> Variant {
+    Variant::Standard
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let start = start_position(self);
+        let start_fen = fen::to_fen(&start);
+        let compact = CompactGame {
+            variant: self.variant,
+            start: (start_fen != fen::to_fen(&Game::with_variant(self.variant))).then_some(start_fen),
+            fen: fen::to_fen(self),
+            moves: self.history.iter().map(|mv| mv.notation()).collect(),
+        };
+        compact.serialize(serializer)
+    }
+}
+
+// the moves are played again, each one checked with Game::is_valid_move, and have to end on the FEN
+// without moves or a start the game is simply set up from the FEN
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Game, D::Error> {
+        let compact = CompactGame::deserialize(deserializer)?;
+        let mut game = match (&compact.start, compact.moves.is_empty()) {
+            (Some(start), _) => fen::parse_for(compact.variant, start).map_err(D::Error::custom)?,
+            (None, false) => Game::with_variant(compact.variant),
+            (None, true) => fen::parse_for(compact.variant, &compact.fen).map_err(D::Error::custom)?,
+        };
+        for text in &compact.moves {
+            let mv = notation::parse_uci_move(&game, text)
+                .filter(|mv| game.is_valid_move(mv.from, mv.to))
+                .ok_or_else(|| D::Error::custom(format!("illegal move {} after {} moves", text, game.history.len())))?;
+            game.make_move(&mv);
+        }
+        // the move counters aren't compared since the start may not have had them, the FEN's are kept
+        let end = fen::parse_for(compact.variant, &compact.fen).map_err(D::Error::custom)?;
+        if position(&game) != position(&end) {
+            return Err(D::Error::custom("the moves don't lead to the FEN"));
+        }
+        game.halfmove_clock = end.halfmove_clock;
+        game.fullmove_number = end.fullmove_number;
+        game.update_state();
+        Ok(game)
+    }
+}
+
+// the FEN without the move counters
+fn position(game: &Game) -> String {
+    fen::to_fen(game).split_whitespace().take(4).collect::<Vec<_>>().join(" ")
+}
+
+// the position before the first move in the history, found by taking the moves back
+fn start_position(game: &Game) -> Game {
+    let mut start = game.clone();
+    while start.undo_move().is_some() {}
+    start.update_state();
+    start
+}
+
+#[derive(Serialize, Deserialize)]
+struct VerboseGame {
+    variant: Variant,
+    turn: Color,
+    state: GameState,
+    en_passant: Option<String>,
+    board: Board,
+    history: Vec<Move>,
+}
+
+impl From<&Game> for VerboseGame {
+    fn from(game: &Game) -> VerboseGame {
+        VerboseGame {
+            variant: game.variant,
+            turn: game.current_player,
+            state: game.state,
+            en_passant: game.en_passant.map(square_name),
+            board: game.board.clone(),
+            history: game.history.clone(),
+        }
+    }
+}
+
+impl VerboseGame {
+    // the history is taken back from the board and played again, each move checked like the compact
+    // form's, and has to lead to the board, the state is worked out again rather than trusted
+    fn into_game(self) -> Result<Game, String> {
+        let en_passant = match &self.en_passant {
+            Some(name) => Some(square::parse(name).filter(|&(row, col)| row < self.board.rows && col < self.board.cols).ok_or(format!("bad en passant square '{}'", name))?),
+            None => None,
+        };
+        let mut game = Game::with_variant(self.variant);
+        game.board = self.board;
+        game.current_player = self.turn;
+        game.en_passant = en_passant;
+        let on_board = |(row, col): (usize, usize)| row < game.board.rows && col < game.board.cols;
+        if let Some(mv) = self.history.iter().find(|mv| !on_board(mv.from) || !on_board(mv.to)) {
+            return Err(format!("the move {} is off the board", mv.notation()));
+        }
+        game.history = self.history;
+        let mut replayed = start_position(&game);
+        for (index, recorded) in game.history.iter().enumerate() {
+            let text = recorded.notation();
+            let mv = notation::parse_uci_move(&replayed, &text)
+                .filter(|mv| mv.piece_moved.piece_type == recorded.piece_moved.piece_type && replayed.is_valid_move(mv.from, mv.to))
+                .ok_or(format!("illegal move {} after {} moves", text, index))?;
+            replayed.make_move(&mv);
+        }
+        if position(&replayed) != position(&game) {
+            return Err(String::from("the history doesn't lead to the board"));
+        }
+        Ok(replayed)
+    }
+}
+
+// the board with only its occupied squares listed, by name
+//   {"rows":8,"cols":8,"pawn_row":1,"squares":{"e1":{"piece_type":"king","color":"white","has_moved":false},...}}
+#[derive(Serialize, Deserialize)]
+struct BoardSquares {
+    rows: usize,
+    cols: usize,
+    pawn_row: usize,
+    squares: BTreeMap<String, Piece>,
+}
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut squares = BTreeMap::new();
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if let Some(piece) = self.get_piece_at((row, col)) {
+                    squares.insert(square_name((row, col)), piece);
+                }
+            }
+        }
+        BoardSquares { rows: self.rows, cols: self.cols, pawn_row: self.pawn_row, squares }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Board, D::Error> {
+        let layout = BoardSquares::deserialize(deserializer)?;
+        if layout.rows == 0 || layout.cols == 0 || layout.rows > MAX_BOARD_SIZE || layout.cols > MAX_BOARD_SIZE {
+            return Err(D::Error::custom(format!("a {}x{} board isn't supported", layout.cols, layout.rows)));
+        }
+        // a pawn on its starting row steps two squares, which has to stay on the board for both colors
+        if layout.pawn_row + 2 >= layout.rows {
+            return Err(D::Error::custom(format!("pawns can't start on row {} of a {}-row board", layout.pawn_row, layout.rows)));
+        }
+        let mut board = Board::empty(layout.rows, layout.cols);
+        board.pawn_row = layout.pawn_row;
+        for (name, piece) in layout.squares {
+            match square::parse(&name).filter(|&(row, col)| row < board.rows && col < board.cols) {
+                Some(position) => board.set_piece_at(position, piece),
+                None => return Err(D::Error::custom(format!("'{}' is not a square on the board", name))),
+            }
+        }
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn played(moves: &[&str]) -> Game {
+        let mut game = Game::new();
+        for text in moves {
+            let mv = notation::parse_uci_move(&game, text).unwrap();
+            game.make_move(&mv);
+        }
+        game
+    }
+
+    #[test]
+    fn both_forms_round_trip() {
+        let game = played(&["e2e4", "f7f6", "d2d4", "g7g5", "d1h5"]);
+        for schema in [Schema::Compact, Schema::Verbose] {
+            let loaded = from_json(&to_json(&game, schema).unwrap()).unwrap();
+            assert_eq!(fen::to_fen(&loaded), fen::to_fen(&game));
+            assert_eq!(loaded.history.len(), 5);
+            assert_eq!(loaded.state, GameState::Checkmate);
+        }
+    }
+
+    #[test]
+    fn bad_pawn_row_is_rejected() {
+        let json = to_json(&Game::new(), Schema::Verbose).unwrap().replace("\"pawn_row\":1", "\"pawn_row\":100");
+        assert!(from_json(&json).is_err());
+    }
+
+    #[test]
+    fn verbose_history_has_to_lead_to_the_board() {
+        let json = to_json(&played(&["e2e4", "e7e5"]), Schema::Verbose).unwrap();
+        assert!(from_json(&json.replace("\"from\":\"e2\"", "\"from\":\"e1\"")).is_err());
+        let ongoing = to_json(&Game::new(), Schema::Verbose).unwrap().replace("\"ongoing\"", "\"checkmate\"");
+        assert_eq!(from_json(&ongoing).unwrap().state, GameState::Ongoing);
+    }
+}
@@ -0,0 +1,673 @@
+use crate::engine_match::TimeControl;
+use crate::fen;
+use crate::game::{Game, GameState, Variant};
+use crate::notation;
+use crate::piece::Color;
+use crate::ratings::Outcome;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PORT: u16 = 8080;
+// how often running clocks are pushed to the feeds
+const CLOCK_INTERVAL: Duration = Duration::from_secs(1);
+// requests bigger than this are refused, nothing the server takes comes close
+const MAX_BODY: usize = 64 * 1024;
+// appended to the client's key in the WebSocket handshake (RFC 6455)
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// a running clock, White's first move starts it
+struct GameClock {
+    remaining: [Duration; 2],
+    increment: Duration,
+    // when the side to move started thinking
+    since: Option<Instant>,
+}
+
+impl GameClock {
+    // the time both sides have left right now
+    fn left(&self, turn: Color) -> [Duration; 2] {
+        let mut left = self.remaining;
+        if let Some(since) = self.since {
+            left[turn as usize] = left[turn as usize].saturating_sub(since.elapsed());
+        }
+        left
+    }
+
+    // stops the clock of the side that just moved and starts the other one
+    fn punch(&mut self, mover: Color) {
+        self.remaining = self.left(mover);
+        self.remaining[mover as usize] += self.increment;
+        self.since = Some(Instant::now());
+    }
+}
+
+// one game on the server with everyone watching its feed
+struct ServerGame {
+    game: Game,
+    clock: Option<GameClock>,
+    // set once the game is over, e.g. ("1-0", "checkmate")
+    result: Option<(&'static str, &'static str)>,
+    // frames for each feed, written by the feed's own thread so a slow client never holds the games lock
+    subscribers: Vec<Sender<Vec<u8>>>,
+}
+
+impl ServerGame {
+    fn to_json(&self, id: usize) -> String {
+        let game = &self.game;
+        let moves: Vec<String> = game.history.iter().map(|mv| json_string(&mv.notation())).collect();
+        let (result, termination) = match self.result {
+            Some((result, termination)) => (json_string(result), json_string(termination)),
+            None => (String::from("null"), String::from("null")),
+        };
+        format!(
+            "{{\"id\":{},\"variant\":{},\"fen\":{},\"turn\":{},\"state\":{},\"moves\":[{}],\"clock\":{},\"result\":{},\"termination\":{}}}",
+            id,
+            json_string(game.variant.name()),
+            json_string(&fen::to_fen(game)),
+            json_string(&game.current_player.name().to_lowercase()),
+            json_string(game.state.name()),
+            moves.join(","),
+            self.clock_json(),
+            result,
+            termination,
+        )
+    }
+
+    fn clock_json(&self) -> String {
+        match &self.clock {
+            Some(clock) => {
+                let left = clock.left(self.game.current_player);
+                format!("{{\"white\":{},\"black\":{},\"running\":{}}}", left[0].as_millis(), left[1].as_millis(), clock.since.is_some() && self.result.is_none())
+            }
+            None => String::from("null"),
+        }
+    }
+
+    // checks and plays a move written in UCI notation (e2e4) or SAN (Nf3)
+    fn play(&mut self, text: &str) -> Result<(), (&'static str, String)> {
+        // the flag may have fallen since the last tick
+        self.check_flag();
+        if self.result.is_some() {
+            return Err(("409 Conflict", String::from("the game is over")));
+        }
+        let game = &mut self.game;
+        let mv = notation::parse_uci_move(game, text)
+            .or_else(|| notation::parse_san(game, text))
+            .filter(|mv| game.is_valid_move(mv.from, mv.to))
+            .ok_or(("422 Unprocessable Entity", format!("'{}' is not a legal move", text)))?;
+        let mover = game.current_player;
+        let san = notation::san(game, &mv);
+        game.make_move(&mv);
+        if let Some(clock) = self.clock.as_mut() {
+            clock.punch(mover);
+        }
+        if let Some(outcome) = Outcome::from_game(&self.game) {
+            let termination = match self.game.state {
+                GameState::Checkmate => "checkmate",
+                GameState::Draw => self.game.draw_reason().unwrap_or("draw"),
+                //running out of moves wins in antichess
+                _ if self.game.variant == Variant::Antichess => "no moves left",
+                _ => "stalemate",
+            };
+            self.result = Some((outcome.result(), termination));
+        }
+        let message = format!(
+            "{{\"type\":\"move\",\"move\":{},\"san\":{},\"fen\":{},\"state\":{},\"clock\":{},\"result\":{}}}",
+            json_string(&mv.notation()),
+            json_string(&san),
+            json_string(&fen::to_fen(&self.game)),
+            json_string(self.game.state.name()),
+            self.clock_json(),
+            self.result.map(|(result, _)| json_string(result)).unwrap_or(String::from("null")),
+        );
+        self.broadcast(&message);
+        Ok(())
+    }
+
+    // ends the game once the side to move has run out of time
+    fn check_flag(&mut self) {
+        let Some(clock) = &self.clock else { return };
+        let turn = self.game.current_player;
+        if self.result.is_none() && clock.since.is_some() && clock.left(turn)[turn as usize].is_zero() {
+            self.result = Some((if turn == Color::White { "0-1" } else { "1-0" }, "time forfeit"));
+        }
+    }
+
+    // queues a text frame for every feed, dropping the ones that have gone away
+    fn broadcast(&mut self, message: &str) {
+        let frame = websocket_frame(0x1, message.as_bytes());
+        self.subscribers.retain(|feed| feed.send(frame.clone()).is_ok());
+    }
+}
+
+struct Server {
+    games: Mutex<BTreeMap<usize, ServerGame>>,
+    next_id: Mutex<usize>,
+    // the clock new games get unless they ask for another one
+    time_control: Option<TimeControl>,
+}
+
+impl Server {
+    // a poisoned lock only means a request thread panicked, the games themselves are still fine
+    fn games(&self) -> MutexGuard<'_, BTreeMap<usize, ServerGame>> {
+        self.games.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// serves games over HTTP until the process is stopped, one thread per connection
+// `time_control` is the clock new games get by default, None for untimed games
+pub fn run(port: u16, time_control: Option<TimeControl>) -> Result<(), String> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|error| format!("port {}: {}", port, error))?;
+    println!("Serving games on http://localhost:{}/games", port);
+    serve(listener, time_control);
+    Ok(())
+}
+
+fn serve(listener: TcpListener, time_control: Option<TimeControl>) {
+    let server = Arc::new(Server { games: Mutex::new(BTreeMap::new()), next_id: Mutex::new(1), time_control });
+    let ticker = Arc::clone(&server);
+    thread::spawn(move || loop {
+        thread::sleep(CLOCK_INTERVAL);
+        tick(&ticker);
+    });
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            handle(&server, stream).ok();
+        });
+    }
+}
+
+// pushes the running clocks and ends games on time
+fn tick(server: &Server) {
+    for game in server.games().values_mut() {
+        let running = game.result.is_none() && game.clock.as_ref().is_some_and(|clock| clock.since.is_some());
+        if !running {
+            continue;
+        }
+        game.check_flag();
+        let message = format!(
+            "{{\"type\":\"clock\",\"clock\":{},\"result\":{}}}",
+            game.clock_json(),
+            game.result.map(|(result, _)| json_string(result)).unwrap_or(String::from("null")),
+        );
+        game.broadcast(&message);
+    }
+}
+
+struct Request {
+    method: String,
+    path: Vec<String>,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    fn query(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+fn read_request(reader: &mut BufReader<TcpStream>) -> Result<Request, String> {
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|error| error.to_string())?;
+    let mut words = line.split_whitespace();
+    let (Some(method), Some(target)) = (words.next(), words.next()) else {
+        return Err(format!("bad request line '{}'", line.trim()));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.split('/').filter(|part| !part.is_empty()).map(percent_decode).collect(),
+        query: query.split('&').filter_map(|pair| pair.split_once('=')).map(|(key, value)| (percent_decode(&key.replace('+', " ")), percent_decode(&value.replace('+', " ")))).collect(),
+        headers: Vec::new(),
+        body: String::new(),
+    };
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|error| error.to_string())? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            request.headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let length: usize = request.header("Content-Length").and_then(|length| length.parse().ok()).unwrap_or(0);
+    if length > MAX_BODY {
+        return Err(String::from("request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|error| error.to_string())?;
+    request.body = String::from_utf8_lossy(&body).into_owned();
+    Ok(request)
+}
+
+// one request per connection, except a feed, which keeps its connection open
+fn handle(server: &Server, stream: TcpStream) -> Result<(), String> {
+    let mut reader = BufReader::new(stream.try_clone().map_err(|error| error.to_string())?);
+    let mut stream = stream;
+    let request = match read_request(&mut reader) {
+        Ok(request) => request,
+        Err(error) => return respond(&mut stream, "400 Bad Request", &error_json(&error)),
+    };
+    let path: Vec<&str> = request.path.iter().map(String::as_str).collect();
+    let method = request.method.as_str();
+    // browsers ask before cross-origin POSTs, and anything is allowed
+    if method == "OPTIONS" {
+        return respond(&mut stream, "204 No Content", "");
+    }
+    let id = path.get(1).and_then(|id| id.parse::<usize>().ok());
+    match (method, path.as_slice()) {
+        ("GET", ["games"]) => {
+            let list: Vec<String> = server.games().iter().map(|(id, game)| game.to_json(*id)).collect();
+            respond(&mut stream, "200 OK", &format!("[{}]", list.join(",")))
+        }
+        ("POST", ["games"]) => match create_game(server, &request) {
+            Ok(json) => respond(&mut stream, "201 Created", &json),
+            Err(error) => respond(&mut stream, "400 Bad Request", &error_json(&error)),
+        },
+        ("GET", ["games", _, "feed"]) if request.header("Upgrade").is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket")) => {
+            subscribe(server, id, &request, stream, reader)
+        }
+        (_, ["games", _, ..]) => {
+            let (status, content_type, body) = game_request(server, id, method, &path[2..], &request);
+            respond_with(&mut stream, status, content_type, &body)
+        }
+        _ => respond(&mut stream, "404 Not Found", &error_json("unknown resource")),
+    }
+}
+
+// the answer to a request about one game as (status, content type, body)
+// worked out under the games lock, which is released before anything is written to the client
+fn game_request(server: &Server, id: Option<usize>, method: &str, path: &[&str], request: &Request) -> (&'static str, &'static str, String) {
+    const JSON: &str = "application/json";
+    let mut games = server.games();
+    let Some(game) = id.and_then(|id| games.get_mut(&id)) else {
+        return ("404 Not Found", JSON, error_json("no such game"));
+    };
+    let id = id.unwrap_or(0);
+    match (method, path) {
+        ("GET", []) => ("200 OK", JSON, game.to_json(id)),
+        ("GET", ["fen"]) => ("200 OK", "text/plain", fen::to_fen(&game.game)),
+        ("GET", ["moves"]) => ("200 OK", JSON, legal_moves_json(&game.game)),
+        // the move is the body, or `?move=e2e4`
+        ("POST", ["moves"]) => {
+            let text = request.query("move").unwrap_or(request.body.trim()).to_string();
+            match game.play(&text) {
+                Ok(()) => ("200 OK", JSON, game.to_json(id)),
+                Err((status, error)) => (status, JSON, error_json(&error)),
+            }
+        }
+        _ => ("404 Not Found", JSON, error_json("unknown resource")),
+    }
+}
+
+// `POST /games?variant=antichess&fen=...&tc=300%2B2`, every parameter is optional
+// tc=none turns the server's default clock off
+fn create_game(server: &Server, request: &Request) -> Result<String, String> {
+    let variant = match request.query("variant") {
+        Some(name) => Variant::from_name(name).ok_or(format!("unknown variant '{}'", name))?,
+        None => Variant::Standard,
+    };
+    let game = match request.query("fen") {
+        Some(text) => fen::parse_for(variant, text)?,
+        None => Game::with_variant(variant),
+    };
+    let time_control = match request.query("tc") {
+        Some("none") => None,
+        Some(text) => Some(TimeControl::parse_clock(text)?),
+        None => server.time_control,
+    };
+    let clock = match time_control {
+        Some(TimeControl::Clock { base, increment }) => Some(GameClock { remaining: [base; 2], increment, since: None }),
+        _ => None,
+    };
+    let id = {
+        let mut next_id = server.next_id.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *next_id += 1;
+        *next_id - 1
+    };
+    let mut game = ServerGame { game, clock, result: None, subscribers: Vec::new() };
+    game.game.update_state();
+    let json = game.to_json(id);
+    server.games().insert(id, game);
+    Ok(json)
+}
+
+fn legal_moves_json(game: &Game) -> String {
+    let moves: Vec<String> = game.generate_moves()
+        .iter()
+        .map(|mv| format!("{{\"uci\":{},\"san\":{}}}", json_string(&mv.notation()), json_string(&notation::san(game, mv))))
+        .collect();
+    format!("[{}]", moves.join(","))
+}
+
+// answers the WebSocket handshake, sends the game as it stands and keeps the connection open
+// the server only pushes: what the client sends is read for pings and the close frame
+// everything for the client, the handshake included, goes through the feed's channel so the frames
+// stay in order and only the feed's writer thread ever blocks on the socket
+fn subscribe(server: &Server, id: Option<usize>, request: &Request, mut stream: TcpStream, mut reader: BufReader<TcpStream>) -> Result<(), String> {
+    let Some(key) = request.header("Sec-WebSocket-Key") else {
+        return respond(&mut stream, "400 Bad Request", &error_json("missing Sec-WebSocket-Key"));
+    };
+    let (feed, frames) = mpsc::channel::<Vec<u8>>();
+    {
+        let mut games = server.games();
+        let Some(game) = id.and_then(|id| games.get_mut(&id)) else {
+            drop(games);
+            return respond(&mut stream, "404 Not Found", &error_json("no such game"));
+        };
+        let accept = base64(&sha1(format!("{}{}", key, WEBSOCKET_GUID).as_bytes()));
+        let handshake = format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", accept);
+        let state = format!("{{\"type\":\"state\",\"game\":{}}}", game.to_json(id.unwrap_or(0)));
+        feed.send(handshake.into_bytes()).ok();
+        feed.send(websocket_frame(0x1, state.as_bytes())).ok();
+        game.subscribers.push(feed.clone());
+    }
+    let mut writer = stream.try_clone().map_err(|error| error.to_string())?;
+    // stops after the close frame or once the client is gone, which drops the feed on the next broadcast
+    thread::spawn(move || {
+        for frame in frames {
+            let closing = frame.first() == Some(&0x88);
+            if writer.write_all(&frame).is_err() || closing {
+                break;
+            }
+        }
+        writer.shutdown(std::net::Shutdown::Both).ok();
+    });
+    while let Some((opcode, payload)) = read_frame(&mut reader) {
+        match opcode {
+            0x8 => {
+                feed.send(websocket_frame(0x8, &payload)).ok();
+                return Ok(());
+            }
+            0x9 => {
+                feed.send(websocket_frame(0xA, &payload)).ok();
+            }
+            _ => {}
+        }
+    }
+    stream.shutdown(std::net::Shutdown::Both).ok();
+    Ok(())
+}
+
+// the next frame from a client as (opcode, unmasked payload), None once the connection is gone
+fn read_frame(reader: &mut impl Read) -> Option<(u8, Vec<u8>)> {
+    let mut header = [0; 2];
+    reader.read_exact(&mut header).ok()?;
+    let opcode = header[0] & 0x0F;
+    let masked = header[1] & 0x80 != 0;
+    let length = match header[1] & 0x7F {
+        126 => {
+            let mut bytes = [0; 2];
+            reader.read_exact(&mut bytes).ok()?;
+            u16::from_be_bytes(bytes) as usize
+        }
+        127 => {
+            let mut bytes = [0; 8];
+            reader.read_exact(&mut bytes).ok()?;
+            u64::from_be_bytes(bytes) as usize
+        }
+        length => length as usize,
+    };
+    if length > MAX_BODY {
+        return None;
+    }
+    let mut mask = [0; 4];
+    if masked {
+        reader.read_exact(&mut mask).ok()?;
+    }
+    let mut payload = vec![0; length];
+    reader.read_exact(&mut payload).ok()?;
+    if masked {
+        for (index, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[index % 4];
+        }
+    }
+    Some((opcode, payload))
+}
+
+// a single unmasked frame, as servers send them
+fn websocket_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        length if length < 126 => frame.push(length as u8),
+        length if length <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> Result<(), String> {
+    respond_with(stream, status, "application/json", body)
+}
+
+fn respond_with(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> Result<(), String> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nAccess-Control-Allow-Headers: Content-Type\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+    .and_then(|_| stream.flush())
+    .map_err(|error| error.to_string())
+}
+
+fn error_json(error: &str) -> String {
+    format!("{{\"error\":{}}}", json_string(error))
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for symbol in text.chars() {
+        match symbol {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            symbol if (symbol as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", symbol as u32)),
+            symbol => json.push(symbol),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// %XX escapes in a URL, a '+' in the query is a space as in HTML forms and URLSearchParams
+// so a time control like 300+2 is written 300%2B2
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = text.get(index + 1..index + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// SHA-1 (FIPS 180-4), only needed for the WebSocket handshake
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (index, word) in block.chunks(4).enumerate() {
+            words[index] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for index in 16..80 {
+            words[index] = (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (index, word) in words.iter().enumerate() {
+            let (f, k) = match index {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+    let mut digest = [0; 20];
+    for (index, value) in state.iter().enumerate() {
+        digest[index * 4..index * 4 + 4].copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                text.push(ALPHABET[(bits >> (18 - 6 * index) & 0x3F) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    fn start() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, None));
+        address
+    }
+
+    // one request on its own connection, answered with (status code, body)
+    fn request(address: SocketAddr, method: &str, target: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}", method, target, body.len(), body).unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        let status = reply.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, reply.split_once("\r\n\r\n").unwrap().1.to_string())
+    }
+
+    // clients mask their frames
+    fn client_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x12, 0x34, 0x56, 0x78];
+        let mut frame = vec![0x80 | opcode, 0x80 | payload.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(index, byte)| byte ^ mask[index % 4]));
+        frame
+    }
+
+    #[test]
+    fn games_are_played_over_rest() {
+        let address = start();
+        let (status, body) = request(address, "POST", "/games", "");
+        assert_eq!(status, 201);
+        assert!(body.contains("\"id\":1"));
+        assert!(request(address, "GET", "/games/1/moves", "").1.contains("{\"uci\":\"g1f3\",\"san\":\"Nf3\"}"));
+        let (status, body) = request(address, "POST", "/games/1/moves?move=e2e4", "");
+        assert_eq!(status, 200);
+        assert!(body.contains("\"moves\":[\"e2e4\"]"));
+        assert_eq!(request(address, "POST", "/games/1/moves", "Nf6").0, 200);
+        assert_eq!(request(address, "POST", "/games/1/moves", "e4e6").0, 422);
+        assert_eq!(request(address, "GET", "/games/1/fen", ""), (200, String::from("rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2")));
+        assert!(request(address, "GET", "/games", "").1.starts_with("[{\"id\":1,"));
+        assert_eq!(request(address, "GET", "/games/2", "").0, 404);
+        assert_eq!(request(address, "GET", "/players", "").0, 404);
+    }
+
+    #[test]
+    fn plus_is_a_space_in_the_query() {
+        let address = start();
+        let (status, body) = request(address, "POST", "/games?fen=7k/8/8/8/8/8/8/K7+b+-+-+3+40&tc=60%2B1", "");
+        assert_eq!(status, 201);
+        assert!(body.contains("\"fen\":\"7k/8/8/8/8/8/8/K7 b - - 3 40\""));
+        assert!(body.contains("\"clock\":{\"white\":60000,\"black\":60000"));
+    }
+
+    #[test]
+    fn antichess_is_won_by_running_out_of_moves() {
+        let address = start();
+        // after h2h3 Black's only pawn is blocked
+        assert_eq!(request(address, "POST", "/games?variant=antichess&fen=8/8/8/8/8/p7/P6P/8+w+-+-+0+1", "").0, 201);
+        let (status, body) = request(address, "POST", "/games/1/moves", "h2h3");
+        assert_eq!(status, 200);
+        assert!(body.contains("\"result\":\"0-1\",\"termination\":\"no moves left\""));
+    }
+
+    #[test]
+    fn feeds_push_the_game() {
+        let address = start();
+        request(address, "POST", "/games", "");
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET /games/1/feed HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n").unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut handshake = String::new();
+        while !handshake.ends_with("\r\n\r\n") {
+            reader.read_line(&mut handshake).unwrap();
+        }
+        // the example key and answer from RFC 6455
+        assert!(handshake.starts_with("HTTP/1.1 101"));
+        assert!(handshake.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+        let (opcode, state) = read_frame(&mut reader).unwrap();
+        assert_eq!(opcode, 0x1);
+        assert!(String::from_utf8(state).unwrap().starts_with("{\"type\":\"state\",\"game\":{\"id\":1,"));
+
+        request(address, "POST", "/games/1/moves", "d4");
+        let (_, moved) = read_frame(&mut reader).unwrap();
+        assert!(String::from_utf8(moved).unwrap().starts_with("{\"type\":\"move\",\"move\":\"d2d4\",\"san\":\"d4\","));
+
+        stream.write_all(&client_frame(0x9, b"ping")).unwrap();
+        assert_eq!(read_frame(&mut reader), Some((0xA, b"ping".to_vec())));
+        stream.write_all(&client_frame(0x8, &[0x03, 0xE8])).unwrap();
+        assert_eq!(read_frame(&mut reader), Some((0x8, vec![0x03, 0xE8])));
+        assert_eq!(read_frame(&mut reader), None);
+    }
+}
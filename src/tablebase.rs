@@ -0,0 +1,1242 @@
+// Syzygy endgame tablebases: for every position with few enough pieces a WDL table (.rtbw) says
+// whether the side to move wins, draws or loses, and a DTZ table (.rtbz) how many plies it takes
+// to reach the next capture or pawn move (or mate) with best play
+// the decoding follows the format of the Syzygy generator as Stockfish's tbprobe.cpp reads it: the
+// positions of a table are numbered through the board's symmetries, and the list of values is
+// compressed by recursive pairing (RE-PAIR) and canonical Huffman codes in fixed-size blocks
+use crate::chess_move::Move;
+use crate::game::{Game, Variant};
+use crate::piece::{Color, PieceType};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// first four bytes of every Syzygy file
+pub(crate) const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+pub(crate) const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+// the most pieces a table can cover
+pub(crate) const MAX_PIECES: usize = 7;
+
+// pieces are coded as in the files: 1 to 6 for a White pawn, knight, bishop, rook, queen and king,
+// the same plus 8 for Black
+pub(crate) const PAWN: u8 = 1;
+pub(crate) const KING: u8 = 6;
+pub(crate) const BLACK: u8 = 8;
+
+// flags of the whole file
+pub(crate) const FILE_HAS_PAWNS: u8 = 2; // a table for each file of the leading pawn, a to d
+
+// flags of each table in a file
+pub(crate) const FLAG_STM: u8 = 1; // DTZ: the side to move the table is for, set for Black
+pub(crate) const FLAG_MAPPED: u8 = 2; // DTZ: values are looked up in the map after the headers
+pub(crate) const FLAG_WIN_PLIES: u8 = 4; // DTZ: wins are counted in plies rather than moves
+pub(crate) const FLAG_LOSS_PLIES: u8 = 8; // DTZ: the same for losses
+pub(crate) const FLAG_WIDE: u8 = 16; // DTZ: the map holds 16-bit values
+pub(crate) const FLAG_SINGLE_VALUE: u8 = 128; // every position has the same value
+
+// a symbol with this right half is a value, not a pair of symbols
+pub(crate) const LEAF: u16 = 0xfff;
+
+// Syzygy endgame tablebases found in one or more directories
+// each file is read the first time a position needs it
+pub struct Tablebases {
+    wdl: HashMap<String, TableFile>,
+    dtz: HashMap<String, TableFile>,
+    // most pieces in any table found, positions with more are never probed
+    pub max_pieces: usize,
+}
+
+struct TableFile {
+    path: PathBuf,
+    table: OnceLock<Result<Table, String>>,
+}
+
+impl Tablebases {
+    // 'path' lists directories separated by ':' (';' on Windows), like the UCI SyzygyPath option
+    // files that aren't Syzygy tables are left out
+    pub fn open(path: &str) -> Result<Tablebases, String> {
+        let separator = if cfg!(windows) { ';' } else { ':' };
+        let mut tables = Tablebases { wdl: HashMap::new(), dtz: HashMap::new(), max_pieces: 0 };
+        for directory in path.split(separator).filter(|directory| !directory.is_empty()) {
+            let entries = fs::read_dir(directory).map_err(|error| format!("{}: {}", directory, error))?;
+            for entry in entries.flatten() {
+                let file = entry.path();
+                let (name, extension) = match (file.file_stem().and_then(|s| s.to_str()), file.extension().and_then(|s| s.to_str())) {
+                    (Some(name), Some(extension)) => (name.to_string(), extension),
+                    _ => continue,
+                };
+                let (magic, map) = match extension {
+                    "rtbw" => (WDL_MAGIC, &mut tables.wdl),
+                    "rtbz" => (DTZ_MAGIC, &mut tables.dtz),
+                    _ => continue,
+                };
+                let Some(shape) = Shape::from_name(&name) else { continue };
+                if !has_magic(&file, magic) {
+                    continue;
+                }
+                tables.max_pieces = tables.max_pieces.max(shape.pieces);
+                map.insert(name, TableFile { path: file, table: OnceLock::new() });
+            }
+        }
+        Ok(tables)
+    }
+
+    pub fn len(&self) -> usize {
+        self.wdl.len() + self.dtz.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // win/draw/loss for the side to move
+    pub fn probe_wdl(&self, game: &Game) -> Result<Wdl, String> {
+        self.pieces(game)?;
+        Ok(Wdl::from_value(self.search(game, false)?.0))
+    }
+
+    // distance to zeroing (the next capture or pawn move) with best play, in plies: positive when the
+    // side to move wins, negative when it loses and 0 for a draw
+    // a win or loss that the fifty-move rule turns into a draw is 100 further away
+    pub fn probe_dtz(&self, game: &Game) -> Result<i32, String> {
+        self.pieces(game)?;
+        self.dtz(game)
+    }
+
+    // the legal moves that keep the best result the tables promise: the quickest way to the next
+    // capture or pawn move when winning, so the win is never lost to the fifty-move rule, and the
+    // slowest when losing
+    pub fn best_moves(&self, game: &Game) -> Result<Vec<Move>, String> {
+        self.pieces(game)?;
+        let mut ranked = Vec::new();
+        for mv in game.generate_moves() {
+            let zeroing = mv.piece_captured.is_some() || mv.piece_moved.piece_type == PieceType::Pawn;
+            let mut child = game.without_history();
+            child.make_move_without_state(&mv);
+            // the DTZ counted from the root, a zeroing move starts the count again
+            let mut dtz = if zeroing {
+                before_zeroing(-self.search(&child, false)?.0)
+            } else {
+                let dtz = -self.dtz(&child)?;
+                dtz + dtz.signum()
+            };
+            if dtz == 2 && child.is_in_checkmate() {
+                dtz = 1;
+            }
+            let rank = match dtz {
+                dtz if dtz > 0 => 1000 - dtz,
+                0 => 0,
+                dtz => -1000 - dtz,
+            };
+            ranked.push((rank, mv));
+        }
+        let best = ranked.iter().map(|(rank, _)| *rank).max();
+        Ok(ranked.into_iter().filter(|(rank, _)| Some(*rank) == best).map(|(_, mv)| mv).collect())
+    }
+
+    // the position's pieces if the tables can cover it
+    fn pieces(&self, game: &Game) -> Result<Vec<(u8, u8)>, String> {
+        if game.variant != Variant::Standard {
+            return Err(String::from("Syzygy tables only cover standard chess"));
+        }
+        if game.castling_rights().iter().any(|right| *right) {
+            return Err(String::from("Syzygy tables don't cover positions with castling rights"));
+        }
+        let mut pieces = Vec::new();
+        for square in 0..64u8 {
+            let Some(piece) = game.board.get_piece_at((square as usize / 8, square as usize % 8)) else { continue };
+            let code = type_code(piece.piece_type).ok_or("Syzygy tables only cover the standard pieces")?;
+            if code == PAWN && !(8..56).contains(&square) {
+                return Err(String::from("a pawn on the first or last rank"));
+            }
+            pieces.push((if piece.color == Color::Black { code | BLACK } else { code }, square));
+        }
+        if pieces.len() > self.max_pieces.max(2) {
+            return Err(format!("{} pieces, the tables found go up to {}", pieces.len(), self.max_pieces));
+        }
+        Ok(pieces)
+    }
+
+    // the WDL of a position with captures played out first (and pawn moves too with 'zeroing'):
+    // the tables know nothing of en passant, and may hold any value for a position whose best move
+    // is a capture, so the stored value only counts when no capture does as well
+    // also says whether the best move is one of those
+    fn search(&self, game: &Game, zeroing: bool) -> Result<(i32, bool), String> {
+        let moves = game.generate_moves();
+        let mut best = -2;
+        let mut searched = 0;
+        for mv in &moves {
+            if mv.piece_captured.is_none() && !(zeroing && mv.piece_moved.piece_type == PieceType::Pawn) {
+                continue;
+            }
+            searched += 1;
+            let mut child = game.without_history();
+            child.make_move_without_state(mv);
+            let value = -self.search(&child, false)?.0;
+            if value > best {
+                best = value;
+                if value == 2 {
+                    return Ok((value, true));
+                }
+            }
+        }
+        // with every move already searched the table isn't needed
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched { best } else { self.lookup(game, false, 0)?.unwrap_or(0) };
+        if best >= value {
+            return Ok((best, best > 0 || all_searched));
+        }
+        Ok((value, false))
+    }
+
+    fn dtz(&self, game: &Game) -> Result<i32, String> {
+        let (wdl, zeroing_best) = self.search(game, true)?;
+        if wdl == 0 {
+            return Ok(0);
+        }
+        if zeroing_best {
+            return Ok(before_zeroing(wdl));
+        }
+        if let Some(dtz) = self.lookup(game, true, wdl)? {
+            let cursed = if wdl.abs() == 1 { 100 } else { 0 };
+            return Ok((dtz + cursed) * wdl.signum());
+        }
+        // the table is for the other side to move, so the answer comes from the moves
+        let mut best = i32::MAX;
+        for mv in game.generate_moves() {
+            let zeroing = mv.piece_captured.is_some() || mv.piece_moved.piece_type == PieceType::Pawn;
+            let mut child = game.without_history();
+            child.make_move_without_state(&mv);
+            let mut dtz = if zeroing { -before_zeroing(self.search(&child, false)?.0) } else { -self.dtz(&child)? };
+            if dtz == 1 && child.is_in_checkmate() {
+                best = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < best && dtz.signum() == wdl.signum() {
+                best = dtz;
+            }
+        }
+        // no moves: checkmated
+        Ok(if best == i32::MAX { -1 } else { best })
+    }
+
+    // the value stored for the position, None when it's a DTZ table for the other side to move
+    fn lookup(&self, game: &Game, dtz: bool, wdl: i32) -> Result<Option<i32>, String> {
+        let pieces = self.pieces(game)?;
+        if pieces.len() == 2 {
+            return Ok(Some(0));
+        }
+        let (white, black) = (material(&pieces, 0), material(&pieces, BLACK));
+        let tables = if dtz { &self.dtz } else { &self.wdl };
+        let extension = if dtz { "rtbz" } else { "rtbw" };
+        // tables are only named for one side's point of view, so both orders are tried
+        let (name, flip) = [(format!("{}v{}", white, black), false), (format!("{}v{}", black, white), true)].into_iter()
+            .find(|(name, _)| tables.contains_key(name))
+            .ok_or(format!("no {}v{}.{} table", white, black, extension))?;
+        let file = &tables[&name];
+        let table = file.table
+            .get_or_init(|| fs::read(&file.path).map_err(|error| error.to_string()).and_then(|data| Table::parse(&name, data, dtz)))
+            .as_ref()
+            .map_err(|error| format!("{}: {}", file.path.display(), error))?;
+        table.lookup(&pieces, game.current_player == Color::Black, flip, wdl)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss, // lost, but the fifty-move rule saves a draw
+    Draw,
+    CursedWin, // won, but not within the fifty-move rule
+    Win,
+}
+
+impl Wdl {
+    // from the -2 to 2 the files store
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            ..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+}
+
+// the DTZ of a position whose best move is a capture or pawn move
+fn before_zeroing(wdl: i32) -> i32 {
+    match wdl {
+        2 => 1,
+        1 => 101,
+        -1 => -101,
+        -2 => -1,
+        _ => 0,
+    }
+}
+
+fn has_magic(file: &Path, magic: [u8; 4]) -> bool {
+    let mut start = [0; 4];
+    File::open(file).and_then(|mut file| file.read_exact(&mut start)).is_ok() && start == magic
+}
+
+pub(crate) fn type_code(piece_type: PieceType) -> Option<u8> {
+    match piece_type {
+        PieceType::Pawn => Some(1),
+        PieceType::Knight => Some(2),
+        PieceType::Bishop => Some(3),
+        PieceType::Rook => Some(4),
+        PieceType::Queen => Some(5),
+        PieceType::King => Some(6),
+        _ => None,
+    }
+}
+
+fn letter_code(letter: char) -> Option<u8> {
+    "PNBRQK".find(letter).map(|at| at as u8 + 1)
+}
+
+// one side's pieces in table name order, e.g. KRP
+pub(crate) fn material(pieces: &[(u8, u8)], color: u8) -> String {
+    let mut codes: Vec<u8> = pieces.iter().filter(|(code, _)| code & BLACK == color).map(|(code, _)| code & 7).collect();
+    codes.sort_unstable_by(|a, b| b.cmp(a));
+    codes.into_iter().map(|code| b"PNBRQK"[code as usize - 1] as char).collect()
+}
+
+fn rank_of(square: u8) -> u64 {
+    (square >> 3) as u64
+}
+
+// above the a1-h8 diagonal when positive, below it when negative
+fn off_diagonal(square: u8) -> i32 {
+    (square >> 3) as i32 - (square & 7) as i32
+}
+
+// the tables for numbering positions, the same for every file
+struct Maps {
+    // binomial[k][n]: the ways to choose k squares out of n
+    binomial: [[u64; 64]; MAX_PIECES],
+    // a2-h7 numbered 47 down to 0 from the edges inwards and from the bottom up, the leading pawn
+    // is the one with the highest number
+    map_pawns: [u64; 64],
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES],
+    lead_pawns_size: [[u64; 4]; MAX_PIECES],
+    // the a1-d1-d4 triangle numbered 0 to 9, the squares on the diagonal last
+    map_a1d1d4: [u64; 64],
+    // the squares below the a1-h8 diagonal numbered 0 to 27
+    map_b1h1h7: [u64; 64],
+    // the 462 ways to place two kings with the first in the a1-d1-d4 triangle
+    map_kk: [[u64; 64]; 10],
+}
+
+fn maps() -> &'static Maps {
+    static MAPS: OnceLock<Maps> = OnceLock::new();
+    MAPS.get_or_init(Maps::new)
+}
+
+impl Maps {
+    fn new() -> Maps {
+        let mut maps = Maps {
+            binomial: [[0; 64]; MAX_PIECES],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES],
+            lead_pawns_size: [[0; 4]; MAX_PIECES],
+            map_a1d1d4: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_kk: [[0; 64]; 10],
+        };
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                maps.map_b1h1h7[square as usize] = code;
+                code += 1;
+            }
+        }
+
+        // a1 to d4 in square order
+        let mut diagonal = Vec::new();
+        code = 0;
+        for square in 0..28 {
+            if square & 7 > 3 {
+                continue;
+            }
+            if off_diagonal(square) < 0 {
+                maps.map_a1d1d4[square as usize] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            maps.map_a1d1d4[square as usize] = code;
+            code += 1;
+        }
+
+        // with the first king on the diagonal the second isn't above it, and the positions with both
+        // on the diagonal come last
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            for first in 0..28u8 {
+                // b1 is the only square of the triangle numbered 0, the other squares are 0 by default
+                if maps.map_a1d1d4[first as usize] != idx || (idx == 0 && first != 1) || first & 7 > 3 {
+                    continue;
+                }
+                for second in 0..64u8 {
+                    let distance = (rank_of(first) as i32 - rank_of(second) as i32).abs().max(((first & 7) as i32 - (second & 7) as i32).abs());
+                    if distance <= 1 || (off_diagonal(first) == 0 && off_diagonal(second) > 0) {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((idx, second));
+                    } else {
+                        maps.map_kk[idx as usize][second as usize] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            maps.map_kk[idx as usize][second as usize] = code;
+            code += 1;
+        }
+
+        maps.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                maps.binomial[k][n] = if k > 0 { maps.binomial[k - 1][n - 1] } else { 0 } + if k < n { maps.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // the leading pawns are numbered per file, starting with the leading pawn on rank 2
+        let mut available = 47i64;
+        for lead_pawns in 1..=5 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        maps.map_pawns[square] = available as u64;
+                        maps.map_pawns[square ^ 7] = (available - 1) as u64;
+                        available -= 2;
+                    }
+                    maps.lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += maps.binomial[lead_pawns - 1][maps.map_pawns[square] as usize];
+                }
+                maps.lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+        maps
+    }
+}
+
+// what the material of a table says about how its positions are numbered
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Shape {
+    pub(crate) pieces: usize,
+    pub(crate) has_pawns: bool,
+    // some side has exactly one of a piece other than the king
+    pub(crate) unique: bool,
+    // pawns of the leading color (the side with fewer pawns, White when they have the same number
+    // or Black has none) and of the other side
+    pub(crate) pawns: [usize; 2],
+    // both sides have the same pieces, e.g. KRvKR
+    pub(crate) symmetric: bool,
+}
+
+impl Shape {
+    pub(crate) fn from_name(name: &str) -> Option<Shape> {
+        let (white, black) = name.split_once('v')?;
+        let mut counts = [[0; 7]; 2];
+        for (side, pieces) in [white, black].into_iter().enumerate() {
+            if !pieces.starts_with('K') {
+                return None;
+            }
+            for letter in pieces.chars() {
+                counts[side][letter_code(letter)? as usize] += 1;
+            }
+            if counts[side][KING as usize] != 1 {
+                return None;
+            }
+        }
+        let pieces = white.len() + black.len();
+        if pieces > MAX_PIECES {
+            return None;
+        }
+        let (white_pawns, black_pawns) = (counts[0][PAWN as usize], counts[1][PAWN as usize]);
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        Some(Shape {
+            pieces,
+            has_pawns: white_pawns + black_pawns > 0,
+            unique: counts.iter().any(|side| side[1..6].contains(&1)),
+            pawns: if white_leads { [white_pawns, black_pawns] } else { [black_pawns, white_pawns] },
+            symmetric: white == black,
+        })
+    }
+}
+
+// how the positions of one table are numbered: its pieces in the order they are encoded, split into
+// groups of pieces that are placed together, and what each group's number is multiplied by
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Encoding {
+    pub(crate) pieces: [u8; MAX_PIECES],
+    group_len: [usize; MAX_PIECES + 1],
+    group_idx: [u64; MAX_PIECES + 1],
+}
+
+impl Encoding {
+    // 'order' gives where the leading group and the other side's pawns come among the groups
+    pub(crate) fn new(shape: &Shape, pieces: &[u8], order: [usize; 2], file: usize) -> Encoding {
+        let maps = maps();
+        let mut encoding = Encoding::default();
+        encoding.pieces[..shape.pieces].copy_from_slice(&pieces[..shape.pieces]);
+        // without pawns the first two or three pieces always form the leading group
+        let mut first_len: i32 = if shape.has_pawns { 0 } else if shape.unique { 3 } else { 2 };
+        let mut n = 0;
+        encoding.group_len[0] = 1;
+        for i in 1..shape.pieces {
+            first_len -= 1;
+            if first_len > 0 || pieces[i] == pieces[i - 1] {
+                encoding.group_len[n] += 1;
+            } else {
+                n += 1;
+                encoding.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        encoding.group_len[n] = 0;
+
+        let both_pawns = shape.has_pawns && shape.pawns[1] > 0;
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - encoding.group_len[0] - if both_pawns { encoding.group_len[1] } else { 0 };
+        let mut idx = 1u64;
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                encoding.group_idx[0] = idx;
+                idx *= if shape.has_pawns {
+                    maps.lead_pawns_size[encoding.group_len[0].min(MAX_PIECES - 1)][file]
+                } else if shape.unique {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                encoding.group_idx[1] = idx;
+                idx *= maps.binomial[encoding.group_len[1]][48 - encoding.group_len[0]];
+            } else {
+                encoding.group_idx[next] = idx;
+                idx *= maps.binomial[encoding.group_len[next]][free_squares];
+                free_squares -= encoding.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        encoding.group_idx[n] = idx;
+        encoding
+    }
+
+    // how many positions the table numbers
+    pub(crate) fn size(&self) -> u64 {
+        let groups = self.group_len.iter().position(|len| *len == 0).unwrap_or(MAX_PIECES);
+        self.group_idx[groups]
+    }
+}
+
+// a position's pieces seen from the table: the leading pawns first, with the one that picks the
+// table's file at the front
+pub(crate) struct Placement {
+    squares: [u8; MAX_PIECES],
+    codes: [u8; MAX_PIECES],
+    len: usize,
+    lead_pawns: usize,
+    pub(crate) file: usize,
+}
+
+// 'pieces' are (code, square) pairs in square order, 'flip' swaps the colors and mirrors the board
+// top to bottom for a table named from Black's side, 'lead_pawn' is the code of the table's leading
+// pawns
+pub(crate) fn place(shape: &Shape, lead_pawn: u8, pieces: &[(u8, u8)], flip: bool) -> Placement {
+    let (flip_color, flip_square) = if flip { (BLACK, 56) } else { (0, 0) };
+    let mut placement = Placement { squares: [0; MAX_PIECES], codes: [0; MAX_PIECES], len: 0, lead_pawns: 0, file: 0 };
+    let leads = |code: u8| shape.has_pawns && code ^ flip_color == lead_pawn;
+    for &(code, square) in pieces.iter().filter(|(code, _)| leads(*code)) {
+        placement.squares[placement.len] = square ^ flip_square;
+        placement.codes[placement.len] = code ^ flip_color;
+        placement.len += 1;
+    }
+    placement.lead_pawns = placement.len;
+    if shape.has_pawns {
+        let map_pawns = &maps().map_pawns;
+        let lead = (0..placement.len).fold(0, |best, i| {
+            if map_pawns[placement.squares[i] as usize] > map_pawns[placement.squares[best] as usize] { i } else { best }
+        });
+        placement.squares.swap(0, lead);
+        let file = (placement.squares[0] & 7) as usize;
+        placement.file = file.min(7 - file);
+    }
+    for &(code, square) in pieces.iter().filter(|(code, _)| !leads(*code)) {
+        placement.squares[placement.len] = square ^ flip_square;
+        placement.codes[placement.len] = code ^ flip_color;
+        placement.len += 1;
+    }
+    placement
+}
+
+// the position's number in the table
+pub(crate) fn index(shape: &Shape, encoding: &Encoding, mut placement: Placement) -> u64 {
+    let maps = maps();
+    let len = placement.len;
+    // the pieces in the table's order
+    for i in placement.lead_pawns..len.saturating_sub(1) {
+        if let Some(j) = (i + 1..len).find(|&j| placement.codes[j] == encoding.pieces[i]) {
+            placement.codes.swap(i, j);
+            placement.squares.swap(i, j);
+        }
+    }
+    let lead_pawns = placement.lead_pawns;
+    let squares = &mut placement.squares[..len];
+    // mirror left to right so the leading piece is on files a to d
+    if squares[0] & 7 > 3 {
+        squares.iter_mut().for_each(|square| *square ^= 7);
+    }
+
+    let mut idx;
+    if shape.has_pawns {
+        idx = maps.lead_pawn_idx[lead_pawns][squares[0] as usize];
+        squares[1..lead_pawns].sort_by_key(|square| maps.map_pawns[*square as usize]);
+        for (i, square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+            idx += maps.binomial[i][maps.map_pawns[*square as usize] as usize];
+        }
+    } else {
+        // without pawns, also mirror top to bottom so the leading piece is on ranks 1 to 4, and along
+        // the a1-h8 diagonal so the first piece of the leading group off it is below it
+        if squares[0] >> 3 > 3 {
+            squares.iter_mut().for_each(|square| *square ^= 56);
+        }
+        for i in 0..encoding.group_len[0] {
+            match off_diagonal(squares[i]) {
+                0 => continue,
+                off if off > 0 => squares[i..].iter_mut().for_each(|square| *square = ((*square >> 3) | (*square << 3)) & 63),
+                _ => {}
+            }
+            break;
+        }
+        let (s0, s1) = (squares[0], squares[1]);
+        idx = if shape.unique {
+            let s2 = squares[2];
+            let adjust1 = (s1 > s0) as u64;
+            let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+            if off_diagonal(s0) != 0 {
+                (maps.map_a1d1d4[s0 as usize] * 63 + (s1 as u64 - adjust1)) * 62 + s2 as u64 - adjust2
+            } else if off_diagonal(s1) != 0 {
+                (6 * 63 + rank_of(s0) * 28 + maps.map_b1h1h7[s1 as usize]) * 62 + s2 as u64 - adjust2
+            } else if off_diagonal(s2) != 0 {
+                6 * 63 * 62 + 4 * 28 * 62 + rank_of(s0) * 7 * 28 + (rank_of(s1) - adjust1) * 28 + maps.map_b1h1h7[s2 as usize]
+            } else {
+                6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank_of(s0) * 7 * 6 + (rank_of(s1) - adjust1) * 6 + (rank_of(s2) - adjust2)
+            }
+        } else {
+            maps.map_kk[maps.map_a1d1d4[s0 as usize] as usize][s1 as usize]
+        };
+    }
+
+    // the other groups by square, each square counted among those the earlier groups left free
+    idx *= encoding.group_idx[0];
+    let mut start = encoding.group_len[0];
+    let mut remaining_pawns = shape.has_pawns && shape.pawns[1] > 0;
+    let mut next = 1;
+    while encoding.group_len[next] != 0 {
+        let group = start..start + encoding.group_len[next];
+        squares[group.clone()].sort_unstable();
+        let mut n = 0;
+        for (i, &square) in squares[group.clone()].iter().enumerate() {
+            let adjust = squares[..start].iter().filter(|&&earlier| square > earlier).count();
+            let free = (square as usize - adjust).saturating_sub(if remaining_pawns { 8 } else { 0 });
+            n += maps.binomial[i + 1][free];
+        }
+        remaining_pawns = false;
+        idx += n * encoding.group_idx[next];
+        start = group.end;
+        next += 1;
+    }
+    idx
+}
+
+// reads a file front to back
+struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.data.get(self.at).ok_or("the file is cut short")?;
+        self.at += 1;
+        Ok(byte)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes([self.byte()?, self.byte()?]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes([self.byte()?, self.byte()?, self.byte()?, self.byte()?]))
+    }
+}
+
+fn le(data: &[u8], at: usize, bytes: usize) -> Option<u64> {
+    let bytes = data.get(at..at + bytes)?;
+    Some(bytes.iter().rev().fold(0, |value, byte| value << 8 | *byte as u64))
+}
+
+// big-endian, reading zeros past the end: the last symbols of a block can be read with a few
+// bytes past them
+fn be(data: &[u8], at: usize, bytes: usize) -> u64 {
+    (at..at + bytes).fold(0, |value, at| value << 8 | *data.get(at).unwrap_or(&0) as u64)
+}
+
+// one table of a file: the values for one side to move (and one leading pawn file)
+#[derive(Clone, Debug, Default)]
+struct Pairs {
+    encoding: Encoding,
+    flags: u8,
+    // file offsets of the parts below
+    sparse_index: usize,
+    block_lengths: usize,
+    blocks: usize,
+    lowest_sym: usize,
+    btree: usize,
+    block_size: usize,
+    // every 'span' values the sparse index says which block holds the value
+    span: u64,
+    sparse_index_size: usize,
+    block_length_size: usize,
+    blocks_num: usize,
+    min_sym_len: u8,
+    // the lowest code of each length, left-aligned
+    base64: Vec<u64>,
+    // how many values minus one each symbol stands for
+    symlen: Vec<u32>,
+    // where the DTZ map for each result starts, plus one
+    map_idx: [usize; 4],
+}
+
+impl Pairs {
+    fn read_sizes(&mut self, reader: &mut Reader) -> Result<(), String> {
+        self.flags = reader.byte()?;
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            // the value itself
+            self.min_sym_len = reader.byte()?;
+            return Ok(());
+        }
+        let (block_size, span) = (reader.byte()?, reader.byte()?);
+        if block_size > 24 || span > 32 {
+            return Err(String::from("bad block size"));
+        }
+        self.block_size = 1 << block_size;
+        self.span = 1 << span;
+        self.sparse_index_size = self.encoding.size().div_ceil(self.span) as usize;
+        let padding = reader.byte()? as usize;
+        self.blocks_num = reader.u32()? as usize;
+        self.block_length_size = self.blocks_num + padding;
+        let max_sym_len = reader.byte()?;
+        self.min_sym_len = reader.byte()?;
+        if self.min_sym_len == 0 || max_sym_len < self.min_sym_len || max_sym_len > 32 {
+            return Err(String::from("bad symbol lengths"));
+        }
+        self.lowest_sym = reader.at;
+        let lengths = (max_sym_len - self.min_sym_len + 1) as usize;
+        let lowest = |i: usize| le(reader.data, self.lowest_sym + 2 * i, 2).ok_or("the file is cut short");
+        // canonical Huffman codes: longer codes have lower values, and the codes of one length are
+        // consecutive, numbered like their symbols
+        self.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            self.base64[i] = (self.base64[i + 1] + lowest(i)?).wrapping_sub(lowest(i + 1)?) / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            *base <<= 64 - i - self.min_sym_len as usize;
+        }
+        reader.at += 2 * lengths;
+        let symbols = reader.u16()? as usize;
+        self.btree = reader.at;
+        reader.at += 3 * symbols + (symbols & 1);
+        if reader.at > reader.data.len() {
+            return Err(String::from("the file is cut short"));
+        }
+        self.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] {
+                self.symlen[symbol] = self.set_symlen(reader.data, symbol, &mut visited)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_symlen(&mut self, data: &[u8], symbol: usize, visited: &mut [bool]) -> Result<u32, String> {
+        visited[symbol] = true;
+        let (left, right) = self.pair(data, symbol);
+        if right == LEAF as usize {
+            return Ok(0);
+        }
+        if left >= visited.len() || right >= visited.len() {
+            return Err(String::from("bad symbol"));
+        }
+        for side in [left, right] {
+            if !visited[side] {
+                self.symlen[side] = self.set_symlen(data, side, visited)?;
+            }
+        }
+        Ok(self.symlen[left] + self.symlen[right] + 1)
+    }
+
+    // the two halves of a symbol, a value and LEAF for a symbol standing for one value
+    fn pair(&self, data: &[u8], symbol: usize) -> (usize, usize) {
+        let at = self.btree + 3 * symbol;
+        let (a, b, c) = (data[at] as usize, data[at + 1] as usize, data[at + 2] as usize);
+        ((b & 0xf) << 8 | a, c << 4 | b >> 4)
+    }
+
+    // the value at 'idx'
+    fn decompress(&self, data: &[u8], idx: u64) -> Option<u32> {
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            return Some(self.min_sym_len as u32);
+        }
+        // the sparse index entry k gives the block holding value k * span + span / 2 and where in it,
+        // from there the blocks are walked to the one holding 'idx'
+        let k = (idx / self.span) as usize;
+        if k >= self.sparse_index_size {
+            return None;
+        }
+        let entry = self.sparse_index + 6 * k;
+        let mut block = le(data, entry, 4)? as usize;
+        let mut offset = le(data, entry + 4, 2)? as i64 + (idx % self.span) as i64 - (self.span / 2) as i64;
+        let block_length = |block: usize| -> Option<i64> {
+            if block >= self.block_length_size {
+                return None;
+            }
+            Some(le(data, self.block_lengths + 2 * block, 2)? as i64)
+        };
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        // the block is a run of codes, read 32 bits at a time
+        let mut at = self.blocks + block * self.block_size;
+        let mut buffer = be(data, at, 8);
+        at += 8;
+        let mut buffer_size = 64;
+        let min_len = self.min_sym_len as usize;
+        let lowest = |len: usize| le(data, self.lowest_sym + 2 * len, 2);
+        let mut symbol;
+        loop {
+            let mut len = 0;
+            while buffer < self.base64[len] {
+                len += 1;
+                if len == self.base64.len() {
+                    return None;
+                }
+            }
+            symbol = ((buffer - self.base64[len]) >> (64 - len - min_len)) as usize + lowest(len)? as usize;
+            let count = *self.symlen.get(symbol)? as i64 + 1;
+            if offset < count {
+                break;
+            }
+            offset -= count;
+            buffer <<= len + min_len;
+            buffer_size -= len + min_len;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= be(data, at, 4) << (64 - buffer_size);
+                at += 4;
+            }
+        }
+        // down the pairs to the value
+        while self.symlen[symbol] != 0 {
+            let (left, right) = self.pair(data, symbol);
+            let count = *self.symlen.get(left)? as i64 + 1;
+            if offset < count {
+                symbol = left;
+            } else {
+                offset -= count;
+                symbol = right;
+            }
+            self.symlen.get(symbol)?;
+        }
+        Some(self.pair(data, symbol).0 as u32)
+    }
+}
+
+// a WDL or DTZ file
+pub(crate) struct Table {
+    data: Vec<u8>,
+    shape: Shape,
+    dtz: bool,
+    // [side to move][file of the leading pawn], a single side for DTZ files and symmetric material
+    tables: Vec<Vec<Pairs>>,
+    map: usize,
+}
+
+impl Table {
+    pub(crate) fn parse(name: &str, data: Vec<u8>, dtz: bool) -> Result<Table, String> {
+        let shape = Shape::from_name(name).ok_or(format!("'{}' isn't a table name", name))?;
+        let magic = if dtz { DTZ_MAGIC } else { WDL_MAGIC };
+        if data.get(..4) != Some(&magic[..]) {
+            return Err(String::from("not a Syzygy table"));
+        }
+        let mut reader = Reader { data: &data, at: 4 };
+        let flags = reader.byte()?;
+        if (flags & FILE_HAS_PAWNS != 0) != shape.has_pawns {
+            return Err(format!("the pawns don't match the name {}", name));
+        }
+        let sides = if !dtz && !shape.symmetric { 2 } else { 1 };
+        let files = if shape.has_pawns { 4 } else { 1 };
+        let both_pawns = shape.has_pawns && shape.pawns[1] > 0;
+        let mut tables = vec![Vec::new(); sides];
+        for file in 0..files {
+            let first = reader.byte()?;
+            let second = if both_pawns { reader.byte()? } else { 0xff };
+            let orders = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            let mut pieces = [[0; MAX_PIECES]; 2];
+            for k in 0..shape.pieces {
+                let byte = reader.byte()?;
+                pieces[0][k] = byte & 0xf;
+                pieces[1][k] = byte >> 4;
+            }
+            for (side, table) in tables.iter_mut().enumerate() {
+                if pieces[side][..shape.pieces].iter().any(|code| !matches!(code & 7, PAWN..=KING)) {
+                    return Err(String::from("bad piece codes"));
+                }
+                let encoding = Encoding::new(&shape, &pieces[side], orders[side].map(|order| order as usize), file);
+                table.push(Pairs { encoding, ..Pairs::default() });
+            }
+        }
+        reader.at += reader.at & 1;
+
+        for file in 0..files {
+            for side in tables.iter_mut() {
+                side[file].read_sizes(&mut reader)?;
+            }
+        }
+        let map = reader.at;
+        if dtz {
+            for pairs in tables[0].iter_mut().filter(|pairs| pairs.flags & FLAG_MAPPED != 0) {
+                if pairs.flags & FLAG_WIDE != 0 {
+                    reader.at += reader.at & 1;
+                    for i in 0..4 {
+                        pairs.map_idx[i] = (reader.at - map) / 2 + 1;
+                        reader.at += 2 * reader.u16()? as usize;
+                    }
+                } else {
+                    for i in 0..4 {
+                        pairs.map_idx[i] = reader.at - map + 1;
+                        reader.at += reader.byte()? as usize;
+                    }
+                }
+            }
+            reader.at += reader.at & 1;
+        }
+        for file in 0..files {
+            for side in tables.iter_mut() {
+                side[file].sparse_index = reader.at;
+                reader.at += 6 * side[file].sparse_index_size;
+            }
+        }
+        for file in 0..files {
+            for side in tables.iter_mut() {
+                side[file].block_lengths = reader.at;
+                reader.at += 2 * side[file].block_length_size;
+            }
+        }
+        for file in 0..files {
+            for side in tables.iter_mut() {
+                reader.at = (reader.at + 63) & !63;
+                side[file].blocks = reader.at;
+                reader.at += side[file].blocks_num * side[file].block_size;
+                if side[file].blocks_num > 0 && reader.at > data.len() {
+                    return Err(String::from("the file is cut short"));
+                }
+            }
+        }
+        Ok(Table { data, shape, dtz, tables, map })
+    }
+
+    // the value stored for the pieces, or None for a DTZ table that holds the other side to move
+    // 'flip' is set when the table is named from Black's side
+    pub(crate) fn lookup(&self, pieces: &[(u8, u8)], black_to_move: bool, flip: bool, wdl: i32) -> Result<Option<i32>, String> {
+        if pieces.len() != self.shape.pieces {
+            return Err(String::from("the pieces don't match the table"));
+        }
+        // with the same pieces on both sides only White to move is stored
+        let flip = flip || (self.shape.symmetric && black_to_move);
+        let side = (flip ^ black_to_move) as usize;
+        let placement = place(&self.shape, self.tables[0][0].encoding.pieces[0], pieces, flip);
+        // symmetric tables without pawns hold both sides to move in one
+        let both_sides = self.shape.symmetric && !self.shape.has_pawns;
+        if self.dtz && !both_sides && (self.tables[0][placement.file].flags & FLAG_STM) as usize != side {
+            return Ok(None);
+        }
+        let pairs = &self.tables[side % self.tables.len()][placement.file];
+        let idx = index(&self.shape, &pairs.encoding, placement);
+        let value = pairs.decompress(&self.data, idx).ok_or("the table is corrupt")?;
+        if !self.dtz {
+            return Ok(Some(value as i32 - 2));
+        }
+        Ok(Some(self.dtz_plies(pairs, value, wdl).ok_or("the table is corrupt")?))
+    }
+
+    // the stored DTZ turned into plies
+    fn dtz_plies(&self, pairs: &Pairs, value: u32, wdl: i32) -> Option<i32> {
+        const RESULT_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+        let mut value = value as usize;
+        if pairs.flags & FLAG_MAPPED != 0 {
+            let at = pairs.map_idx[RESULT_MAP[(wdl + 2) as usize]] + value;
+            value = if pairs.flags & FLAG_WIDE != 0 { le(&self.data, self.map + 2 * at, 2)? as usize } else { *self.data.get(self.map + at)? as usize };
+        }
+        let in_moves = match wdl {
+            2 => pairs.flags & FLAG_WIN_PLIES == 0,
+            -2 => pairs.flags & FLAG_LOSS_PLIES == 0,
+            _ => true,
+        };
+        if in_moves {
+            value *= 2;
+        }
+        Some(value as i32 + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen;
+    use std::collections::HashSet;
+
+    fn fixtures() -> Tablebases {
+        Tablebases::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy")).unwrap()
+    }
+
+    fn probe(tables: &Tablebases, text: &str) -> (Wdl, i32) {
+        let game = fen::parse(text).unwrap();
+        (tables.probe_wdl(&game).unwrap(), tables.probe_dtz(&game).unwrap())
+    }
+
+    // a small generator of squares, the same every run
+    fn random_square(seed: &mut u64) -> u8 {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (*seed >> 58) as u8
+    }
+
+    // positions of a table's material placed at random, legal with the side to move alternating
+    fn random_positions(name: &str, count: usize) -> Vec<Game> {
+        let mut seed = name.bytes().fold(7, |seed, byte| seed * 31 + byte as u64);
+        let (white, black) = name.split_once('v').unwrap();
+        let pieces: Vec<char> = white.chars().chain(black.chars().map(|letter| letter.to_ascii_lowercase())).collect();
+        let mut positions = Vec::new();
+        while positions.len() < count {
+            let mut cells = [None; 64];
+            for &letter in &pieces {
+                let square = random_square(&mut seed) as usize;
+                // pawns never stand on the first or last rank
+                let allowed = (8..56).contains(&square) || !letter.eq_ignore_ascii_case(&'p');
+                if cells[square].is_none() && allowed {
+                    cells[square] = Some(letter);
+                }
+            }
+            if cells.iter().flatten().count() != pieces.len() {
+                continue;
+            }
+            let side = if positions.len() % 2 == 0 { "w" } else { "b" };
+            let mut game = fen::parse(&format!("{} {} - - 0 1", placement(&cells), side)).unwrap();
+            // the side that just moved can't be in check
+            game.switch_player();
+            let illegal = game.is_in_check();
+            game.switch_player();
+            if !illegal {
+                positions.push(game);
+            }
+        }
+        positions
+    }
+
+    // the first field of a FEN
+    fn placement(cells: &[Option<char>; 64]) -> String {
+        let ranks: Vec<String> = (0..8).rev().map(|rank| {
+            let mut text = String::new();
+            let mut empty = 0;
+            for cell in &cells[rank * 8..rank * 8 + 8] {
+                match cell {
+                    Some(letter) => {
+                        if empty > 0 {
+                            text.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        text.push(*letter);
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                text.push_str(&empty.to_string());
+            }
+            text
+        }).collect();
+        ranks.join("/")
+    }
+
+    #[test]
+    fn maps_number_every_placement_once() {
+        let maps = maps();
+        let mut kings = HashSet::new();
+        for idx in 0..10 {
+            kings.extend(maps.map_kk[idx].iter().copied().filter(|code| *code != 0));
+        }
+        // 0 is also b1 with the other king on a3, counted here as the missing one
+        assert_eq!(kings.len() + 1, 462);
+        assert_eq!(maps.map_pawns[8], 47);
+        assert_eq!(maps.map_pawns[15], 46);
+        assert_eq!(maps.binomial[2][62], 1891);
+        assert_eq!((0..4).map(|file| maps.lead_pawns_size[1][file]).sum::<u64>(), 24);
+        let shape = Shape::from_name("KQvKR").unwrap();
+        assert_eq!(Encoding::new(&shape, &[6, 14, 5, 12], [0, 0xf], 0).size(), 31332 * 61);
+    }
+
+    #[test]
+    fn open_skips_files_that_are_not_tables() {
+        let directory = std::env::temp_dir().join(format!("final_proj_syzygy_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("KQvK.rtbw"), b"not a table").unwrap();
+        fs::write(directory.join("README.rtbw"), WDL_MAGIC).unwrap();
+        fs::copy(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy/KRvK.rtbw"), directory.join("KRvK.rtbw")).unwrap();
+        let tables = Tablebases::open(directory.to_str().unwrap()).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables.max_pieces, 3);
+    }
+
+    #[test]
+    fn known_positions() {
+        let tables = fixtures();
+        // mate in one, the queen move doesn't zero but mates
+        assert_eq!(probe(&tables, "7k/8/6K1/8/8/8/8/Q7 w - - 0 1"), (Wdl::Win, 1));
+        assert_eq!(probe(&tables, "7k/6Q1/6K1/8/8/8/8/8 b - - 0 1"), (Wdl::Loss, -1));
+        // stalemate, and the rook can't be taken
+        assert_eq!(probe(&tables, "8/8/8/8/8/2k5/1r6/K7 w - - 0 1"), (Wdl::Draw, 0));
+        assert_eq!(probe(&tables, "8/8/8/8/8/2k5/1R6/K7 b - - 0 1").0, Wdl::Loss);
+        // stalemate, but with White to move Kf6 and Kf7 wins
+        assert_eq!(probe(&tables, "4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), (Wdl::Draw, 0));
+        assert_eq!(probe(&tables, "4k3/4P3/4K3/8/8/8/8/8 w - - 0 1").0, Wdl::Win);
+        // the king in front of its pawn with the opposition, Black to move loses
+        assert_eq!(probe(&tables, "4k3/8/4K3/4P3/8/8/8/8 b - - 0 1").0, Wdl::Loss);
+        assert_eq!(probe(&tables, "8/8/8/8/8/k7/p7/K7 w - - 0 1").0, Wdl::Draw);
+        assert_eq!(probe(&tables, "8/8/8/8/8/8/4k3/1KN5 w - - 0 1"), (Wdl::Draw, 0));
+        assert_eq!(probe(&tables, "8/8/3k4/8/8/8/1RR5/4K3 b - - 0 1").0, Wdl::Loss);
+        // KQvKR with the side to move winning the rook at once
+        assert_eq!(probe(&tables, "8/8/8/8/3k4/8/3r4/3QK3 w - - 0 1"), (Wdl::Win, 1));
+        assert_eq!(probe(&tables, "8/8/8/8/3k4/8/3r4/3QK3 b - - 0 1"), (Wdl::Draw, 0));
+        assert_eq!(probe(&tables, "8/8/8/8/2k5/8/8/KQ1r4 b - - 0 1").0, Wdl::Draw);
+    }
+
+    #[test]
+    fn colors_can_be_swapped() {
+        let tables = fixtures();
+        assert_eq!(probe(&tables, "8/8/8/4k3/8/8/8/K6q w - - 0 1"), probe(&tables, "k6Q/8/8/8/4K3/8/8/8 b - - 0 1"));
+        assert_eq!(probe(&tables, "8/8/8/8/8/8/2Kp4/k7 b - - 0 1"), probe(&tables, "K7/2kP4/8/8/8/8/8/8 w - - 0 1"));
+    }
+
+    // every stored value agrees with the moves out of the position, played with the game's rules
+    #[test]
+    fn values_follow_the_moves() {
+        let tables = fixtures();
+        for name in ["KQvK", "KRvK", "KBvK", "KNvK", "KPvK", "KRRvK", "KQvKR"] {
+            for game in random_positions(name, 40) {
+                let wdl = tables.probe_wdl(&game).unwrap() as i32 - 2;
+                let dtz = tables.probe_dtz(&game).unwrap();
+                let moves = game.generate_moves();
+                let mut best_wdl = if moves.is_empty() && !game.is_in_check() { 0 } else { -2 };
+                let mut best_dtz = if moves.is_empty() { if game.is_in_check() { -1 } else { 0 } } else { i32::MAX };
+                for mv in &moves {
+                    let mut child = game.without_history();
+                    child.make_move_without_state(mv);
+                    let child_wdl = -(tables.probe_wdl(&child).unwrap() as i32 - 2);
+                    best_wdl = best_wdl.max(child_wdl);
+                    let zeroing = mv.piece_captured.is_some() || mv.piece_moved.piece_type == PieceType::Pawn;
+                    let mut child_dtz = if zeroing { before_zeroing(child_wdl) } else { -tables.probe_dtz(&child).unwrap() };
+                    if !zeroing {
+                        child_dtz += child_dtz.signum();
+                    }
+                    if child.is_in_checkmate() {
+                        child_dtz = 1;
+                    }
+                    if child_dtz.signum() == wdl.signum() && wdl != 0 {
+                        best_dtz = best_dtz.min(child_dtz);
+                    }
+                }
+                let position = fen::to_fen(&game);
+                assert_eq!(wdl, best_wdl, "{}", position);
+                if wdl == 0 {
+                    assert_eq!(dtz, 0, "{}", position);
+                } else if !moves.is_empty() {
+                    assert_eq!(dtz, best_dtz, "{}", position);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn best_moves_make_progress() {
+        let tables = fixtures();
+        let mut game = fen::parse("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").unwrap();
+        let start = tables.probe_dtz(&game).unwrap();
+        let mut previous = start;
+        // each side's best move brings the mate one ply closer
+        for _ in 0..start {
+            let mv = tables.best_moves(&game).unwrap()[0];
+            game.make_move(&mv);
+            if game.is_in_checkmate() {
+                break;
+            }
+            let dtz = tables.probe_dtz(&game).unwrap();
+            assert_eq!(dtz.abs(), previous.abs() - 1);
+            previous = dtz;
+        }
+        assert!(game.is_in_checkmate());
+    }
+
+    // against the official files, fetched into tests/syzygy/official by fetch_official.sh or found
+    // through SYZYGY_PATH; the fixtures come from this crate's own generator, so only this shows
+    // that both read the format the way the Syzygy generator writes it
+    #[test]
+    fn official_tables_agree() {
+        let official = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy/official");
+        let path = match std::env::var("SYZYGY_PATH") {
+            Ok(path) => path,
+            Err(_) if Path::new(official).is_dir() => official.to_string(),
+            Err(_) => {
+                eprintln!("no official tables in {}, run tests/syzygy/fetch_official.sh", official);
+                return;
+            }
+        };
+        let official = Tablebases::open(&path).unwrap();
+        let tables = fixtures();
+        for name in ["KQvK", "KRvK", "KBvK", "KNvK", "KPvK", "KRRvK", "KQvKR"] {
+            for game in random_positions(name, 500) {
+                let position = fen::to_fen(&game);
+                assert_eq!(official.probe_wdl(&game), tables.probe_wdl(&game), "{}", position);
+                // the official files mostly count in moves, so their DTZ can be a ply longer
+                let (expected, dtz) = (official.probe_dtz(&game).unwrap(), tables.probe_dtz(&game).unwrap());
+                assert!(expected.signum() == dtz.signum() && (expected - dtz).abs() <= 1, "{}: {} against {}", position, expected, dtz);
+            }
+        }
+    }
+}
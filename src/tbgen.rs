@@ -0,0 +1,818 @@
+// writes Syzygy tables for small endings, worked out by retrograde analysis
+// only the tests use it: the fixtures under tests/syzygy come from the ignored write_fixtures test,
+// `cargo test write_fixtures -- --ignored` makes them again
+// it covers endings of up to four pieces without pawns or with White pawns only; the endings that
+// captures and promotions lead to are solved along the way, but only the named ones are written
+// the fifty-move rule isn't modelled, so an ending with a longer way to the next capture or pawn
+// move is refused rather than written with cursed wins
+use crate::tablebase::{
+    self, Encoding, Shape, Table, BLACK, DTZ_MAGIC, FILE_HAS_PAWNS, FLAG_LOSS_PLIES,
+    FLAG_SINGLE_VALUE, FLAG_WIN_PLIES, KING, LEAF, PAWN, WDL_MAGIC,
+};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs;
+use std::path::Path;
+
+// set in the file's flags when there is a table for each side to move, the decoder works that out
+// from the name instead
+const FILE_SPLIT: u8 = 1;
+// the position arrays grow 64 times with each piece
+const MAX_GENERATED: usize = 4;
+// plies to zeroing past which the fifty-move rule would matter
+const FIFTY_MOVES: usize = 100;
+// marks a placement that isn't a legal position
+const INVALID: i8 = i8::MIN;
+
+// what is known about a position while its slice is solved
+const UNKNOWN: u8 = 0;
+const CANT_LOSE: u8 = 1; // a capture or pawn move draws, or there is no move at all
+const WON: u8 = 2;
+const LOST: u8 = 3;
+const ILLEGAL: u8 = 4;
+
+// how the compressed tables are laid out
+const BLOCK_SIZE_LOG: u8 = 8;
+const SPAN_LOG: u8 = 12;
+const MAX_BLOCK_VALUES: u32 = 32768;
+// the values one symbol may stand for, and the most symbols a table may have
+const MAX_SYMBOL_VALUES: u32 = 4096;
+const MAX_SYMBOLS: usize = 4095;
+const MAX_CODE_LEN: u8 = 24;
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_STEPS: [(i8, i8); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+
+fn generate(directory: &Path, names: &[&str]) -> Result<(), String> {
+    let mut solved = HashMap::new();
+    for name in names {
+        solve(name, &mut solved)?;
+        let table = &solved[*name];
+        for dtz in [false, true] {
+            let bytes = write(name, table, dtz)?;
+            check(name, table, &bytes, dtz)?;
+            let path = directory.join(format!("{}.{}", name, if dtz { "rtbz" } else { "rtbw" }));
+            fs::write(&path, bytes).map_err(|error| format!("{}: {}", path.display(), error))?;
+            println!("{}: {} positions", path.display(), table.wdl.iter().filter(|wdl| **wdl != INVALID).count());
+        }
+    }
+    Ok(())
+}
+
+// an ending with every placement of its pieces solved, for either side to move
+// position 'idx' has piece i on square (idx >> 6i) & 63 and Black to move when bit 6n is set
+struct Solved {
+    codes: Vec<u8>,
+    wdl: Vec<i8>,
+    // plies to the next capture, pawn move or mate, 0 for a position that is checkmated
+    level: Vec<u8>,
+}
+
+impl Solved {
+    fn decode(&self, idx: usize) -> ([u8; MAX_GENERATED], bool) {
+        let mut squares = [0; MAX_GENERATED];
+        for (i, square) in squares.iter_mut().enumerate().take(self.codes.len()) {
+            *square = (idx >> (6 * i) & 63) as u8;
+        }
+        (squares, idx >> (6 * self.codes.len()) & 1 == 1)
+    }
+
+    // the pieces of a position as the tables take them, in square order
+    fn pieces(&self, squares: &[u8]) -> Vec<(u8, u8)> {
+        let mut pieces: Vec<(u8, u8)> = self.codes.iter().copied().zip(squares.iter().copied()).collect();
+        pieces.sort_unstable_by_key(|(_, square)| *square);
+        pieces
+    }
+}
+
+fn encode(squares: &[u8], black_to_move: bool) -> usize {
+    squares.iter().rev().fold(black_to_move as usize, |idx, square| idx << 6 | *square as usize)
+}
+
+fn codes_of(name: &str) -> Option<Vec<u8>> {
+    let (white, black) = name.split_once('v')?;
+    let code = |letter: char| "PNBRQK".find(letter).map(|at| at as u8 + 1);
+    let mut codes: Vec<u8> = white.chars().map(code).collect::<Option<_>>()?;
+    for letter in black.chars() {
+        codes.push(code(letter)? | BLACK);
+    }
+    Some(codes)
+}
+
+// the name of a table, from the stronger side
+fn table_name(pieces: &[(u8, u8)]) -> String {
+    let (white, black) = (tablebase::material(pieces, 0), tablebase::material(pieces, BLACK));
+    let strength = |side: &str| (side.len(), side.chars().map(|letter| "PNBRQ".find(letter).map_or(0, |at| [1, 3, 3, 5, 9][at])).sum::<u32>());
+    if strength(&black) > strength(&white) { format!("{}v{}", black, white) } else { format!("{}v{}", white, black) }
+}
+
+// the endings a capture or a promotion leads to
+fn dependencies(codes: &[u8]) -> Vec<String> {
+    let pieces: Vec<(u8, u8)> = codes.iter().map(|code| (*code, 0)).collect();
+    let mut endings = Vec::new();
+    for (i, &(code, _)) in pieces.iter().enumerate() {
+        if code & 7 == KING {
+            continue;
+        }
+        let mut captured = pieces.clone();
+        captured.remove(i);
+        endings.push(captured);
+        if code == PAWN {
+            for promotion in 2..=5 {
+                let mut promoted = pieces.clone();
+                promoted[i].0 = promotion;
+                endings.push(promoted.clone());
+                for (j, &(code, _)) in pieces.iter().enumerate() {
+                    if code & BLACK != 0 && code & 7 != KING {
+                        let mut both = promoted.clone();
+                        both.remove(j);
+                        endings.push(both);
+                    }
+                }
+            }
+        }
+    }
+    endings.iter().filter(|pieces| pieces.len() > 2).map(|pieces| table_name(pieces)).collect()
+}
+
+fn solve(name: &str, solved: &mut HashMap<String, Solved>) -> Result<(), String> {
+    if solved.contains_key(name) {
+        return Ok(());
+    }
+    let shape = Shape::from_name(name).ok_or(format!("'{}' isn't a table name like KQvKR", name))?;
+    let codes = codes_of(name).ok_or(format!("'{}' isn't a table name like KQvKR", name))?;
+    if shape.pieces > MAX_GENERATED {
+        return Err(format!("{}: only endings of up to {} pieces can be generated", name, MAX_GENERATED));
+    }
+    if codes.contains(&(PAWN | BLACK)) {
+        return Err(format!("{}: only endings where White alone has pawns can be generated", name));
+    }
+    for ending in dependencies(&codes) {
+        solve(&ending, solved)?;
+    }
+    let size = 2 << (6 * codes.len());
+    let mut solver = Solver {
+        name,
+        codes: codes.clone(),
+        done: solved,
+        wdl: vec![INVALID; size],
+        level: vec![0; size],
+        state: vec![UNKNOWN; size],
+        remaining: vec![0; size],
+    };
+    solver.run()?;
+    let (wdl, level) = (solver.wdl, solver.level);
+    solved.insert(name.to_string(), Solved { codes, wdl, level });
+    Ok(())
+}
+
+struct Solver<'a> {
+    name: &'a str,
+    codes: Vec<u8>,
+    done: &'a HashMap<String, Solved>,
+    wdl: Vec<i8>,
+    level: Vec<u8>,
+    state: Vec<u8>,
+    // non-zeroing moves whose result isn't known yet
+    remaining: Vec<u8>,
+}
+
+fn step(square: u8, (file, rank): (i8, i8)) -> Option<u8> {
+    let (file, rank) = ((square & 7) as i8 + file, (square >> 3) as i8 + rank);
+    ((0..8).contains(&file) && (0..8).contains(&rank)).then_some((rank * 8 + file) as u8)
+}
+
+// the squares a piece attacks, which for everything but pawns are also where it can move
+fn reach(code: u8, from: u8, occupied: u64) -> u64 {
+    let mut squares = 0;
+    let steps: &[(i8, i8)] = match code & 7 {
+        PAWN => &[(-1, 1), (1, 1)],
+        2 => &KNIGHT_STEPS,
+        KING => &KING_STEPS,
+        _ => &[],
+    };
+    for &(file, rank) in steps {
+        let rank = if code == PAWN | BLACK { -rank } else { rank };
+        if let Some(square) = step(from, (file, rank)) {
+            squares |= 1 << square;
+        }
+    }
+    if matches!(code & 7, 3..=5) {
+        for &(file, rank) in &KING_STEPS {
+            let diagonal = file != 0 && rank != 0;
+            if (diagonal && code & 7 == 4) || (!diagonal && code & 7 == 3) {
+                continue;
+            }
+            let mut square = from;
+            while let Some(next) = step(square, (file, rank)) {
+                squares |= 1 << next;
+                if occupied & 1 << next != 0 {
+                    break;
+                }
+                square = next;
+            }
+        }
+    }
+    squares
+}
+
+fn bits(mut set: u64) -> impl Iterator<Item = u8> {
+    std::iter::from_fn(move || {
+        (set != 0).then(|| {
+            let square = set.trailing_zeros() as u8;
+            set &= set - 1;
+            square
+        })
+    })
+}
+
+impl Solver<'_> {
+    fn black(&self, piece: usize) -> bool {
+        self.codes[piece] & BLACK != 0
+    }
+
+    fn king(&self, black: bool) -> usize {
+        (0..self.codes.len()).find(|&i| self.codes[i] == KING | if black { BLACK } else { 0 }).unwrap_or(0)
+    }
+
+    fn decode(&self, idx: usize) -> ([u8; MAX_GENERATED], bool) {
+        let mut squares = [0; MAX_GENERATED];
+        for (i, square) in squares.iter_mut().enumerate().take(self.codes.len()) {
+            *square = (idx >> (6 * i) & 63) as u8;
+        }
+        (squares, idx >> (6 * self.codes.len()) & 1 == 1)
+    }
+
+    // whether a side attacks 'target', with piece 'captured' taken off the board
+    fn attacked(&self, squares: &[u8], target: u8, by_black: bool, captured: Option<usize>) -> bool {
+        let pieces = (0..self.codes.len()).filter(|&i| Some(i) != captured);
+        let occupied = pieces.clone().fold(0u64, |occupied, i| occupied | 1 << squares[i]);
+        pieces.filter(|&i| self.black(i) == by_black).any(|i| reach(self.codes[i], squares[i], occupied) & 1 << target != 0)
+    }
+
+    fn legal(&self, squares: &[u8], black_to_move: bool) -> bool {
+        let occupied = squares.iter().fold(0u64, |occupied, square| occupied | 1 << square);
+        if occupied.count_ones() as usize != squares.len() {
+            return false;
+        }
+        if (0..squares.len()).any(|i| self.codes[i] & 7 == PAWN && !(8..56).contains(&squares[i])) {
+            return false;
+        }
+        let king = self.king(!black_to_move);
+        !self.attacked(squares, squares[king], black_to_move, None)
+    }
+
+    // the WDL of a position after a capture or pawn move, for its side to move
+    fn result(&self, pieces: &[(u8, u8)], black_to_move: bool) -> Result<i8, String> {
+        if pieces.len() == 2 {
+            return Ok(0);
+        }
+        let (white, black) = (tablebase::material(pieces, 0), tablebase::material(pieces, BLACK));
+        for (name, flip) in [(format!("{}v{}", white, black), false), (format!("{}v{}", black, white), true)] {
+            let (codes, wdl) = match self.done.get(&name) {
+                _ if name == self.name => (&self.codes, &self.wdl),
+                Some(table) => (&table.codes, &table.wdl),
+                None => continue,
+            };
+            // each piece takes the first free place with its code
+            let mut squares = [0; MAX_GENERATED];
+            let mut used = [false; MAX_GENERATED];
+            for &(code, square) in pieces {
+                let code = if flip { code ^ BLACK } else { code };
+                let place = (0..codes.len()).find(|&i| !used[i] && codes[i] == code).ok_or("pieces missing from a table")?;
+                used[place] = true;
+                squares[place] = if flip { square ^ 56 } else { square };
+            }
+            let value = wdl[encode(&squares[..codes.len()], black_to_move ^ flip)];
+            if value == INVALID {
+                return Err(format!("{}: a move led to a position that wasn't solved", self.name));
+            }
+            return Ok(value);
+        }
+        Err(format!("{}: no table for {}v{}", self.name, white, black))
+    }
+
+    fn run(&mut self) -> Result<(), String> {
+        let n = self.codes.len();
+        let pawns: Vec<usize> = (0..n).filter(|&i| self.codes[i] == PAWN).collect();
+        let others: Vec<usize> = (0..n).filter(|&i| self.codes[i] != PAWN).collect();
+        // a slice for each placement of the pawns, the most advanced first, since pawn moves lead to
+        // slices further on
+        let mut slices: Vec<Vec<u8>> = vec![Vec::new()];
+        for _ in &pawns {
+            slices = slices.into_iter().flat_map(|slice| (8..56).map(move |square| [slice.clone(), vec![square]].concat())).collect();
+        }
+        slices.sort_by_key(|slice| Reverse(slice.iter().map(|square| square >> 3).sum::<u8>()));
+        for slice in slices {
+            let mut indices = Vec::new();
+            for black_to_move in [false, true] {
+                for rest in 0..1usize << (6 * others.len()) {
+                    let mut squares = [0; MAX_GENERATED];
+                    for (k, &i) in pawns.iter().enumerate() {
+                        squares[i] = slice[k];
+                    }
+                    for (k, &i) in others.iter().enumerate() {
+                        squares[i] = (rest >> (6 * k) & 63) as u8;
+                    }
+                    indices.push(encode(&squares[..n], black_to_move) as u32);
+                }
+            }
+            self.solve_slice(&indices)?;
+        }
+        Ok(())
+    }
+
+    fn solve_slice(&mut self, indices: &[u32]) -> Result<(), String> {
+        let mut levels: Vec<Vec<u32>> = vec![Vec::new(); FIFTY_MOVES + 1];
+        for &idx in indices {
+            let idx = idx as usize;
+            match self.start(idx)? {
+                (LOST, level) | (WON, level) => levels[level as usize].push(idx as u32),
+                _ => {}
+            }
+        }
+        // a lost position makes the positions before it won, one ply further away; a won one takes
+        // a move away from the positions before it, and once they have none left they're lost
+        for level in 0..levels.len() {
+            let mut next = 0;
+            while next < levels[level].len() {
+                let idx = levels[level][next] as usize;
+                next += 1;
+                let won = self.state[idx] == WON;
+                let further = level + 1;
+                for before in self.predecessors(idx) {
+                    match (self.state[before], won) {
+                        (UNKNOWN | CANT_LOSE, false) => {
+                            if further > FIFTY_MOVES {
+                                return Err(format!("{}: a win takes more than fifty moves", self.name));
+                            }
+                            self.state[before] = WON;
+                            self.level[before] = further as u8;
+                            levels[further].push(before as u32);
+                        }
+                        (UNKNOWN, true) => {
+                            self.remaining[before] -= 1;
+                            self.level[before] = self.level[before].max(further as u8);
+                            if self.remaining[before] == 0 {
+                                if self.level[before] as usize > FIFTY_MOVES {
+                                    return Err(format!("{}: a loss takes more than fifty moves", self.name));
+                                }
+                                self.state[before] = LOST;
+                                levels[self.level[before] as usize].push(before as u32);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        for &idx in indices {
+            let idx = idx as usize;
+            self.wdl[idx] = match self.state[idx] {
+                ILLEGAL => INVALID,
+                WON => 2,
+                LOST => -2,
+                _ => 0,
+            };
+        }
+        Ok(())
+    }
+
+    // what the moves out of a position show before anything else is known
+    fn start(&mut self, idx: usize) -> Result<(u8, u8), String> {
+        let (squares, black_to_move) = self.decode(idx);
+        let n = self.codes.len();
+        let squares = &squares[..n];
+        if !self.legal(squares, black_to_move) {
+            self.state[idx] = ILLEGAL;
+            return Ok((ILLEGAL, 0));
+        }
+        let occupied = squares.iter().fold(0u64, |occupied, square| occupied | 1 << square);
+        let king = self.king(black_to_move);
+        let mut quiet = 0;
+        let mut zeroing: Option<i8> = None;
+        for piece in (0..n).filter(|&i| self.black(i) == black_to_move) {
+            let (code, from) = (self.codes[piece], squares[piece]);
+            let mut targets = Vec::new();
+            if code == PAWN {
+                if occupied & 1 << (from + 8) == 0 {
+                    targets.push(from + 8);
+                    if from < 16 && occupied & 1 << (from + 16) == 0 {
+                        targets.push(from + 16);
+                    }
+                }
+                targets.extend(bits(reach(code, from, occupied) & occupied));
+            } else {
+                targets.extend(bits(reach(code, from, occupied)));
+            }
+            for to in targets {
+                let captured = (0..n).find(|&i| squares[i] == to);
+                if captured.is_some_and(|i| self.black(i) == black_to_move || self.codes[i] & 7 == KING) {
+                    continue;
+                }
+                let mut after = [0; MAX_GENERATED];
+                after[..n].copy_from_slice(squares);
+                after[piece] = to;
+                if self.attacked(&after[..n], after[king], !black_to_move, captured) {
+                    continue;
+                }
+                if captured.is_none() && code != PAWN {
+                    quiet += 1;
+                    continue;
+                }
+                let promotions: &[u8] = if code == PAWN && to >= 56 { &[5, 4, 3, 2] } else { &[code] };
+                for &promotion in promotions {
+                    let pieces: Vec<(u8, u8)> = (0..n)
+                        .filter(|&i| Some(i) != captured)
+                        .map(|i| (if i == piece { promotion } else { self.codes[i] }, after[i]))
+                        .collect();
+                    let value = -self.result(&pieces, !black_to_move)?;
+                    zeroing = Some(zeroing.map_or(value, |best| best.max(value)));
+                }
+            }
+        }
+        let in_check = self.attacked(squares, squares[king], !black_to_move, None);
+        let (state, level) = match zeroing {
+            // checkmate, level 0 so the mating move is 1
+            None if quiet == 0 && in_check => (LOST, 0),
+            None if quiet == 0 => (CANT_LOSE, 0),
+            Some(2) => (WON, 1),
+            Some(-2) if quiet == 0 => (LOST, 1),
+            Some(-2) => (UNKNOWN, 1),
+            Some(_) => (CANT_LOSE, 0),
+            None => (UNKNOWN, 0),
+        };
+        self.state[idx] = state;
+        self.level[idx] = level;
+        self.remaining[idx] = quiet;
+        Ok((state, level))
+    }
+
+    // the positions a non-zeroing move leads from to 'idx'
+    fn predecessors(&self, idx: usize) -> Vec<usize> {
+        let (squares, black_to_move) = self.decode(idx);
+        let n = self.codes.len();
+        let occupied = squares[..n].iter().fold(0u64, |occupied, square| occupied | 1 << square);
+        let mut before = Vec::new();
+        for piece in (0..n).filter(|&i| self.black(i) != black_to_move && self.codes[i] != PAWN) {
+            for from in bits(reach(self.codes[piece], squares[piece], occupied) & !occupied) {
+                let mut earlier = squares;
+                earlier[piece] = from;
+                let earlier = encode(&earlier[..n], !black_to_move);
+                if self.state[earlier] != ILLEGAL {
+                    before.push(earlier);
+                }
+            }
+        }
+        before
+    }
+}
+
+// the order the pieces are numbered in: the leading pawns, the kings, the pieces a side has one of
+fn piece_order(codes: &[u8]) -> Vec<u8> {
+    let count = |code: u8| codes.iter().filter(|other| **other == code).count();
+    let mut order = codes.to_vec();
+    order.sort_by_key(|&code| (code & 7 != PAWN, code & 7 != KING, count(code) != 1, code & BLACK, Reverse(code & 7)));
+    order
+}
+
+// the file for one ending, WDL or DTZ
+fn write(name: &str, table: &Solved, dtz: bool) -> Result<Vec<u8>, String> {
+    let shape = Shape::from_name(name).ok_or(format!("'{}' isn't a table name", name))?;
+    let order = piece_order(&table.codes);
+    // DTZ keeps White to move only, and so does WDL when both sides have the same pieces
+    let sides = if !dtz && !shape.symmetric { 2 } else { 1 };
+    let files = if shape.has_pawns { 4 } else { 1 };
+    let encodings: Vec<Encoding> = (0..files).map(|file| Encoding::new(&shape, &order, [0, 0xf], file)).collect();
+
+    // positions that can't be reached are left free to help the compression
+    const FREE: u8 = u8::MAX;
+    let mut values: Vec<Vec<Vec<u8>>> = (0..sides).map(|_| encodings.iter().map(|encoding| vec![FREE; encoding.size() as usize]).collect()).collect();
+    for idx in 0..table.wdl.len() {
+        let wdl = table.wdl[idx];
+        let (squares, black_to_move) = table.decode(idx);
+        if wdl == INVALID || black_to_move as usize >= sides || (dtz && wdl == 0) {
+            continue;
+        }
+        let placement = tablebase::place(&shape, order[0], &table.pieces(&squares[..order.len()]), false);
+        let file = placement.file;
+        let position = tablebase::index(&shape, &encodings[file], placement) as usize;
+        let value = if dtz { table.level[idx].max(1) - 1 } else { (wdl + 2) as u8 };
+        let slot = &mut values[black_to_move as usize][file][position];
+        if *slot != FREE && *slot != value {
+            return Err(format!("{}: two different positions got number {}", name, position));
+        }
+        *slot = value;
+    }
+
+    let magic = if dtz { DTZ_MAGIC } else { WDL_MAGIC };
+    let mut bytes = magic.to_vec();
+    bytes.push(if shape.symmetric { 0 } else { FILE_SPLIT } | if shape.has_pawns { FILE_HAS_PAWNS } else { 0 });
+    for _ in 0..files {
+        bytes.push(0);
+        bytes.extend(order.iter().map(|code| code | code << 4));
+    }
+    bytes.resize(bytes.len() + (bytes.len() & 1), 0);
+    let flags = if dtz { FLAG_WIN_PLIES | FLAG_LOSS_PLIES } else { 0 };
+    let mut tables = Vec::new();
+    for file in 0..files {
+        for side in values.iter_mut() {
+            let compressed = compress(&mut side[file], FREE);
+            compressed.write_sizes(&mut bytes, flags);
+            tables.push(compressed);
+        }
+    }
+    // no DTZ map
+    if dtz {
+        bytes.resize(bytes.len() + (bytes.len() & 1), 0);
+    }
+    for compressed in &tables {
+        for (block, offset) in &compressed.sparse_index {
+            bytes.extend(block.to_le_bytes());
+            bytes.extend(offset.to_le_bytes());
+        }
+    }
+    for compressed in &tables {
+        for length in &compressed.block_lengths {
+            bytes.extend(length.to_le_bytes());
+        }
+    }
+    for compressed in &tables {
+        bytes.resize(bytes.len().next_multiple_of(64), 0);
+        bytes.extend(&compressed.blocks);
+    }
+    Ok(bytes)
+}
+
+// reads the file back and compares every position
+fn check(name: &str, table: &Solved, bytes: &[u8], dtz: bool) -> Result<(), String> {
+    let written = Table::parse(name, bytes.to_vec(), dtz)?;
+    for idx in 0..table.wdl.len() {
+        let wdl = table.wdl[idx];
+        if wdl == INVALID {
+            continue;
+        }
+        let (squares, black_to_move) = table.decode(idx);
+        let pieces = table.pieces(&squares[..table.codes.len()]);
+        let expected = match (dtz, black_to_move) {
+            (false, _) => Some(wdl as i32),
+            (true, true) => None,
+            (true, false) if wdl == 0 => continue,
+            (true, false) => Some(table.level[idx].max(1) as i32),
+        };
+        if written.lookup(&pieces, black_to_move, false, wdl as i32)? != expected {
+            return Err(format!("{}: position {} reads back wrong", name, idx));
+        }
+    }
+    Ok(())
+}
+
+// one table's values compressed: RE-PAIR symbols coded with canonical Huffman codes in blocks
+struct Compressed {
+    single_value: Option<u8>,
+    blocks_num: u32,
+    min_len: u8,
+    max_len: u8,
+    lowest_sym: Vec<u16>,
+    // the two halves of each symbol
+    btree: Vec<(u16, u16)>,
+    sparse_index: Vec<(u32, u16)>,
+    block_lengths: Vec<u16>,
+    blocks: Vec<u8>,
+}
+
+impl Compressed {
+    fn write_sizes(&self, bytes: &mut Vec<u8>, flags: u8) {
+        if let Some(value) = self.single_value {
+            bytes.extend([flags | FLAG_SINGLE_VALUE, value]);
+            return;
+        }
+        bytes.extend([flags, BLOCK_SIZE_LOG, SPAN_LOG, 0]);
+        bytes.extend(self.blocks_num.to_le_bytes());
+        bytes.extend([self.max_len, self.min_len]);
+        for lowest in &self.lowest_sym {
+            bytes.extend(lowest.to_le_bytes());
+        }
+        bytes.extend((self.btree.len() as u16).to_le_bytes());
+        for &(left, right) in &self.btree {
+            bytes.extend([left as u8, (left >> 8) as u8 | (right << 4) as u8, (right >> 4) as u8]);
+        }
+        if self.btree.len() % 2 == 1 {
+            bytes.push(0);
+        }
+    }
+}
+
+fn compress(values: &mut [u8], free: u8) -> Compressed {
+    // a free position takes the value before it, or the first value when it leads
+    let mut last = values.iter().copied().find(|value| *value != free).unwrap_or(0);
+    for value in values.iter_mut() {
+        if *value == free {
+            *value = last;
+        }
+        last = *value;
+    }
+    if values.iter().all(|value| *value == values[0]) {
+        return Compressed {
+            single_value: Some(values[0]),
+            blocks_num: 0,
+            min_len: 0,
+            max_len: 0,
+            lowest_sym: Vec::new(),
+            btree: Vec::new(),
+            sparse_index: Vec::new(),
+            block_lengths: Vec::new(),
+            blocks: Vec::new(),
+        };
+    }
+
+    // recursive pairing: the most frequent pair of neighbouring symbols becomes a new symbol, over
+    // and over
+    let leaves = *values.iter().max().unwrap_or(&0) as usize + 1;
+    let mut pairs: Vec<(u16, u16)> = (0..leaves as u16).map(|value| (value, LEAF)).collect();
+    let mut counts_of: Vec<u32> = vec![1; leaves];
+    let mut sequence: Vec<u16> = values.iter().map(|value| *value as u16).collect();
+    let mut counts = vec![0u32; 1 << 24];
+    while pairs.len() < MAX_SYMBOLS {
+        let mut seen = Vec::new();
+        for pair in sequence.windows(2) {
+            let key = (pair[0] as usize) << 12 | pair[1] as usize;
+            if counts[key] == 0 {
+                seen.push(key);
+            }
+            counts[key] += 1;
+        }
+        let mut best = (0, 0);
+        for key in seen {
+            let (left, right) = (key >> 12, key & 0xfff);
+            if counts[key] > best.1 && counts_of[left] + counts_of[right] <= MAX_SYMBOL_VALUES {
+                best = (key, counts[key]);
+            }
+            counts[key] = 0;
+        }
+        if best.1 < 8 {
+            break;
+        }
+        let (left, right) = ((best.0 >> 12) as u16, (best.0 & 0xfff) as u16);
+        let symbol = pairs.len() as u16;
+        pairs.push((left, right));
+        counts_of.push(counts_of[left as usize] + counts_of[right as usize]);
+        let mut paired = Vec::with_capacity(sequence.len());
+        let mut i = 0;
+        while i < sequence.len() {
+            if i + 1 < sequence.len() && sequence[i] == left && sequence[i + 1] == right {
+                paired.push(symbol);
+                i += 2;
+            } else {
+                paired.push(sequence[i]);
+                i += 1;
+            }
+        }
+        sequence = paired;
+    }
+
+    // Huffman code lengths, flattening the frequencies until the longest code is short enough
+    let mut frequencies = vec![0u64; pairs.len()];
+    for symbol in &sequence {
+        frequencies[*symbol as usize] += 1;
+    }
+    let mut lengths = code_lengths(&frequencies);
+    while lengths.iter().copied().max().unwrap_or(0) > MAX_CODE_LEN {
+        frequencies.iter_mut().filter(|frequency| **frequency > 0).for_each(|frequency| *frequency = *frequency / 2 + 1);
+        lengths = code_lengths(&frequencies);
+    }
+
+    // symbols are numbered so that longer codes come first, unused symbols before them all
+    let mut numbering: Vec<usize> = (0..pairs.len()).collect();
+    numbering.sort_by_key(|&symbol| (lengths[symbol] != 0, Reverse(lengths[symbol])));
+    let mut number = vec![0u16; pairs.len()];
+    for (new, &old) in numbering.iter().enumerate() {
+        number[old] = new as u16;
+    }
+    let btree = numbering.iter().map(|&old| match pairs[old] {
+        (value, LEAF) => (value, LEAF),
+        (left, right) => (number[left as usize], number[right as usize]),
+    }).collect();
+    let used: Vec<u8> = lengths.iter().copied().filter(|length| *length > 0).collect();
+    let (min_len, max_len) = (*used.iter().min().unwrap_or(&1), *used.iter().max().unwrap_or(&1));
+    let of_length = |length: u8| lengths.iter().filter(|other| **other == length).count() as u64;
+    let count = (max_len - min_len + 1) as usize;
+    let mut lowest_sym = vec![0u16; count];
+    let mut base = vec![0u64; count];
+    lowest_sym[count - 1] = (pairs.len() - used.len()) as u16;
+    for i in (0..count - 1).rev() {
+        let longer = of_length(min_len + i as u8 + 1);
+        lowest_sym[i] = lowest_sym[i + 1] + longer as u16;
+        base[i] = (base[i + 1] + longer) / 2;
+    }
+    let code = |symbol: usize| {
+        let i = (lengths[symbol] - min_len) as usize;
+        base[i] + (number[symbol] - lowest_sym[i]) as u64
+    };
+
+    // blocks of whole symbols, each with at most MAX_BLOCK_VALUES values
+    let block_bits = 8usize << BLOCK_SIZE_LOG;
+    let mut blocks: Vec<u8> = Vec::new();
+    let mut block_values: Vec<u32> = Vec::new();
+    let mut bit = block_bits;
+    for &symbol in &sequence {
+        let symbol = symbol as usize;
+        let length = lengths[symbol] as usize;
+        if bit + length > block_bits || block_values.last().is_some_and(|values| values + counts_of[symbol] > MAX_BLOCK_VALUES) {
+            blocks.resize(blocks.len() + (1 << BLOCK_SIZE_LOG), 0);
+            block_values.push(0);
+            bit = 0;
+        }
+        let start = blocks.len() - (1 << BLOCK_SIZE_LOG);
+        let value = code(symbol);
+        for i in 0..length {
+            if value >> (length - 1 - i) & 1 == 1 {
+                blocks[start + (bit + i) / 8] |= 0x80 >> ((bit + i) % 8);
+            }
+        }
+        bit += length;
+        *block_values.last_mut().unwrap_or(&mut 0) += counts_of[symbol];
+    }
+
+    // where every span-th value (plus half a span) is: its block and how far into it
+    let span = 1u64 << SPAN_LOG;
+    let total = values.len() as u64;
+    let mut sparse_index = Vec::new();
+    let (mut block, mut block_start) = (0, 0u64);
+    for k in 0..total.div_ceil(span) {
+        let idx = k * span + span / 2;
+        while block + 1 < block_values.len() && idx >= block_start + block_values[block] as u64 {
+            block_start += block_values[block] as u64;
+            block += 1;
+        }
+        sparse_index.push((block as u32, (idx - block_start) as u16));
+    }
+
+    Compressed {
+        single_value: None,
+        blocks_num: block_values.len() as u32,
+        min_len,
+        max_len,
+        lowest_sym,
+        btree,
+        sparse_index,
+        block_lengths: block_values.iter().map(|values| (values - 1) as u16).collect(),
+        blocks,
+    }
+}
+
+// the length of each symbol's Huffman code, 0 for symbols that aren't used
+fn code_lengths(frequencies: &[u64]) -> Vec<u8> {
+    let mut lengths = vec![0u8; frequencies.len()];
+    let used: Vec<usize> = (0..frequencies.len()).filter(|&symbol| frequencies[symbol] > 0).collect();
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+        return lengths;
+    }
+    // nodes past the symbols are the merged ones, each with its parent
+    let mut parent = vec![usize::MAX; frequencies.len()];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = used.iter().map(|&symbol| Reverse((frequencies[symbol], symbol))).collect();
+    while heap.len() > 1 {
+        let (Some(Reverse((a, first))), Some(Reverse((b, second)))) = (heap.pop(), heap.pop()) else { break };
+        let node = parent.len();
+        parent.push(usize::MAX);
+        parent[first] = node;
+        parent[second] = node;
+        heap.push(Reverse((a + b, node)));
+    }
+    for &symbol in &used {
+        let mut node = symbol;
+        while parent[node] != usize::MAX {
+            lengths[symbol] += 1;
+            node = parent[node];
+        }
+    }
+    lengths
+}
+
+mod tests {
+    use super::*;
+
+    const FIXTURES: [&str; 7] = ["KQvK", "KRvK", "KBvK", "KNvK", "KPvK", "KRRvK", "KQvKR"];
+
+    // the longest mates, known from every other tablebase: 10 moves in KQvK and 16 in KRvK
+    #[test]
+    fn longest_wins_are_the_known_ones() {
+        let mut solved = HashMap::new();
+        for (name, plies) in [("KQvK", 19), ("KRvK", 31)] {
+            solve(name, &mut solved).unwrap();
+            let table = &solved[name];
+            let longest = (0..table.wdl.len())
+                .filter(|&idx| !table.decode(idx).1 && table.wdl[idx] == 2)
+                .map(|idx| table.level[idx])
+                .max();
+            assert_eq!(longest, Some(plies), "{}", name);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn write_fixtures() {
+        generate(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy")), &FIXTURES).unwrap();
+    }
+}
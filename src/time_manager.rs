@@ -0,0 +1,274 @@
+use crate::piece::Color;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+// kept back from every move for the time it takes the GUI to get our answer
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+// how many more moves the game is assumed to last when the GUI doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+// the hard limit may go this far past the planned time on a difficult move
+const HARD_LIMIT_FACTOR: u32 = 5;
+
+// where the time manager gets the time from, so it can be driven by a fake clock
+pub trait Clock: Send + Sync {
+    // time since some fixed point, only differences are used
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+// the time information from a UCI `go` command, all optional
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limits {
+    pub white_time: Option<Duration>,
+    pub black_time: Option<Duration>,
+    pub white_increment: Option<Duration>,
+    pub black_increment: Option<Duration>,
+    pub moves_to_go: Option<u32>,
+    pub move_time: Option<Duration>,
+}
+
+impl Limits {
+    pub fn is_timed(&self) -> bool {
+        self.move_time.is_some() || self.white_time.is_some() || self.black_time.is_some()
+    }
+}
+
+// how the last finished iteration went, used to decide whether to start another one
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Iteration {
+    pub best_move_changed: bool,
+    // iterations in a row that ended with the same best move
+    pub stable_iterations: u32,
+    // how much worse the score got than in the previous iteration, 0 if it didn't
+    pub score_drop: i32,
+    pub legal_moves: usize,
+    pub mate_found: bool,
+}
+
+// splits the remaining time into a soft limit, after which no new iteration is started,
+// and a hard limit, at which the search is stopped wherever it is
+// while pondering the clock isn't ours yet, so no limit applies until ponderhit starts it
+pub struct TimeManager {
+    clock: Box<dyn Clock>,
+    start_nanos: AtomicU64,
+    soft: Duration,
+    hard: Duration,
+    pondering: AtomicBool,
+}
+
+impl TimeManager {
+    pub fn new(clock: Box<dyn Clock>, limits: &Limits, side: Color) -> Self {
+        let start = clock.now();
+        let (soft, hard) = match limits.move_time {
+            Some(move_time) => {
+                let budget = move_time.saturating_sub(MOVE_OVERHEAD).max(Duration::from_millis(1));
+                (budget, budget)
+            }
+            None => {
+                let (time, increment) = match side {
+                    Color::White => (limits.white_time, limits.white_increment),
+                    Color::Black => (limits.black_time, limits.black_increment),
+                };
+                let time = time.unwrap_or(Duration::MAX);
+                let increment = increment.unwrap_or(Duration::ZERO);
+                // the hard limit never uses up the clock, whatever the increment
+                let usable = time.saturating_sub(MOVE_OVERHEAD).max(Duration::from_millis(1));
+                let moves = limits.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).clamp(1, DEFAULT_MOVES_TO_GO);
+                let soft = (usable / moves + increment * 3 / 4).min(usable * 3 / 4);
+                let hard = soft.saturating_mul(HARD_LIMIT_FACTOR).min(usable / 2).max(soft);
+                (soft, hard)
+            }
+        };
+        TimeManager { clock, start_nanos: AtomicU64::new(start.as_nanos() as u64), soft, hard, pondering: AtomicBool::new(false) }
+    }
+
+    pub fn start_pondering(&self) {
+        self.pondering.store(true, Ordering::Relaxed);
+    }
+
+    // the opponent played the expected move, the budget counts from now
+    pub fn ponderhit(&self) {
+        self.start_nanos.store(self.clock.now().as_nanos() as u64, Ordering::Relaxed);
+        self.pondering.store(false, Ordering::Relaxed);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.clock.now().saturating_sub(Duration::from_nanos(self.start_nanos.load(Ordering::Relaxed)))
+    }
+
+    fn is_pondering(&self) -> bool {
+        self.pondering.load(Ordering::Relaxed)
+    }
+
+    pub fn hard_limit_reached(&self) -> bool {
+        !self.is_pondering() && self.elapsed() >= self.hard
+    }
+
+    // whether to start the next iteration after 'iteration' finished
+    // unstable best moves and falling scores get more time, a move that stays best gets less,
+    // and with a single legal move or a mate found there is nothing more to think about
+    pub fn keep_searching(&self, iteration: &Iteration) -> bool {
+        if self.is_pondering() {
+            return true;
+        }
+        if iteration.legal_moves <= 1 || iteration.mate_found {
+            return false;
+        }
+        let mut scale = 1.0;
+        if iteration.best_move_changed {
+            scale *= 1.4;
+        }
+        if iteration.score_drop >= 30 {
+            scale *= 1.5;
+        }
+        if iteration.stable_iterations >= 4 {
+            scale *= 0.6;
+        }
+        self.elapsed() < self.soft.mul_f64(scale).min(self.hard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    // a clock that only moves when the test moves it
+    #[derive(Clone, Default)]
+    struct FakeClock {
+        nanos: Arc<AtomicU64>,
+    }
+
+    impl FakeClock {
+        fn advance(&self, by: Duration) {
+            self.nanos.fetch_add(by.as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+        }
+    }
+
+    fn manager(limits: Limits, side: Color) -> (TimeManager, FakeClock) {
+        let clock = FakeClock::default();
+        // a clock that doesn't start at zero, only differences count
+        clock.advance(Duration::from_secs(1000));
+        (TimeManager::new(Box::new(clock.clone()), &limits, side), clock)
+    }
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn iteration() -> Iteration {
+        Iteration { legal_moves: 20, ..Iteration::default() }
+    }
+
+    #[test]
+    fn sudden_death() {
+        let limits = Limits { white_time: Some(millis(60_000)), black_time: Some(millis(1_000)), ..Limits::default() };
+        let (time, clock) = manager(limits, Color::White);
+        // 59.97s after the overhead spread over 30 moves, and at most five times that
+        assert_eq!((time.soft, time.hard), (millis(1_999), millis(9_995)));
+        clock.advance(millis(1_998));
+        assert!(time.keep_searching(&iteration()));
+        clock.advance(millis(1));
+        assert!(!time.keep_searching(&iteration()));
+        clock.advance(millis(7_995));
+        assert!(!time.hard_limit_reached());
+        clock.advance(millis(1));
+        assert!(time.hard_limit_reached());
+        // Black's own clock is used for Black
+        let (time, _) = manager(limits, Color::Black);
+        assert_eq!(time.soft, Duration::from_nanos(970_000_000 / 30));
+    }
+
+    #[test]
+    fn increment() {
+        let limits = Limits { white_time: Some(millis(10_000)), white_increment: Some(millis(1_000)), ..Limits::default() };
+        let (time, _) = manager(limits, Color::White);
+        // three quarters of the increment on top, and the hard limit never takes more than half the clock
+        assert_eq!(time.soft, Duration::from_nanos(9_970_000_000 / 30) + millis(750));
+        assert_eq!(time.hard, millis(4_985));
+    }
+
+    #[test]
+    fn moves_to_go() {
+        let limits = Limits { black_time: Some(millis(10_000)), moves_to_go: Some(2), ..Limits::default() };
+        let (time, _) = manager(limits, Color::Black);
+        assert_eq!((time.soft, time.hard), (millis(4_985), millis(4_985)));
+        // the last move before the time control still keeps a quarter back
+        let (time, _) = manager(Limits { moves_to_go: Some(1), ..limits }, Color::Black);
+        assert_eq!((time.soft, time.hard), (millis(7_477) + Duration::from_micros(500), millis(7_477) + Duration::from_micros(500)));
+    }
+
+    #[test]
+    fn move_time() {
+        let (time, clock) = manager(Limits { move_time: Some(millis(1_000)), ..Limits::default() }, Color::White);
+        assert_eq!((time.soft, time.hard), (millis(970), millis(970)));
+        clock.advance(millis(970));
+        assert!(time.hard_limit_reached());
+    }
+
+    #[test]
+    fn ponderhit_restarts_the_budget() {
+        let (time, clock) = manager(Limits { move_time: Some(millis(1_000)), ..Limits::default() }, Color::White);
+        time.start_pondering();
+        clock.advance(millis(5_000));
+        assert!(!time.hard_limit_reached());
+        assert!(time.keep_searching(&iteration()));
+        time.ponderhit();
+        assert_eq!(time.elapsed(), Duration::ZERO);
+        clock.advance(millis(969));
+        assert!(!time.hard_limit_reached());
+        clock.advance(millis(1));
+        assert!(time.hard_limit_reached());
+    }
+
+    #[test]
+    fn stability_scales_the_soft_limit() {
+        let limits = Limits { white_time: Some(millis(60_000)), ..Limits::default() };
+        let (time, clock) = manager(limits, Color::White);
+        let changed = Iteration { best_move_changed: true, ..iteration() };
+        let stable = Iteration { stable_iterations: 4, ..iteration() };
+        let falling = Iteration { score_drop: 50, ..iteration() };
+        // the soft limit is 1999ms: 1199.4ms for a stable move, 2798.6ms for a new one and 2998.5ms for a falling score
+        clock.advance(millis(1_500));
+        assert!(time.keep_searching(&iteration()));
+        assert!(!time.keep_searching(&stable));
+        clock.advance(millis(1_200));
+        assert!(!time.keep_searching(&iteration()));
+        assert!(time.keep_searching(&changed));
+        assert!(time.keep_searching(&falling));
+        clock.advance(millis(200));
+        assert!(!time.keep_searching(&changed));
+        assert!(time.keep_searching(&falling));
+        // nothing to think about with one legal move or a mate found
+        let (time, _) = manager(limits, Color::White);
+        assert!(!time.keep_searching(&Iteration { legal_moves: 1, ..iteration() }));
+        assert!(!time.keep_searching(&Iteration { mate_found: true, ..iteration() }));
+    }
+}
@@ -0,0 +1,521 @@
+use crate::uci_engine::EngineConfig;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    RoundRobin,
+    Swiss,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name.to_ascii_lowercase().as_str() {
+            "roundrobin" | "round-robin" | "rr" => Some(Format::RoundRobin),
+            "swiss" => Some(Format::Swiss),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::RoundRobin => "roundrobin",
+            Format::Swiss => "swiss",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Player {
+    pub name: String,
+    // only used to order players who are level on points
+    pub rating: u32,
+    // None for a human, whose results are entered by hand
+    pub engine: Option<EngineConfig>,
+}
+
+// one board of a round, players are indices into Tournament::players
+#[derive(Clone, Debug)]
+pub struct Pairing {
+    pub white: usize,
+    pub black: usize,
+    // 1-0, 0-1, 1/2-1/2, or * while the game hasn't been played
+    pub result: String,
+}
+
+impl Pairing {
+    pub fn is_finished(&self) -> bool {
+        self.result != "*"
+    }
+
+    // points for White, None while unfinished
+    fn white_points(&self) -> Option<f64> {
+        match self.result.as_str() {
+            "1-0" => Some(1.0),
+            "0-1" => Some(0.0),
+            "1/2-1/2" => Some(0.5),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Round {
+    pub pairings: Vec<Pairing>,
+    // sits the round out and gets a point for it
+    pub bye: Option<usize>,
+}
+
+// one line of the standings
+#[derive(Clone, Debug)]
+pub struct Standing {
+    pub player: usize,
+    pub points: f64,
+    // sum of the opponents' points
+    pub buchholz: f64,
+    // sum of the points of the opponents beaten, plus half of those drawn with
+    pub sonneborn_berger: f64,
+    pub games: u32,
+}
+
+pub struct Tournament {
+    pub name: String,
+    pub format: Format,
+    // how many rounds a Swiss runs for, a round robin always has everyone meet once
+    pub swiss_rounds: usize,
+    pub players: Vec<Player>,
+    pub rounds: Vec<Round>,
+}
+
+impl Tournament {
+    pub fn new(name: &str, format: Format, swiss_rounds: usize) -> Tournament {
+        Tournament { name: name.to_string(), format, swiss_rounds, players: Vec::new(), rounds: Vec::new() }
+    }
+
+    // players can only join before the first round is paired, the round robin schedule depends on the count
+    pub fn add_player(&mut self, player: Player) -> Result<(), String> {
+        if !self.rounds.is_empty() {
+            return Err(String::from("the tournament has already started"));
+        }
+        if self.players.iter().any(|other| other.name == player.name) {
+            return Err(format!("there is already a player called {}", player.name));
+        }
+        self.players.push(player);
+        Ok(())
+    }
+
+    pub fn total_rounds(&self) -> usize {
+        match self.format {
+            // with an odd count everyone gets one bye, so there is a round more than with an even count
+            Format::RoundRobin => (self.players.len() + self.players.len() % 2).saturating_sub(1),
+            Format::Swiss => self.swiss_rounds,
+        }
+    }
+
+    // pairs the next round once every game of the current one has a result
+    pub fn pair_next_round(&mut self) -> Result<&Round, String> {
+        if self.players.len() < 2 {
+            return Err(String::from("a tournament needs at least two players"));
+        }
+        if self.rounds.last().is_some_and(|round| round.pairings.iter().any(|pairing| !pairing.is_finished())) {
+            return Err(format!("round {} still has games without a result", self.rounds.len()));
+        }
+        if self.rounds.len() >= self.total_rounds() {
+            return Err(format!("all {} rounds have been played", self.total_rounds()));
+        }
+        let round = match self.format {
+            Format::RoundRobin => round_robin_round(self.players.len(), self.rounds.len()),
+            Format::Swiss => self.swiss_round(),
+        };
+        self.rounds.push(round);
+        Ok(&self.rounds[self.rounds.len() - 1])
+    }
+
+    // 'round' and 'board' count from 1 as printed
+    pub fn set_result(&mut self, round: usize, board: usize, result: &str) -> Result<(), String> {
+        if !["1-0", "0-1", "1/2-1/2", "*"].contains(&result) {
+            return Err(format!("'{}' should be 1-0, 0-1, 1/2-1/2 or *", result));
+        }
+        let pairing = self.rounds.get_mut(round.wrapping_sub(1))
+            .and_then(|round| round.pairings.get_mut(board.wrapping_sub(1)))
+            .ok_or(format!("there is no board {} in round {}", board, round))?;
+        pairing.result = result.to_string();
+        Ok(())
+    }
+
+    // each finished game of 'player' as (opponent, points scored), byes as (None, 1.0)
+    fn results(&self, player: usize) -> Vec<(Option<usize>, f64)> {
+        let mut results = Vec::new();
+        for round in &self.rounds {
+            if round.bye == Some(player) {
+                results.push((None, 1.0));
+            }
+            for pairing in &round.pairings {
+                let Some(white_points) = pairing.white_points() else { continue };
+                if pairing.white == player {
+                    results.push((Some(pairing.black), white_points));
+                } else if pairing.black == player {
+                    results.push((Some(pairing.white), 1.0 - white_points));
+                }
+            }
+        }
+        results
+    }
+
+    fn points(&self, player: usize) -> f64 {
+        self.results(player).iter().map(|(_, points)| points).sum()
+    }
+
+    // best first: points, then Buchholz, then Sonneborn-Berger, then rating
+    pub fn standings(&self) -> Vec<Standing> {
+        let points: Vec<f64> = (0..self.players.len()).map(|player| self.points(player)).collect();
+        let mut standings: Vec<Standing> = (0..self.players.len())
+            .map(|player| {
+                let results = self.results(player);
+                let opponents = results.iter().filter_map(|(opponent, score)| opponent.map(|opponent| (opponent, *score)));
+                Standing {
+                    player,
+                    points: points[player],
+                    buchholz: opponents.clone().map(|(opponent, _)| points[opponent]).sum(),
+                    sonneborn_berger: opponents.clone().map(|(opponent, score)| points[opponent] * score).sum(),
+                    games: opponents.count() as u32,
+                }
+            })
+            .collect();
+        standings.sort_by(|a, b| {
+            b.points.total_cmp(&a.points)
+                .then(b.buchholz.total_cmp(&a.buchholz))
+                .then(b.sonneborn_berger.total_cmp(&a.sonneborn_berger))
+                .then(self.players[b.player].rating.cmp(&self.players[a.player].rating))
+        });
+        standings
+    }
+
+    // Dutch-style Swiss pairing: players are ranked by points and rating, and within each score
+    // group the top half meets the bottom half in order, moving down the list when that would be
+    // a rematch; a player left over floats down to the next group
+    // the lowest ranked player without a bye so far sits out when the count is odd
+    fn swiss_round(&self) -> Round {
+        let points: Vec<f64> = (0..self.players.len()).map(|player| self.points(player)).collect();
+        let mut order: Vec<usize> = (0..self.players.len()).collect();
+        order.sort_by(|&a, &b| points[b].total_cmp(&points[a]).then(self.players[b].rating.cmp(&self.players[a].rating)).then(a.cmp(&b)));
+        let mut bye = None;
+        if order.len() % 2 == 1 {
+            let had_bye = |player: usize| self.rounds.iter().any(|round| round.bye == Some(player));
+            let at = order.iter().rposition(|&player| !had_bye(player)).unwrap_or(order.len() - 1);
+            bye = Some(order.remove(at));
+        }
+        let met = |a: usize, b: usize| {
+            self.rounds.iter().flat_map(|round| &round.pairings)
+                .any(|pairing| (pairing.white, pairing.black) == (a, b) || (pairing.white, pairing.black) == (b, a))
+        };
+        // a rematch is only allowed when no pairing avoids one, or none turns up within the tries
+        let pairs = swiss_pairs(&order, &points, &met).or_else(|| swiss_pairs(&order, &points, &|_, _| false)).unwrap_or_default();
+        let pairings = pairs.into_iter()
+            .map(|(a, b)| {
+                let (white, black) = self.colours(a, b);
+                Pairing { white, black, result: String::from("*") }
+            })
+            .collect();
+        Round { pairings, bye }
+    }
+
+    // White goes to whoever has had it less often, then to whoever had Black last time,
+    // then to the higher ranked player 'a'
+    fn colours(&self, a: usize, b: usize) -> (usize, usize) {
+        let balance = |player: usize| {
+            self.rounds.iter().flat_map(|round| &round.pairings)
+                .map(|pairing| if pairing.white == player { 1 } else if pairing.black == player { -1 } else { 0 })
+                .sum::<i32>()
+        };
+        let last_white = |player: usize| {
+            self.rounds.iter().rev().flat_map(|round| &round.pairings)
+                .find(|pairing| pairing.white == player || pairing.black == player)
+                .map(|pairing| pairing.white == player)
+        };
+        let a_first = match balance(a).cmp(&balance(b)) {
+            Ordering::Less => true,
+            Ordering::Greater => false,
+            Ordering::Equal => !matches!((last_white(a), last_white(b)), (Some(true), Some(false))),
+        };
+        if a_first { (a, b) } else { (b, a) }
+    }
+
+    pub fn load(path: &str) -> Result<Tournament, String> {
+        let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        Tournament::from_text(&text).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_text()).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    // one record per line with tab separated fields, so names can have spaces:
+    //   name <name> / format <format> <swiss rounds> / player <name> <rating> [<engine settings>]
+    //   round / game <white> <black> <result> / bye <player>
+    // players are numbered from 0 in the order they appear, games belong to the round above them
+    pub fn to_text(&self) -> String {
+        let mut text = format!("name\t{}\nformat\t{}\t{}\n", self.name, self.format.name(), self.swiss_rounds);
+        for player in &self.players {
+            text.push_str(&format!("player\t{}\t{}", player.name, player.rating));
+            if let Some(engine) = &player.engine {
+                text.push('\t');
+                text.push_str(&engine.to_words().join(" "));
+            }
+            text.push('\n');
+        }
+        for round in &self.rounds {
+            text.push_str("round\n");
+            for pairing in &round.pairings {
+                text.push_str(&format!("game\t{}\t{}\t{}\n", pairing.white, pairing.black, pairing.result));
+            }
+            if let Some(bye) = round.bye {
+                text.push_str(&format!("bye\t{}\n", bye));
+            }
+        }
+        text
+    }
+
+    fn from_text(text: &str) -> Result<Tournament, String> {
+        let mut tournament = Tournament::new("", Format::Swiss, 0);
+        for (number, line) in text.lines().enumerate() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let error = || format!("line {}: can't read '{}'", number + 1, line);
+            let index = |field: Option<&&str>| field.and_then(|field| field.parse::<usize>().ok()).ok_or_else(error);
+            match fields[0] {
+                "name" => tournament.name = fields.get(1).ok_or_else(error)?.to_string(),
+                "format" => {
+                    tournament.format = fields.get(1).and_then(|name| Format::from_name(name)).ok_or_else(error)?;
+                    tournament.swiss_rounds = index(fields.get(2))?;
+                }
+                "player" => {
+                    let name = fields.get(1).ok_or_else(error)?.to_string();
+                    let rating = fields.get(2).and_then(|rating| rating.parse().ok()).ok_or_else(error)?;
+                    let engine = match fields.get(3) {
+                        Some(words) => Some(EngineConfig { name: name.clone(), ..EngineConfig::parse(&words.split_whitespace().collect::<Vec<_>>())? }),
+                        None => None,
+                    };
+                    tournament.players.push(Player { name, rating, engine });
+                }
+                "round" => tournament.rounds.push(Round::default()),
+                "game" => {
+                    let (white, black) = (index(fields.get(1))?, index(fields.get(2))?);
+                    let result = fields.get(3).ok_or_else(error)?.to_string();
+                    tournament.rounds.last_mut().ok_or_else(error)?.pairings.push(Pairing { white, black, result });
+                }
+                "bye" => tournament.rounds.last_mut().ok_or_else(error)?.bye = Some(index(fields.get(1))?),
+                "" => {}
+                _ => return Err(error()),
+            }
+        }
+        let count = tournament.players.len();
+        let out_of_range = tournament.rounds.iter()
+            .any(|round| round.bye.is_some_and(|bye| bye >= count) || round.pairings.iter().any(|pairing| pairing.white >= count || pairing.black >= count));
+        if out_of_range {
+            return Err(String::from("a game refers to a player that isn't listed"));
+        }
+        Ok(tournament)
+    }
+}
+
+// the 'round'th round (from 0) of a round robin by the circle method: the first player stays put
+// while the others rotate one seat per round, and an odd count gets an empty seat for the bye
+fn round_robin_round(players: usize, round: usize) -> Round {
+    let mut seats: Vec<Option<usize>> = (0..players).map(Some).collect();
+    if players % 2 == 1 {
+        seats.push(None);
+    }
+    let count = seats.len();
+    let rotated: Vec<Option<usize>> = (0..count)
+        .map(|seat| if seat == 0 { seats[0] } else { seats[1 + (seat - 1 + round) % (count - 1)] })
+        .collect();
+    let mut result = Round::default();
+    for board in 0..count / 2 {
+        let (a, b) = (rotated[board], rotated[count - 1 - board]);
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                // alternating by round and board gives everyone close to half their games with White
+                let (white, black) = if (round + board).is_multiple_of(2) { (a, b) } else { (b, a) };
+                result.pairings.push(Pairing { white, black, result: String::from("*") });
+            }
+            (Some(player), None) | (None, Some(player)) => result.bye = Some(player),
+            (None, None) => {}
+        }
+    }
+    result
+}
+
+// how many opponents swiss_pairs tries in all before it gives up, there is almost always a pairing
+// without rematches early on, but late in a long event there may be none, and proving that by
+// trying every order takes exponential time
+const SWISS_PAIRING_TRIES: u32 = 100_000;
+
+// pairs 'order' (ranked best first) without rematches, trying the Dutch opponent first and
+// backtracking when the rest can't be paired, None when there is no way to do it or the search
+// runs out of tries
+fn swiss_pairs(order: &[usize], points: &[f64], met: &dyn Fn(usize, usize) -> bool) -> Option<Vec<(usize, usize)>> {
+    let mut tries = SWISS_PAIRING_TRIES;
+    swiss_pairs_from(order, points, met, &mut tries, &mut HashSet::new())
+}
+
+// 'dead_ends' holds the groups of players already found to have no pairing, so the same
+// leftover group isn't searched again after pairing its elders another way
+fn swiss_pairs_from(order: &[usize], points: &[f64], met: &dyn Fn(usize, usize) -> bool,
+                    tries: &mut u32, dead_ends: &mut HashSet<Vec<usize>>) -> Option<Vec<(usize, usize)>> {
+    let Some((&top, rest)) = order.split_first() else { return Some(Vec::new()) };
+    if dead_ends.contains(order) {
+        return None;
+    }
+    // the score group of the top player, the top player included
+    let group = 1 + rest.iter().take_while(|&&player| points[player] == points[top]).count();
+    // its natural opponent is the first of the group's bottom half, then come the rest of the bottom
+    // half, the top half, and the lower groups in order
+    let natural = (group / 2).max(1) - 1;
+    let candidates = (natural..group - 1).chain(0..natural).chain(group - 1..rest.len());
+    for at in candidates {
+        let opponent = rest[at];
+        if met(top, opponent) {
+            continue;
+        }
+        if *tries == 0 {
+            return None;
+        }
+        *tries -= 1;
+        let remaining: Vec<usize> = rest.iter().enumerate().filter(|(i, _)| *i != at).map(|(_, &player)| player).collect();
+        if let Some(mut pairs) = swiss_pairs_from(&remaining, points, met, tries, dead_ends) {
+            pairs.insert(0, (top, opponent));
+            return Some(pairs);
+        }
+    }
+    // running out of tries says nothing about this group, so only a full search marks it
+    if *tries > 0 {
+        dead_ends.insert(order.to_vec());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tournament(format: Format, players: usize, swiss_rounds: usize) -> Tournament {
+        let mut tournament = Tournament::new("Test", format, swiss_rounds);
+        for number in 0..players {
+            tournament.add_player(Player { name: format!("Player {}", number), rating: 2000 - number as u32 * 10, engine: None }).unwrap();
+        }
+        tournament
+    }
+
+    // plays out the round just paired with the higher ranked (lower numbered) player winning
+    fn play_round(tournament: &mut Tournament) {
+        let round = tournament.rounds.len();
+        let results: Vec<&str> = tournament.rounds[round - 1].pairings.iter()
+            .map(|pairing| if pairing.white < pairing.black { "1-0" } else { "0-1" })
+            .collect();
+        for (board, result) in results.into_iter().enumerate() {
+            tournament.set_result(round, board + 1, result).unwrap();
+        }
+    }
+
+    fn meetings(tournament: &Tournament, a: usize, b: usize) -> usize {
+        tournament.rounds.iter().flat_map(|round| &round.pairings)
+            .filter(|pairing| (pairing.white, pairing.black) == (a, b) || (pairing.white, pairing.black) == (b, a))
+            .count()
+    }
+
+    #[test]
+    fn round_robin_has_everyone_meet_once() {
+        for players in [4, 5] {
+            let mut tournament = tournament(Format::RoundRobin, players, 0);
+            while tournament.rounds.len() < tournament.total_rounds() {
+                tournament.pair_next_round().unwrap();
+                play_round(&mut tournament);
+            }
+            assert!(tournament.pair_next_round().is_err());
+            for a in 0..players {
+                for b in a + 1..players {
+                    assert_eq!(meetings(&tournament, a, b), 1, "{} and {} of {}", a, b, players);
+                }
+                let byes = tournament.rounds.iter().filter(|round| round.bye == Some(a)).count();
+                assert_eq!(byes, players % 2);
+            }
+        }
+    }
+
+    #[test]
+    fn swiss_avoids_rematches() {
+        // five rounds for six players leaves exactly one way to finish, so the last rounds need backtracking
+        let mut tournament = tournament(Format::Swiss, 6, 5);
+        for _ in 0..5 {
+            tournament.pair_next_round().unwrap();
+            play_round(&mut tournament);
+        }
+        for a in 0..6 {
+            for b in a + 1..6 {
+                assert_eq!(meetings(&tournament, a, b), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn swiss_gives_each_bye_to_someone_new() {
+        let mut tournament = tournament(Format::Swiss, 5, 5);
+        let mut byes = Vec::new();
+        for _ in 0..5 {
+            byes.push(tournament.pair_next_round().unwrap().bye.unwrap());
+            play_round(&mut tournament);
+        }
+        byes.sort();
+        assert_eq!(byes, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn impossible_pairings_give_up_quickly() {
+        // the last player has met everyone, which only shows once everyone else is paired
+        let order: Vec<usize> = (0..30).collect();
+        let points = vec![0.0; 30];
+        let met = |a: usize, b: usize| a == 29 || b == 29;
+        assert_eq!(swiss_pairs(&order, &points, &met), None);
+        assert_eq!(swiss_pairs(&order, &points, &|_, _| false).map(|pairs| pairs.len()), Some(15));
+    }
+
+    #[test]
+    fn tie_breaks_add_up_the_opponents_points() {
+        // 0 beats 1, 1 beats 2, 0 and 2 draw
+        let mut tournament = tournament(Format::RoundRobin, 3, 0);
+        tournament.rounds = vec![Round {
+            pairings: vec![
+                Pairing { white: 0, black: 1, result: String::from("1-0") },
+                Pairing { white: 1, black: 2, result: String::from("1-0") },
+                Pairing { white: 2, black: 0, result: String::from("1/2-1/2") },
+            ],
+            bye: None,
+        }];
+        let standings = tournament.standings();
+        let order: Vec<usize> = standings.iter().map(|standing| standing.player).collect();
+        assert_eq!(order, [0, 1, 2]);
+        // 0 has 1.5, 1 has 1, 2 has 0.5
+        assert_eq!(standings[0].buchholz, 1.5);
+        assert_eq!(standings[0].sonneborn_berger, 1.0 + 0.25);
+        assert_eq!(standings[1].buchholz, 2.0);
+        assert_eq!(standings[1].sonneborn_berger, 0.5);
+        assert_eq!(standings[2].buchholz, 2.5);
+        assert_eq!(standings[2].sonneborn_berger, 0.75);
+    }
+
+    #[test]
+    fn saved_tournaments_load_the_same() {
+        let mut tournament = tournament(Format::Swiss, 3, 2);
+        tournament.players[1].engine = Some(EngineConfig::parse(&["cmd=./engine", "arg=--uci", "option.Hash=64"]).unwrap());
+        tournament.pair_next_round().unwrap();
+        play_round(&mut tournament);
+        tournament.pair_next_round().unwrap();
+        let text = tournament.to_text();
+        let loaded = Tournament::from_text(&text).unwrap();
+        assert_eq!(loaded.to_text(), text);
+        assert_eq!(loaded.format, Format::Swiss);
+        assert_eq!(loaded.players[1].engine.as_ref().map(|engine| engine.name.as_str()), Some("Player 1"));
+        assert!(Tournament::from_text("game\t0\t1\t1-0\n").is_err());
+    }
+}
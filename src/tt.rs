@@ -0,0 +1,229 @@
+use crate::board::MAX_BOARD_SIZE;
+use crate::chess_move::Move;
+use crate::piece::PieceType;
+use crate::search::MATE;
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const DEFAULT_HASH_MB: usize = 16;
+pub const MAX_HASH_MB: usize = 4096;
+// scores past this are mates, which are stored relative to the node instead of the root
+const MATE_BOUND: i32 = MATE - 1000;
+
+// what the stored score says about the real value of the position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower, // the search failed high, the real score is at least this
+    Upper, // the search failed low, the real score is at most this
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TtEntry {
+    pub key: u64,
+    pub best_move: u32, // encode_move of the best move, 0 when there is none
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+    pub age: u8, // which search wrote it, old entries are replaced first
+}
+
+// everything but the key packed into one word:
+// move in bits 0-19, score + SCORE_OFFSET in 20-39, depth in 40-47, bound in 48-49, age in 50-57
+const SCORE_OFFSET: i64 = 1 << 19;
+
+impl TtEntry {
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        self.best_move as u64
+            | ((self.score as i64 + SCORE_OFFSET) as u64) << 20
+            | (self.depth as u64) << 40
+            | bound << 48
+            | (self.age as u64) << 50
+    }
+
+    fn unpack(key: u64, data: u64) -> Option<TtEntry> {
+        let bound = match (data >> 48) & 3 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            _ => return None, // an empty slot
+        };
+        Some(TtEntry {
+            key,
+            best_move: (data & 0xf_ffff) as u32,
+            score: (((data >> 20) & 0xf_ffff) as i64 - SCORE_OFFSET) as i32,
+            depth: (data >> 40) as u8,
+            bound,
+            age: (data >> 50) as u8,
+        })
+    }
+}
+
+// one entry as two words, the key is stored xor-ed with the data so an entry torn by two
+// threads writing at once no longer matches its key and is ignored instead of misread
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    fn load(&self) -> Option<TtEntry> {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.key.load(Ordering::Relaxed) ^ data;
+        TtEntry::unpack(key, data)
+    }
+
+    fn save(&self, entry: Option<TtEntry>) {
+        let (key, data) = entry.map(|entry| (entry.key, entry.pack())).unwrap_or((0, 0));
+        self.key.store(key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
+// each bucket has a depth-preferred slot that keeps the most expensive result
+// and an always-replace slot that keeps the most recent one
+type Bucket = [Slot; 2];
+
+// fixed-size hash table of search results keyed by zobrist hash
+// it is shared by all search threads without locking
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: u8,
+}
+
+impl TranspositionTable {
+    // a table using about 'megabytes' of memory, 0 turns it off
+    pub fn new(megabytes: usize) -> Self {
+        let count = megabytes.min(MAX_HASH_MB) * 1024 * 1024 / mem::size_of::<Bucket>();
+        TranspositionTable { buckets: (0..count).map(|_| Bucket::default()).collect(), age: 0 }
+    }
+
+    pub fn resize(&mut self, megabytes: usize) {
+        *self = TranspositionTable::new(megabytes);
+    }
+
+    pub fn clear(&mut self) {
+        for slot in self.buckets.iter().flatten() {
+            slot.save(None);
+        }
+        self.age = 0;
+    }
+
+    // called at the start of every search so older entries can be told apart
+    pub fn new_search(&mut self) {
+        self.age = self.age.wrapping_add(1);
+    }
+
+    fn bucket(&self, key: u64) -> Option<&Bucket> {
+        if self.buckets.is_empty() {
+            None
+        } else {
+            Some(&self.buckets[(key % self.buckets.len() as u64) as usize])
+        }
+    }
+
+    // looks up a position, mate scores come back relative to the root again
+    pub fn probe(&self, key: u64, ply: i32) -> Option<TtEntry> {
+        let mut entry = self.bucket(key)?.iter().filter_map(Slot::load).find(|entry| entry.key == key)?;
+        entry.score = score_from_table(entry.score, ply);
+        Some(entry)
+    }
+
+    pub fn store(&self, key: u64, depth: u32, score: i32, bound: Bound, best_move: Option<&Move>, ply: i32) {
+        let bucket = match self.bucket(key) {
+            Some(bucket) => bucket,
+            None => return,
+        };
+        let age = self.age;
+        let mut entry = TtEntry {
+            key,
+            best_move: best_move.map(encode_move).unwrap_or(0),
+            score: score_to_table(score, ply),
+            depth: depth.min(u8::MAX as u32) as u8,
+            bound,
+            age,
+        };
+        let slots = [bucket[0].load(), bucket[1].load()];
+        // keep the old best move if this search didn't find one, it's still good for ordering
+        if let Some(previous) = slots.iter().flatten().find(|previous| previous.key == key) {
+            if entry.best_move == 0 {
+                entry.best_move = previous.best_move;
+            }
+        }
+        let replace_deep = match slots[0] {
+            None => true,
+            Some(existing) => existing.key == key || existing.age != age || entry.depth >= existing.depth,
+        };
+        if replace_deep {
+            // the deep slot's old entry still gets a second chance in the other slot
+            if let Some(existing) = slots[0] {
+                if existing.key != key {
+                    bucket[1].save(Some(existing));
+                } else if slots[1].is_some_and(|other| other.key == key) {
+                    bucket[1].save(None);
+                }
+            }
+            bucket[0].save(Some(entry));
+        } else {
+            bucket[1].save(Some(entry));
+        }
+    }
+
+    // permille of the first thousand buckets written by the current search, as reported over UCI
+    pub fn hashfull(&self) -> usize {
+        let sample = self.buckets.len().min(1000);
+        if sample == 0 {
+            return 0;
+        }
+        let used = self.buckets[..sample].iter()
+            .flat_map(|bucket| bucket.iter().filter_map(Slot::load))
+            .filter(|entry| entry.age == self.age)
+            .count();
+        used * 1000 / (sample * 2)
+    }
+}
+
+// mates are stored as distance from the node so they stay right when found again at another ply
+fn score_to_table(score: i32, ply: i32) -> i32 {
+    if score > MATE_BOUND {
+        score + ply
+    } else if score < -MATE_BOUND {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: i32) -> i32 {
+    if score > MATE_BOUND {
+        score - ply
+    } else if score < -MATE_BOUND {
+        score + ply
+    } else {
+        score
+    }
+}
+
+// squares and promotion packed into a number, never 0 so 0 can mean no move
+pub fn encode_move(mv: &Move) -> u32 {
+    let square = |position: (usize, usize)| (position.0 * MAX_BOARD_SIZE + position.1) as u32;
+    let promotion = match mv.promoted_to {
+        None => 0,
+        Some(PieceType::Queen) => 1,
+        Some(PieceType::Rook) => 2,
+        Some(PieceType::Bishop) => 3,
+        Some(PieceType::Knight) => 4,
+        Some(PieceType::King) => 5,
+        Some(PieceType::Archbishop) => 6,
+        Some(PieceType::Chancellor) => 7,
+        Some(PieceType::Amazon) => 8,
+        Some(PieceType::Pawn) => 9,
+    };
+    1 + square(mv.from) + (square(mv.to) << 8) + (promotion << 16)
+}
@@ -0,0 +1,567 @@
+use crate::board::{Cell, GlyphSet, Orientation};
+use crate::chess_move::square_name;
+use crate::game::Game;
+use crate::notation;
+use crate::piece::{Color, Piece, PieceType};
+use crate::search::Computer;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// where the board starts on screen (1-based terminal rows/columns)
+const BOARD_TOP: usize = 3;
+const BOARD_LEFT: usize = 4;
+// each square is drawn three characters wide so it looks roughly square
+const SQUARE_WIDTH: usize = 3;
+
+// background colours (256-colour palette)
+const LIGHT_SQUARE: u8 = 187;
+const DARK_SQUARE: u8 = 101;
+const SELECTED_SQUARE: u8 = 33;
+const LEGAL_SQUARE: u8 = 71;
+const LAST_MOVE_SQUARE: u8 = 179;
+const CURSOR_SQUARE: u8 = 167;
+
+// puts the terminal in raw mode with mouse reporting on the alternate screen
+// and restores everything when dropped, even if the game panics
+struct TerminalGuard {
+    saved_settings: String,
+}
+
+impl TerminalGuard {
+    fn enter() -> Option<Self> {
+        let saved = Command::new("stty").arg("-g").stdin(Stdio::inherit()).output().ok()?;
+        if !saved.status.success() {
+            return None;
+        }
+        let status = Command::new("stty").args(["raw", "-echo"]).stdin(Stdio::inherit()).status().ok()?;
+        if !status.success() {
+            return None;
+        }
+        // alternate screen, hide cursor, mouse button reporting in SGR format
+        print!("\x1b[?1049h\x1b[?25l\x1b[?1000h\x1b[?1006h");
+        io::stdout().flush().ok();
+        Some(TerminalGuard { saved_settings: String::from_utf8_lossy(&saved.stdout).trim().to_string() })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        print!("\x1b[?1006l\x1b[?1000l\x1b[?25h\x1b[?1049l");
+        io::stdout().flush().ok();
+        let _ = Command::new("stty").arg(&self.saved_settings).stdin(Stdio::inherit()).status();
+    }
+}
+
+// what a chunk of terminal input means for the game
+enum Input {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Cancel,
+    Interrupt,
+    Click(usize, usize), // terminal column, row
+    Key(char),
+}
+
+// time used by each player, counted up since there is no time control
+struct Clocks {
+    white: Duration,
+    black: Duration,
+    turn_started: Instant,
+}
+
+impl Clocks {
+    fn new() -> Self {
+        Clocks { white: Duration::ZERO, black: Duration::ZERO, turn_started: Instant::now() }
+    }
+    fn elapsed(&self, color: Color, to_move: Color) -> Duration {
+        let used = match color {
+            Color::White => self.white,
+            Color::Black => self.black,
+        };
+        if color == to_move { used + self.turn_started.elapsed() } else { used }
+    }
+    // charges the finished turn to the player who just moved
+    fn end_turn(&mut self, color: Color) {
+        let spent = self.turn_started.elapsed();
+        match color {
+            Color::White => self.white += spent,
+            Color::Black => self.black += spent,
+        }
+        self.turn_started = Instant::now();
+    }
+}
+
+struct Tui<'a> {
+    game: Game,
+    // the engine when it plays one of the sides
+    computer: Option<&'a mut Computer>,
+    cursor: (usize, usize),
+    selected: Option<(usize, usize)>,
+    // a pawn move waiting for the player to pick the promotion piece
+    pending_promotion: Option<((usize, usize), (usize, usize))>,
+    clocks: Clocks,
+    glyphs: GlyphSet,
+    orientation: Orientation,
+    message: String,
+    game_over: bool,
+    quit: bool,
+}
+
+// runs the interactive full-screen mode, returns false if the terminal could not be set up
+// so the caller can fall back to the plain mode
+pub fn run(game: Game, glyphs: GlyphSet, orientation: Orientation, computer: Option<&mut Computer>) -> bool {
+    let guard = match TerminalGuard::enter() {
+        Some(guard) => guard,
+        None => return false,
+    };
+    let input = spawn_input_reader();
+    let cursor = (game.board.pawn_row, game.board.cols / 2);
+    let mut tui = Tui {
+        game,
+        computer,
+        cursor,
+        selected: None,
+        pending_promotion: None,
+        clocks: Clocks::new(),
+        glyphs,
+        orientation,
+        message: String::from("Arrows/mouse to pick a square, Enter to select, f to flip, q to quit"),
+        game_over: false,
+        quit: false,
+    };
+    tui.update_status();
+
+    let mut pending = Vec::new();
+    loop {
+        tui.draw();
+        if tui.computer_to_move() {
+            tui.play_computer();
+            continue;
+        }
+        // redraw at least once a second so the clocks keep ticking
+        match input.recv_timeout(Duration::from_millis(500)) {
+            Ok(bytes) => pending.extend(bytes),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        for event in parse_input(&mut pending) {
+            tui.handle(event);
+        }
+        if tui.quit {
+            break;
+        }
+    }
+    drop(guard);
+    true
+}
+
+// stdin is read on its own thread so the main loop can keep redrawing the clocks
+fn spawn_input_reader() -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0u8; 64];
+        loop {
+            match io::stdin().read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(count) => {
+                    if sender.send(buffer[..count].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    receiver
+}
+
+// turns raw bytes into events, leaving an incomplete escape sequence in 'pending'
+fn parse_input(pending: &mut Vec<u8>) -> Vec<Input> {
+    let mut events = Vec::new();
+    let mut i = 0;
+    while i < pending.len() {
+        let rest = &pending[i..];
+        if rest[0] == 0x1b {
+            if rest.len() == 1 {
+                // a lone escape is the Esc key
+                events.push(Input::Cancel);
+                i += 1;
+                continue;
+            }
+            if rest[1] != b'[' {
+                events.push(Input::Cancel);
+                i += 1;
+                continue;
+            }
+            if rest.len() < 3 {
+                break;
+            }
+            match rest[2] {
+                b'A' => { events.push(Input::Up); i += 3; }
+                b'B' => { events.push(Input::Down); i += 3; }
+                b'C' => { events.push(Input::Right); i += 3; }
+                b'D' => { events.push(Input::Left); i += 3; }
+                b'<' => {
+                    // SGR mouse report: ESC [ < button ; column ; row (M press | m release)
+                    let end = match rest.iter().position(|&b| b == b'M' || b == b'm') {
+                        Some(end) => end,
+                        None => break,
+                    };
+                    let fields: Vec<usize> = String::from_utf8_lossy(&rest[3..end])
+                        .split(';')
+                        .filter_map(|field| field.parse().ok())
+                        .collect();
+                    if rest[end] == b'M' && fields.len() == 3 && fields[0] == 0 {
+                        events.push(Input::Click(fields[1], fields[2]));
+                    }
+                    i += end + 1;
+                }
+                _ => {
+                    // some other sequence we don't use, skip up to its final byte
+                    let end = rest.iter().skip(2).position(|&b| (0x40..=0x7e).contains(&b)).map(|p| p + 2);
+                    match end {
+                        Some(end) => i += end + 1,
+                        None => break,
+                    }
+                }
+            }
+            continue;
+        }
+        events.push(match rest[0] {
+            b'\r' | b'\n' | b' ' => Input::Select,
+            3 => Input::Interrupt, // ctrl-c, raw mode doesn't turn it into a signal
+            // h, j, k and l move the cursor too, but only once handle knows no promotion piece is being picked
+            other => Input::Key(other as char),
+        });
+        i += 1;
+    }
+    pending.drain(..i);
+    events
+}
+
+impl Tui<'_> {
+    fn handle(&mut self, event: Input) {
+        if let Input::Interrupt = event {
+            self.quit = true;
+            return;
+        }
+        if let Some((from, to)) = self.pending_promotion {
+            let choice = match event {
+                Input::Key(key) => promotion_piece(key),
+                Input::Cancel => {
+                    self.pending_promotion = None;
+                    self.message = String::from("Promotion cancelled");
+                    return;
+                }
+                _ => None,
+            };
+            if let Some(piece_type) = choice {
+                if self.game.can_promote_to(piece_type) {
+                    self.pending_promotion = None;
+                    self.play(from, to, piece_type);
+                }
+            }
+            return;
+        }
+        let rows = self.game.board.rows;
+        let cols = self.game.board.cols;
+        // the cursor moves the way the arrows point on screen, whichever way the board is turned
+        let (line, column) = self.screen_square(self.cursor);
+        match event {
+            Input::Up | Input::Key('k') => self.cursor = self.screen_square((line.saturating_sub(1), column)),
+            Input::Down | Input::Key('j') => self.cursor = self.screen_square(((line + 1).min(rows - 1), column)),
+            Input::Right | Input::Key('l') => self.cursor = self.screen_square((line, (column + 1).min(cols - 1))),
+            Input::Left | Input::Key('h') => self.cursor = self.screen_square((line, column.saturating_sub(1))),
+            Input::Select => self.select(self.cursor),
+            Input::Cancel => self.selected = None,
+            Input::Click(column, row) => {
+                if let Some(square) = self.square_at(column, row) {
+                    self.cursor = square;
+                    self.select(square);
+                }
+            }
+            // 'q' only quits when it isn't picking a queen
+            Input::Key('q') => self.quit = true,
+            Input::Key('f') => self.orientation = self.orientation.flipped(self.game.current_player),
+            Input::Key(_) | Input::Interrupt => {}
+        }
+    }
+
+    // first pick chooses one of your pieces, the second pick is where it goes
+    fn select(&mut self, square: (usize, usize)) {
+        if self.game_over {
+            return;
+        }
+        let own_piece = matches!(self.game.board.get_piece_at(square), Some(piece) if piece.color == self.game.current_player);
+        match self.selected {
+            Some(from) if from == square => self.selected = None,
+            Some(from) if !own_piece || self.game.is_valid_move(from, square) => {
+                self.selected = None;
+                if !self.game.is_valid_move(from, square) {
+                    self.message = String::from("Invalid move. Please try again.");
+                } else if self.game.is_promotion_move(from, square) {
+                    self.pending_promotion = Some((from, square));
+                    self.message = format!("Promote to (q, r, b, n{}{})",
+                        if self.game.can_promote_to(PieceType::Archbishop) { ", a, c" } else { "" },
+                        if self.game.can_promote_to(PieceType::King) { ", k" } else { "" });
+                } else {
+                    self.play(from, square, PieceType::Queen);
+                }
+            }
+            _ if own_piece => self.selected = Some(square),
+            _ => {}
+        }
+    }
+
+    fn play(&mut self, from: (usize, usize), to: (usize, usize), promotion: PieceType) {
+        let mover = self.game.current_player;
+        self.game.apply_move(from, to, promotion);
+        self.clocks.end_turn(mover);
+        self.message.clear();
+        self.update_status();
+    }
+
+    fn computer_to_move(&self) -> bool {
+        !self.game_over && self.computer.as_ref().is_some_and(|computer| computer.color == self.game.current_player)
+    }
+
+    // the search runs on this thread, so input typed meanwhile is handled once it is done
+    fn play_computer(&mut self) {
+        let computer = match self.computer.as_mut() {
+            Some(computer) => computer,
+            None => return,
+        };
+        match computer.engine.choose_move(&self.game, computer.depth) {
+            Some((mv, from_book)) => {
+                let name = notation::san(&self.game, &mv);
+                self.play(mv.from, mv.to, mv.promoted_to.unwrap_or(PieceType::Queen));
+                if self.message.is_empty() {
+                    self.message = format!("Computer played {}{}", name, if from_book { " (book)" } else { "" });
+                }
+            }
+            None => self.game_over = true,
+        }
+    }
+
+    // same end of game checks as the plain mode
+    fn update_status(&mut self) {
+        let to_move = self.game.current_player.name();
+        if self.game.is_in_checkmate() {
+            self.message = format!("Checkmate! {} wins!", self.game.current_player.opponent().name());
+            self.game_over = true;
+        } else if let Some(winner) = self.game.antichess_winner() {
+            self.message = format!("{} has no moves left and wins!", winner.name());
+            self.game_over = true;
+        } else if !self.game.has_legal_move() {
+            self.message = String::from("Stalemate! The game is a draw.");
+            self.game_over = true;
+        } else if let Some(reason) = self.game.draw_reason() {
+            self.message = format!("Draw by {}!", reason);
+            self.game_over = true;
+        } else if self.game.is_in_check() {
+            self.message = format!("{} is in check!", to_move);
+        }
+    }
+
+    // maps a terminal position from a mouse click back to a board square
+    fn square_at(&self, column: usize, row: usize) -> Option<(usize, usize)> {
+        let rows = self.game.board.rows;
+        if column < BOARD_LEFT || row < BOARD_TOP || row >= BOARD_TOP + rows {
+            return None;
+        }
+        let col = (column - BOARD_LEFT) / SQUARE_WIDTH;
+        if col >= self.game.board.cols {
+            return None;
+        }
+        Some(self.screen_square((row - BOARD_TOP, col)))
+    }
+
+    // swaps between a board square and its (line, column) on screen counted from the top left
+    // the mapping is its own inverse so it works in both directions
+    fn screen_square(&self, (a, b): (usize, usize)) -> (usize, usize) {
+        let board = &self.game.board;
+        if self.orientation.black_at_bottom(self.game.current_player) {
+            (a, board.cols - 1 - b)
+        } else {
+            (board.rows - 1 - a, b)
+        }
+    }
+
+    fn draw(&self) {
+        let board = &self.game.board;
+        let mut frame = String::from("\x1b[2J\x1b[H");
+        frame.push_str(&format!("\x1b[1;{}H\x1b[1mChess\x1b[0m", BOARD_LEFT));
+
+        let legal: Vec<(usize, usize)> = match self.selected {
+            Some(from) => self.game.legal_moves().into_iter().filter(|&(f, _)| f == from).map(|(_, to)| to).collect(),
+            None => Vec::new(),
+        };
+        let last = self.game.last_move().map(|m| (m.from, m.to));
+
+        for line in 0..board.rows {
+            let row = self.screen_square((line, 0)).0;
+            frame.push_str(&format!("\x1b[{};1H{:>2} ", BOARD_TOP + line, row + 1));
+            for column in 0..board.cols {
+                let square = self.screen_square((line, column));
+                let (row, col) = square;
+                let background = if square == self.cursor {
+                    CURSOR_SQUARE
+                } else if Some(square) == self.selected {
+                    SELECTED_SQUARE
+                } else if legal.contains(&square) {
+                    LEGAL_SQUARE
+                } else if last.is_some_and(|(from, to)| from == square || to == square) {
+                    LAST_MOVE_SQUARE
+                } else if (row + col) % 2 == 0 {
+                    DARK_SQUARE
+                } else {
+                    LIGHT_SQUARE
+                };
+                let contents = match board.grid[row][col] {
+                    Cell::Occupied(piece) => {
+                        let foreground = if piece.color == Color::White { 231 } else { 16 };
+                        format!("\x1b[1;38;5;{}m{}", foreground, self.glyph(&piece))
+                    }
+                    Cell::Empty if legal.contains(&square) => String::from("\x1b[38;5;16m•"),
+                    Cell::Empty => String::from(" "),
+                };
+                frame.push_str(&format!("\x1b[48;5;{}m {} \x1b[0m", background, contents));
+            }
+        }
+        let files: String = (0..board.cols).map(|column| {
+            let col = self.screen_square((0, column)).1;
+            format!(" {} ", (b'a' + col as u8) as char)
+        }).collect();
+        frame.push_str(&format!("\x1b[{};{}H{}", BOARD_TOP + board.rows, BOARD_LEFT, files));
+
+        // side panel: whose turn, clocks and the move list
+        let panel = BOARD_LEFT + board.cols * SQUARE_WIDTH + 4;
+        let mut line = BOARD_TOP;
+        let to_move = self.game.current_player;
+        for color in [Color::White, Color::Black] {
+            let marker = if color == to_move && !self.game_over { "▶" } else { " " };
+            frame.push_str(&format!("\x1b[{};{}H{} {:<6} {}", line, panel, marker, color.name(), format_clock(self.clocks.elapsed(color, to_move))));
+            line += 1;
+        }
+        line += 1;
+        frame.push_str(&format!("\x1b[{};{}HMoves", line, panel));
+        line += 1;
+        // only the most recent moves fit next to the board
+        let move_rows = board.rows.saturating_sub(4).max(1);
+        let numbered: Vec<String> = self.game.history.chunks(2).enumerate().map(|(i, pair)| {
+            let black = pair.get(1).map(|m| m.notation()).unwrap_or_default();
+            format!("{:>3}. {:<7} {}", i + 1, pair[0].notation(), black)
+        }).collect();
+        for entry in numbered.iter().skip(numbered.len().saturating_sub(move_rows)) {
+            frame.push_str(&format!("\x1b[{};{}H{}", line, panel, entry));
+            line += 1;
+        }
+
+        let status_row = BOARD_TOP + board.rows + 2;
+        let cursor_name = square_name(self.cursor);
+        frame.push_str(&format!("\x1b[{};1H{} [{}]", status_row, self.message, cursor_name));
+        // raw mode doesn't translate \n so every line is positioned explicitly above
+        print!("{}", frame);
+        io::stdout().flush().ok();
+    }
+
+    // the colours already tell the sides apart so Unicode uses the filled figurines for both
+    fn glyph(&self, piece: &Piece) -> &'static str {
+        match self.glyphs {
+            GlyphSet::Unicode => figurine(piece),
+            letters => letters.symbol(piece),
+        }
+    }
+
+}
+
+// filled figurines for every piece, the colour comes from the foreground
+fn figurine(piece: &Piece) -> &'static str {
+    match piece.piece_type {
+        PieceType::King => "♚",
+        PieceType::Queen => "♛",
+        PieceType::Rook => "♜",
+        PieceType::Bishop => "♝",
+        PieceType::Knight => "♞",
+        PieceType::Pawn => "♟",
+        PieceType::Archbishop => "A",
+        PieceType::Chancellor => "C",
+        PieceType::Amazon => "Z",
+    }
+}
+
+fn promotion_piece(key: char) -> Option<PieceType> {
+    match key.to_ascii_lowercase() {
+        'q' => Some(PieceType::Queen),
+        'r' => Some(PieceType::Rook),
+        'b' => Some(PieceType::Bishop),
+        'n' => Some(PieceType::Knight),
+        'k' => Some(PieceType::King),
+        'a' => Some(PieceType::Archbishop),
+        'c' => Some(PieceType::Chancellor),
+        _ => None,
+    }
+}
+
+fn format_clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen;
+    use crate::game::Variant;
+
+    fn tui(game: Game) -> Tui<'static> {
+        Tui {
+            game,
+            computer: None,
+            cursor: (0, 0),
+            selected: None,
+            pending_promotion: None,
+            clocks: Clocks::new(),
+            glyphs: GlyphSet::Ascii,
+            orientation: Orientation::White,
+            message: String::new(),
+            game_over: false,
+            quit: false,
+        }
+    }
+
+    fn press(tui: &mut Tui, keys: &[u8]) {
+        for event in parse_input(&mut keys.to_vec()) {
+            tui.handle(event);
+        }
+    }
+
+    #[test]
+    fn stalemate_ends_the_game() {
+        let mut tui = tui(fen::parse_for(Variant::Standard, "k7/8/1K6/8/8/8/8/2Q5 w - - 0 1").unwrap());
+        tui.play((0, 2), (6, 2), PieceType::Queen);
+        assert!(tui.game_over);
+        assert_eq!(tui.message, "Stalemate! The game is a draw.");
+    }
+
+    #[test]
+    fn k_picks_a_king_when_promoting() {
+        let mut tui = tui(fen::parse_for(Variant::Antichess, "8/4P3/8/8/8/8/8/k7 w - - 0 1").unwrap());
+        tui.select((6, 4));
+        tui.select((7, 4));
+        assert_eq!(tui.pending_promotion, Some(((6, 4), (7, 4))));
+        press(&mut tui, b"k");
+        assert_eq!(tui.game.board.get_piece_at((7, 4)).map(|piece| piece.piece_type), Some(PieceType::King));
+        // without a promotion to pick it moves the cursor, j down the screen and k back up
+        tui.cursor = (3, 3);
+        press(&mut tui, b"j");
+        assert_eq!(tui.cursor, (2, 3));
+        press(&mut tui, b"k");
+        assert_eq!(tui.cursor, (3, 3));
+    }
+}
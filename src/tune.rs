@@ -0,0 +1,184 @@
+use crate::board::Board;
+use crate::eval::{self, EvalParams};
+use crate::fen;
+use std::fs;
+use std::thread;
+
+// a quiet position and how the game it came from ended, 1.0 is a White win
+struct Sample {
+    board: Board,
+    result: f64,
+}
+
+// reads one position per line as a FEN followed by the result, written either as
+// 1-0 / 0-1 / 1/2-1/2 (optionally quoted, as in EPD c9 fields) or as [1.0] / [0.5] / [0.0]
+fn parse_sample(line: &str) -> Option<Result<Sample, String>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let result = fields.iter().rev().find_map(|field| {
+        match field.trim_matches(|c: char| c == '"' || c == ';' || c == '[' || c == ']') {
+            "1-0" | "1.0" => Some(1.0),
+            "0-1" | "0.0" => Some(0.0),
+            "1/2-1/2" | "0.5" => Some(0.5),
+            _ => None,
+        }
+    });
+    let result = match result {
+        Some(result) => result,
+        None => return Some(Err(format!("no result in '{}'", line))),
+    };
+    // placement, side, castling and en passant, the move counters are left out since
+    // they could be mistaken for a result
+    let fen = fields.iter().take(4).copied().collect::<Vec<_>>().join(" ");
+    Some(fen::parse(&fen).map(|game| Sample { board: game.board, result }))
+}
+
+fn load_samples(path: &str) -> Result<Vec<Sample>, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    let mut samples = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        match parse_sample(line) {
+            Some(Ok(sample)) => samples.push(sample),
+            Some(Err(error)) => return Err(format!("{}:{}: {}", path, number + 1, error)),
+            None => {}
+        }
+    }
+    if samples.is_empty() {
+        return Err(format!("{}: no positions", path));
+    }
+    Ok(samples)
+}
+
+// expected score for White given an evaluation in centipawns
+fn sigmoid(score: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score / 400.0))
+}
+
+// mean squared difference between the results and what the evaluation predicts,
+// the positions are split over all cores since this runs once per parameter change
+fn error(samples: &[Sample], params: &EvalParams, k: f64) -> f64 {
+    let threads = thread::available_parallelism().map(|count| count.get()).unwrap_or(1);
+    let chunk = samples.len().div_ceil(threads);
+    let total: f64 = thread::scope(|scope| {
+        let workers: Vec<_> = samples.chunks(chunk)
+            .map(|part| scope.spawn(move || {
+                part.iter()
+                    .map(|sample| (sample.result - sigmoid(eval::evaluate(&sample.board, params) as f64, k)).powi(2))
+                    .sum::<f64>()
+            }))
+            .collect();
+        workers.into_iter().map(|worker| worker.join().unwrap_or(0.0)).sum()
+    });
+    total / samples.len() as f64
+}
+
+// the sigmoid scaling that fits the starting weights best, found by narrowing a scan
+fn best_k(samples: &[Sample], params: &EvalParams) -> f64 {
+    let scores: Vec<(f64, f64)> = samples.iter().map(|sample| (eval::evaluate(&sample.board, params) as f64, sample.result)).collect();
+    let fit = |k: f64| scores.iter().map(|&(score, result)| (result - sigmoid(score, k)).powi(2)).sum::<f64>();
+    let (mut low, mut high) = (0.0, 3.0);
+    for _ in 0..4 {
+        let step = (high - low) / 20.0;
+        let best = (0..=20).map(|i| low + step * i as f64)
+            .min_by(|a, b| fit(*a).total_cmp(&fit(*b)))
+            .unwrap_or(1.0);
+        low = (best - step).max(0.0);
+        high = best + step;
+    }
+    (low + high) / 2.0
+}
+
+// Texel tuning: nudges every weight up or down by one and keeps the change whenever it lowers the
+// error over the positions, repeating until a pass changes nothing or 'passes' runs out
+// the weights are written to 'output' after every pass so a long run can be stopped at any time
+pub fn run(positions: &str, start: EvalParams, output: &str, passes: u32) -> Result<(), String> {
+    let samples = load_samples(positions)?;
+    let mut params = start;
+    let k = best_k(&samples, &params);
+    let mut best_error = error(&samples, &params, k);
+    println!("{} positions, K = {:.3}, starting error {:.6}", samples.len(), k, best_error);
+
+    let count = params.entries_mut().len();
+    for pass in 1..=passes {
+        let mut improved = 0;
+        for index in 0..count {
+            for delta in [1, -1] {
+                let mut candidate = params.clone();
+                *candidate.entries_mut()[index].1 += delta;
+                let candidate_error = error(&samples, &candidate, k);
+                if candidate_error < best_error {
+                    best_error = candidate_error;
+                    params = candidate;
+                    improved += 1;
+                    break;
+                }
+            }
+        }
+        params.save(output)?;
+        println!("pass {}: {} weights changed, error {:.6}, saved to {}", pass, improved, best_error, output);
+        if improved == 0 {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(lines: &[&str]) -> Vec<Sample> {
+        lines.iter().filter_map(|line| parse_sample(line)).collect::<Result<_, _>>().unwrap()
+    }
+
+    // the side a queen up mostly wins, and an even position is drawn
+    const LINES: [&str; 5] = [
+        "4k3/8/8/8/8/8/8/3QK3 w - - 0 1 1-0",
+        "4k3/8/8/8/8/8/8/3QK3 w - - 0 1 1/2-1/2",
+        "4k3/8/8/8/8/8/8/3QK3 b - - [1.0]",
+        "3qk3/8/8/8/8/8/8/4K3 w - - 0 1 \"0-1\";",
+        "4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 1/2-1/2",
+    ];
+
+    #[test]
+    fn samples_are_read_with_either_kind_of_result() {
+        let samples = samples(&LINES);
+        let results: Vec<f64> = samples.iter().map(|sample| sample.result).collect();
+        assert_eq!(results, [1.0, 0.5, 1.0, 0.0, 0.5]);
+        assert!(parse_sample("# a comment").is_none());
+        assert!(parse_sample("4k3/8/8/8/8/8/8/3QK3 w - -").unwrap().is_err());
+    }
+
+    #[test]
+    fn best_k_fits_better_than_its_neighbours() {
+        let samples = samples(&LINES);
+        let params = EvalParams::default();
+        let k = best_k(&samples, &params);
+        assert!(k > 0.0 && k <= 3.0);
+        let best = error(&samples, &params, k);
+        for other in [k / 2.0, k * 2.0, 0.0] {
+            assert!(best <= error(&samples, &params, other));
+        }
+    }
+
+    #[test]
+    fn a_pass_lowers_the_error() {
+        // whoever is a pawn up wins, which the starting weights undervalue
+        let lines = ["4k3/p7/8/8/8/8/PP6/4K3 w - - 1-0", "4k3/pp6/8/8/8/8/P7/4K3 w - - 0-1"];
+        let samples = samples(&lines);
+        let path = std::env::temp_dir().join(format!("tune_test_{}.txt", std::process::id()));
+        let positions = path.to_str().unwrap().to_string();
+        let output = format!("{}.out", positions);
+        std::fs::write(&positions, lines.join("\n")).unwrap();
+        run(&positions, EvalParams::default(), &output, 1).unwrap();
+        let tuned = EvalParams::load(&output).unwrap();
+        let start = EvalParams::default();
+        let k = best_k(&samples, &start);
+        assert!(error(&samples, &tuned, k) < error(&samples, &start, k));
+        let _ = std::fs::remove_file(&positions);
+        let _ = std::fs::remove_file(&output);
+    }
+}
@@ -0,0 +1,309 @@
+use crate::eval::EvalParams;
+use crate::fen;
+use crate::game::Game;
+use crate::notation;
+use crate::polyglot::Book;
+use crate::search::{Engine, SearchControl, SearchResult, MATE, MAX_DEPTH, MAX_MULTI_PV};
+use crate::tablebase::Tablebases;
+use crate::time_manager::{Limits, SystemClock, TimeManager};
+use crate::tt::{DEFAULT_HASH_MB, MAX_HASH_MB};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+// depth used for `go` with neither a depth nor any time information
+const DEFAULT_DEPTH: u32 = 5;
+const MAX_THREADS: usize = 256;
+
+// speaks the Universal Chess Interface on stdin/stdout so GUIs and match runners can use the engine
+pub fn run() {
+    let mut engine = Engine::new();
+    let mut game = Game::new();
+    // the book is only used when the GUI turns on OwnBook, as the protocol expects
+    let mut own_book = false;
+    let lines = spawn_line_reader();
+    while let Ok(line) = lines.recv() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.first().copied() {
+            Some("uci") => {
+                println!("id name final_proj");
+                println!("id author final_proj developers");
+                println!("option name Hash type spin default {} min 0 max {}", DEFAULT_HASH_MB, MAX_HASH_MB);
+                println!("option name Threads type spin default 1 min 1 max {}", MAX_THREADS);
+                println!("option name Ponder type check default false");
+                println!("option name MultiPV type spin default 1 min 1 max {}", MAX_MULTI_PV);
+                println!("option name EvalFile type string default <default>");
+                println!("option name OwnBook type check default false");
+                println!("option name BookFile type string default <empty>");
+                println!("option name SyzygyPath type string default <empty>");
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("setoption") => set_option(&mut engine, &mut own_book, &words),
+            Some("ucinewgame") => {
+                engine.tt.clear();
+                game = Game::new();
+            }
+            Some("position") => {
+                if let Some(position) = parse_position(&words) {
+                    game = position;
+                }
+            }
+            Some("go") => {
+                if let Some(mv) = engine.book_move(&game).filter(|_| own_book) {
+                    println!("info string book move");
+                    println!("bestmove {}", mv.notation());
+                } else if !go(&mut engine, &game, &words, &lines) {
+                    break;
+                }
+            }
+            Some("quit") => break,
+            _ => {}
+        }
+        io::stdout().flush().ok();
+    }
+}
+
+// stdin is read on its own thread so stop and ponderhit arrive while a search is running
+fn spawn_line_reader() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+// searches on a worker thread while still answering stop, ponderhit and isready
+// `go ponder` and `go infinite` don't answer until they are told to, even if the search ends first
+// returns false if quit arrived during the search
+fn go(engine: &mut Engine, game: &Game, words: &[&str], lines: &Receiver<String>) -> bool {
+    let limits = parse_limits(words);
+    let infinite = words.contains(&"infinite");
+    let mut waiting = infinite || words.contains(&"ponder");
+    let time = limits.is_timed().then(|| TimeManager::new(Box::new(SystemClock::new()), &limits, game.current_player));
+    if let Some(time) = time.as_ref().filter(|_| waiting && !infinite) {
+        time.start_pondering();
+    }
+    let depth = value_after(words, "depth").unwrap_or(if time.is_some() || waiting { MAX_DEPTH } else { DEFAULT_DEPTH });
+    let stop = AtomicBool::new(false);
+    let report = |result: &SearchResult| {
+        print_info(result);
+        io::stdout().flush().ok();
+    };
+    let control = SearchControl { time: time.as_ref(), stop: Some(&stop), report: Some(&report) };
+    let mut keep_running = true;
+    let result = thread::scope(|scope| {
+        let searcher = scope.spawn(|| engine.search_controlled(game, depth, &control));
+        while waiting || !searcher.is_finished() {
+            let line = match lines.recv_timeout(Duration::from_millis(10)) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => String::from("quit"),
+            };
+            match line.trim() {
+                "stop" => {
+                    stop.store(true, Ordering::Relaxed);
+                    waiting = false;
+                }
+                // the opponent played the move we pondered on, our clock is running now
+                "ponderhit" => {
+                    if let Some(time) = &time {
+                        time.ponderhit();
+                    }
+                    waiting = infinite;
+                }
+                "isready" => println!("readyok"),
+                "quit" => {
+                    stop.store(true, Ordering::Relaxed);
+                    waiting = false;
+                    keep_running = false;
+                }
+                _ => {}
+            }
+            io::stdout().flush().ok();
+        }
+        searcher.join()
+    });
+    match result {
+        Ok(result) => {
+            print_info(&result);
+            match (result.best_move, result.pv.get(1)) {
+                (Some(mv), Some(reply)) => println!("bestmove {} ponder {}", mv.notation(), reply.notation()),
+                (Some(mv), None) => println!("bestmove {}", mv.notation()),
+                (None, _) => println!("bestmove 0000"),
+            }
+        }
+        Err(_) => println!("bestmove 0000"),
+    }
+    keep_running
+}
+
+// setoption name <name> value <value>
+fn set_option(engine: &mut Engine, own_book: &mut bool, words: &[&str]) {
+    let name_at = words.iter().position(|&word| word == "name");
+    let value_at = words.iter().position(|&word| word == "value");
+    let (name_at, value_at) = match (name_at, value_at) {
+        (Some(name_at), Some(value_at)) if value_at > name_at => (name_at, value_at),
+        _ => return,
+    };
+    let name = words[name_at + 1..value_at].join(" ");
+    let value = words[value_at + 1..].join(" ");
+    if name.eq_ignore_ascii_case("Hash") {
+        if let Ok(megabytes) = value.parse::<usize>() {
+            engine.tt.resize(megabytes.min(MAX_HASH_MB));
+        }
+    } else if name.eq_ignore_ascii_case("Threads") {
+        if let Ok(threads) = value.parse::<usize>() {
+            engine.threads = threads.clamp(1, MAX_THREADS);
+        }
+    } else if name.eq_ignore_ascii_case("MultiPV") {
+        if let Ok(lines) = value.parse::<usize>() {
+            engine.multi_pv = lines.clamp(1, MAX_MULTI_PV);
+        }
+    } else if name.eq_ignore_ascii_case("EvalFile") {
+        // <default> or an empty value goes back to the built-in weights
+        if value.is_empty() || value == "<default>" {
+            engine.params = EvalParams::default();
+        } else {
+            match EvalParams::load(&value) {
+                Ok(params) => engine.params = params,
+                Err(error) => println!("info string {}", error),
+            }
+        }
+    } else if name.eq_ignore_ascii_case("OwnBook") {
+        *own_book = value.eq_ignore_ascii_case("true");
+    } else if name.eq_ignore_ascii_case("BookFile") {
+        if value.is_empty() || value == "<empty>" {
+            engine.book = None;
+        } else {
+            match Book::load(&value) {
+                Ok(book) => engine.book = Some(book),
+                Err(error) => println!("info string {}", error),
+            }
+        }
+    } else if name.eq_ignore_ascii_case("SyzygyPath") {
+        if value.is_empty() || value == "<empty>" {
+            engine.tablebases = None;
+        } else {
+            match Tablebases::open(&value) {
+                Ok(tables) if tables.is_empty() => {
+                    println!("info string no Syzygy tables in {}", value);
+                    engine.tablebases = None;
+                }
+                Ok(tables) => {
+                    println!("info string found {} Syzygy table files, up to {} pieces", tables.len(), tables.max_pieces);
+                    engine.tablebases = Some(tables);
+                }
+                Err(error) => println!("info string {}", error),
+            }
+        }
+    }
+}
+
+// position startpos [moves e2e4 ...] or position fen <fen> [moves ...]
+fn parse_position(words: &[&str]) -> Option<Game> {
+    let moves_at = words.iter().position(|&word| word == "moves");
+    let mut game = match words.get(1).copied() {
+        Some("startpos") => Game::new(),
+        Some("fen") => fen::parse(&words[2..moves_at.unwrap_or(words.len())].join(" ")).ok()?,
+        _ => return None,
+    };
+    if let Some(moves_at) = moves_at {
+        for text in &words[moves_at + 1..] {
+            let mv = notation::parse_uci_move(&game, text)?;
+            game.make_move(&mv);
+        }
+    }
+    Some(game)
+}
+
+// go wtime <ms> btime <ms> winc <ms> binc <ms> movestogo <n> movetime <ms>
+fn parse_limits(words: &[&str]) -> Limits {
+    let milliseconds = |name| value_after(words, name).map(Duration::from_millis);
+    Limits {
+        white_time: milliseconds("wtime"),
+        black_time: milliseconds("btime"),
+        white_increment: milliseconds("winc"),
+        black_increment: milliseconds("binc"),
+        moves_to_go: value_after(words, "movestogo"),
+        move_time: milliseconds("movetime"),
+    }
+}
+
+fn value_after<T: std::str::FromStr>(words: &[&str], name: &str) -> Option<T> {
+    let at = words.iter().position(|&word| word == name)?;
+    words.get(at + 1)?.parse().ok()
+}
+
+// mate scores are reported in moves rather than centipawns
+fn score_text(score: i32) -> String {
+    if score.abs() > MATE - 1000 {
+        let plies = MATE - score.abs();
+        let moves = (plies + 1) / 2;
+        format!("mate {}", if score > 0 { moves } else { -moves })
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+// one line per MultiPV candidate, best first
+fn print_info(result: &SearchResult) {
+    let progress = format!(
+        "nodes {} nps {} time {} hashfull {}",
+        result.nodes, result.nodes_per_second(), result.elapsed.as_millis(), result.hashfull
+    );
+    if result.lines.is_empty() {
+        println!("info depth {} score {} {}", result.depth, score_text(result.score), progress);
+    }
+    for (rank, line) in result.lines.iter().enumerate() {
+        let pv: Vec<String> = line.pv.iter().map(|mv| mv.notation()).collect();
+        println!("info depth {} multipv {} score {} {} pv {}", result.depth, rank + 1, score_text(line.score), progress, pv.join(" "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    // runs `go` with the given words while the lines arrive as if typed after it
+    fn go_with(words: &str, lines: &[&str]) -> (bool, Duration) {
+        let (sender, receiver) = mpsc::channel();
+        for line in lines {
+            sender.send(line.to_string()).unwrap();
+        }
+        let started = Instant::now();
+        let keep_running = go(&mut Engine::new(), &Game::new(), &words.split_whitespace().collect::<Vec<_>>(), &receiver);
+        (keep_running, started.elapsed())
+    }
+
+    #[test]
+    fn stop_ends_pondering_and_infinite_searches() {
+        // without the stop neither would ever answer, with a minute on the clock
+        let (keep_running, elapsed) = go_with("go ponder wtime 60000 btime 60000", &["stop"]);
+        assert!(keep_running);
+        assert!(elapsed < Duration::from_secs(30));
+        let (keep_running, _) = go_with("go infinite", &["isready", "stop"]);
+        assert!(keep_running);
+    }
+
+    #[test]
+    fn ponderhit_lets_the_search_answer_on_its_own_time() {
+        let (keep_running, elapsed) = go_with("go ponder movetime 50", &["ponderhit"]);
+        assert!(keep_running);
+        assert!(elapsed < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn quit_during_a_search_ends_the_loop() {
+        let (keep_running, _) = go_with("go infinite", &["quit"]);
+        assert!(!keep_running);
+    }
+}
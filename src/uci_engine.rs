@@ -0,0 +1,184 @@
+use crate::search::MATE;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// how long an engine gets to answer `uci` and `isready`
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// how to start an engine and set it up, read from words like
+// `name=new cmd=./final_proj arg=uci option.Hash=64`
+#[derive(Clone, Debug)]
+pub struct EngineConfig {
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub options: Vec<(String, String)>,
+}
+
+impl EngineConfig {
+    pub fn parse(words: &[&str]) -> Result<EngineConfig, String> {
+        let mut config = EngineConfig { name: String::new(), command: String::new(), args: Vec::new(), options: Vec::new() };
+        for word in words {
+            match word.split_once('=') {
+                Some(("name", value)) => config.name = value.to_string(),
+                Some(("cmd", value)) => config.command = value.to_string(),
+                Some(("arg", value)) => config.args.push(value.to_string()),
+                Some((key, value)) if key.starts_with("option.") => config.options.push((key["option.".len()..].to_string(), value.to_string())),
+                _ => return Err(format!("unknown engine setting '{}', expected name=, cmd=, arg= or option.NAME=", word)),
+            }
+        }
+        if config.command.is_empty() {
+            return Err(String::from("an engine needs cmd=PATH"));
+        }
+        if config.name.is_empty() {
+            config.name = config.command.clone();
+        }
+        Ok(config)
+    }
+
+    // the settings as parse reads them, without the name
+    pub fn to_words(&self) -> Vec<String> {
+        let mut words = vec![format!("cmd={}", self.command)];
+        words.extend(self.args.iter().map(|arg| format!("arg={}", arg)));
+        words.extend(self.options.iter().map(|(name, value)| format!("option.{}={}", name, value)));
+        words
+    }
+}
+
+// an engine running as a child process, spoken to over its stdin and stdout
+// its output is read on a separate thread so a hung engine can be timed out
+pub struct UciEngine {
+    pub name: String,
+    child: Child,
+    input: ChildStdin,
+    lines: Receiver<String>,
+}
+
+// the engine's answer to one `go`
+pub struct EngineMove {
+    // in UCI notation, e.g. e7e8q
+    pub mv: String,
+    // the last score it reported, from its own point of view, mates as in search::MATE
+    pub score: Option<i32>,
+    pub elapsed: Duration,
+}
+
+impl UciEngine {
+    // starts the engine, applies the options and waits until it is ready
+    pub fn start(config: &EngineConfig) -> Result<UciEngine, String> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| format!("{}: {}", config.command, error))?;
+        let (input, output) = match (child.stdin.take(), child.stdout.take()) {
+            (Some(input), Some(output)) => (input, output),
+            _ => return Err(format!("{}: no pipes to the engine", config.command)),
+        };
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(output).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = UciEngine { name: config.name.clone(), child, input, lines };
+        engine.send("uci")?;
+        engine.wait_for("uciok", HANDSHAKE_TIMEOUT)?;
+        for (name, value) in &config.options {
+            engine.send(&format!("setoption name {} value {}", name, value))?;
+        }
+        engine.ready()?;
+        Ok(engine)
+    }
+
+    pub fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.input, "{}", line)
+            .and_then(|_| self.input.flush())
+            .map_err(|error| format!("{}: {}", self.name, error))
+    }
+
+    pub fn ready(&mut self) -> Result<(), String> {
+        self.send("isready")?;
+        self.wait_for("readyok", HANDSHAKE_TIMEOUT).map(|_| ())
+    }
+
+    pub fn new_game(&mut self) -> Result<(), String> {
+        self.send("ucinewgame")?;
+        self.ready()
+    }
+
+    // sends the position and the go command, then waits up to 'timeout' for bestmove
+    pub fn go(&mut self, position: &str, go: &str, timeout: Duration) -> Result<EngineMove, String> {
+        self.send(position)?;
+        let started = Instant::now();
+        self.send(go)?;
+        let mut score = None;
+        loop {
+            let left = timeout.saturating_sub(started.elapsed());
+            let line = self.read_line(left)?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.first().copied() {
+                Some("info") => score = parse_score(&words).or(score),
+                Some("bestmove") => {
+                    let mv = words.get(1).ok_or(format!("{}: bestmove without a move", self.name))?;
+                    return Ok(EngineMove { mv: mv.to_string(), score, elapsed: started.elapsed() });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn read_line(&mut self, timeout: Duration) -> Result<String, String> {
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(format!("{} stopped responding", self.name)),
+            Err(RecvTimeoutError::Disconnected) => Err(format!("{} exited", self.name)),
+        }
+    }
+
+    fn wait_for(&mut self, word: &str, timeout: Duration) -> Result<String, String> {
+        let started = Instant::now();
+        loop {
+            let line = self.read_line(timeout.saturating_sub(started.elapsed()))?;
+            if line.split_whitespace().next() == Some(word) {
+                return Ok(line);
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    // asks the engine to quit and makes sure it does
+    fn drop(&mut self) {
+        self.send("quit").ok();
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+// `score cp 35` or `score mate -3` from an info line, bounds (lowerbound/upperbound) included
+fn parse_score(words: &[&str]) -> Option<i32> {
+    let at = words.iter().position(|&word| word == "score")?;
+    let value: i32 = words.get(at + 2)?.parse().ok()?;
+    match *words.get(at + 1)? {
+        "cp" => Some(value),
+        "mate" if value > 0 => Some(MATE - (2 * value - 1)),
+        "mate" => Some(-MATE - 2 * value),
+        _ => None,
+    }
+}
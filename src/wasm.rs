@@ -0,0 +1,117 @@
+use crate::chess_move::Move;
+use crate::fen;
+use crate::game::{self, GameState, Variant};
+use crate::notation;
+use wasm_bindgen::prelude::*;
+
+// the rules for JavaScript, exported as the class Game
+//   const game = new Game("standard");
+//   game.makeMove("e2e4");
+//   game.legalMoves();   // ["e7e5", "e7e6", ...]
+// moves go in and come out in UCI notation (e2e4, e7e8q), makeMove also takes SAN (Nf3)
+#[wasm_bindgen(js_name = Game)]
+pub struct WasmGame {
+    game: game::Game,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WasmGame {
+    // a game in its starting position, standard chess when no variant is named
+    #[wasm_bindgen(constructor)]
+    pub fn new(variant: Option<String>) -> Result<WasmGame, JsError> {
+        Ok(WasmGame { game: game::Game::with_variant(variant_named(variant.as_deref())?) })
+    }
+
+    #[wasm_bindgen(js_name = fromFen)]
+    pub fn from_fen(text: &str, variant: Option<String>) -> Result<WasmGame, JsError> {
+        let mut game = WasmGame { game: game::Game::with_variant(variant_named(variant.as_deref())?) };
+        game.load_fen(text)?;
+        Ok(game)
+    }
+
+    // replaces the position, the move history starts over
+    #[wasm_bindgen(js_name = loadFen)]
+    pub fn load_fen(&mut self, text: &str) -> Result<(), JsError> {
+        self.game = fen::parse_for(self.game.variant, text).map_err(|error| JsError::new(&error))?;
+        Ok(())
+    }
+
+    pub fn fen(&self) -> String {
+        fen::to_fen(&self.game)
+    }
+
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> Vec<String> {
+        self.game.generate_moves().iter().map(Move::notation).collect()
+    }
+
+    // the same moves in SAN, in the same order
+    #[wasm_bindgen(js_name = legalMovesSan)]
+    pub fn legal_moves_san(&self) -> Vec<String> {
+        self.game.generate_moves().iter().map(|mv| notation::san(&self.game, mv)).collect()
+    }
+
+    #[wasm_bindgen(js_name = isLegal)]
+    pub fn is_legal(&self, text: &str) -> bool {
+        self.find_move(text).is_some()
+    }
+
+    // plays the move and returns it in SAN, or throws when it isn't legal
+    #[wasm_bindgen(js_name = makeMove)]
+    pub fn make_move(&mut self, text: &str) -> Result<String, JsError> {
+        let mv = self.find_move(text).ok_or_else(|| JsError::new(&format!("'{}' is not a legal move", text)))?;
+        let san = notation::san(&self.game, &mv);
+        self.game.make_move(&mv);
+        Ok(san)
+    }
+
+    // "white" or "black"
+    pub fn turn(&self) -> String {
+        self.game.current_player.name().to_lowercase()
+    }
+
+    // "ongoing", "check", "checkmate" or "stalemate"
+    pub fn state(&self) -> String {
+        self.game.state.name().to_string()
+    }
+
+    pub fn variant(&self) -> String {
+        self.game.variant.name().to_string()
+    }
+
+    #[wasm_bindgen(js_name = isOver)]
+    pub fn is_over(&self) -> bool {
+        matches!(self.game.state, GameState::Checkmate | GameState::Stalemate | GameState::Draw)
+    }
+
+    // "white" or "black" once someone has won, undefined while the game goes on or after a draw
+    // the side to move has lost when mated, but in antichess has won when it can't move
+    pub fn winner(&self) -> Option<String> {
+        let winner = match self.game.state {
+            _ if self.game.variant == Variant::Antichess => self.game.antichess_winner(),
+            GameState::Checkmate => Some(self.game.current_player.opponent()),
+            _ => None,
+        };
+        winner.map(|color| color.name().to_lowercase())
+    }
+
+    // every move played since the start or the last loadFen, in UCI notation
+    pub fn history(&self) -> Vec<String> {
+        self.game.history.iter().map(Move::notation).collect()
+    }
+}
+
+impl WasmGame {
+    fn find_move(&self, text: &str) -> Option<Move> {
+        notation::parse_uci_move(&self.game, text)
+            .or_else(|| notation::parse_san(&self.game, text))
+            .filter(|mv| self.game.is_valid_move(mv.from, mv.to))
+    }
+}
+
+fn variant_named(name: Option<&str>) -> Result<Variant, JsError> {
+    match name {
+        Some(name) => Variant::from_name(name).ok_or_else(|| JsError::new(&format!("unknown variant '{}'", name))),
+        None => Ok(Variant::Standard),
+    }
+}
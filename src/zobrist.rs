@@ -0,0 +1,127 @@
+use crate::board::MAX_BOARD_SIZE;
+use crate::chess_move::Move;
+use crate::game::Game;
+use crate::piece::{Color, PieceType, PIECE_TYPE_COUNT};
+use std::sync::OnceLock;
+
+// one random key per piece type and colour on every square, plus the side to move,
+// castling rights and en passant file, xor-ed together to identify a position
+struct Keys {
+    pieces: Vec<u64>,
+    black_to_move: u64,
+    castling: [u64; 4],
+    en_passant: [u64; MAX_BOARD_SIZE],
+}
+
+fn keys() -> &'static Keys {
+    static KEYS: OnceLock<Keys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        // fixed seed so hashes are the same on every run
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = || {
+            // splitmix64
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        let pieces = (0..PIECE_TYPE_COUNT * 2 * MAX_BOARD_SIZE * MAX_BOARD_SIZE).map(|_| next()).collect();
+        let black_to_move = next();
+        let castling = [next(), next(), next(), next()];
+        let mut en_passant = [0; MAX_BOARD_SIZE];
+        for key in en_passant.iter_mut() {
+            *key = next();
+        }
+        Keys { pieces, black_to_move, castling, en_passant }
+    })
+}
+
+fn piece_key(piece_type: PieceType, color: Color, position: (usize, usize)) -> u64 {
+    let color_index = if color == Color::White { 0 } else { 1 };
+    let square = position.0 * MAX_BOARD_SIZE + position.1;
+    keys().pieces[(piece_type.index() * 2 + color_index) * MAX_BOARD_SIZE * MAX_BOARD_SIZE + square]
+}
+
+// hash of the whole position, equal positions always get the same value
+pub fn hash(game: &Game) -> u64 {
+    let keys = keys();
+    let mut hash = 0;
+    for row in 0..game.board.rows {
+        for col in 0..game.board.cols {
+            if let Some(piece) = game.board.get_piece_at((row, col)) {
+                hash ^= piece_key(piece.piece_type, piece.color, (row, col));
+            }
+        }
+    }
+    if game.current_player == Color::Black {
+        hash ^= keys.black_to_move;
+    }
+    for (right, key) in game.castling_rights().iter().zip(keys.castling) {
+        if *right {
+            hash ^= key;
+        }
+    }
+    if let Some((_, col)) = game.en_passant {
+        hash ^= keys.en_passant[col];
+    }
+    hash
+}
+
+// the hash after 'mv', worked out from the hash before it instead of from the whole board
+// 'after' is 'before' with the move made, only the squares the move can change are compared
+pub fn update(hash: u64, before: &Game, mv: &Move, after: &Game) -> u64 {
+    let keys = keys();
+    let (from, to) = (mv.from, mv.to);
+    let row = from.0;
+    // the en passant victim's square, and the rook's squares when the king castles
+    let squares = [from, to, (row, to.1), (row, 0), (row, before.board.cols - 1), (row, to.1.saturating_sub(1)), (row, to.1 + 1)];
+    let squares = &squares[..if mv.is_castling_move { 7 } else { 3 }];
+    let mut hash = hash ^ keys.black_to_move;
+    for (index, &square) in squares.iter().enumerate() {
+        // a square listed twice would cancel itself out
+        if square.1 >= before.board.cols || squares[..index].contains(&square) {
+            continue;
+        }
+        for piece in [before.board.get_piece_at(square), after.board.get_piece_at(square)].into_iter().flatten() {
+            hash ^= piece_key(piece.piece_type, piece.color, square);
+        }
+    }
+    for ((right_before, right_after), key) in before.castling_rights().iter().zip(after.castling_rights()).zip(keys.castling) {
+        if *right_before != right_after {
+            hash ^= key;
+        }
+    }
+    for en_passant in [before.en_passant, after.en_passant].into_iter().flatten() {
+        hash ^= keys.en_passant[en_passant.1];
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation;
+
+    // every move along lines that offer castling, en passant and promotions
+    #[test]
+    fn update_matches_a_full_hash() {
+        let check = |game: &Game| {
+            let key = hash(game);
+            for mv in game.generate_moves() {
+                let mut child = game.without_history();
+                child.make_move(&mv);
+                assert_eq!(update(key, game, &mv, &child), hash(&child), "after {}", mv.notation());
+            }
+        };
+        for line in ["e2e4 a7a6 e4e5 d7d5 g1f3 a6a5 f1c4 a5a4 e1g1", "e2e4 d7d5 e4d5 c7c6 d5c6 g8f6 c6b7 e7e5"] {
+            let mut game = Game::new();
+            for text in line.split_whitespace() {
+                check(&game);
+                let mv = notation::parse_uci_move(&game, text).unwrap();
+                game.make_move(&mv);
+            }
+            check(&game);
+        }
+    }
+}
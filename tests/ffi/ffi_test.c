@@ -0,0 +1,79 @@
+/* exercises the C API, build the library first (`cargo rustc --release --lib --crate-type cdylib`) and then from the repository root:
+ *   cc tests/ffi/ffi_test.c -Iinclude -Ltarget/release -lfinal_proj -o target/ffi_test
+ *   LD_LIBRARY_PATH=target/release ./target/ffi_test
+ */
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "final_proj.h"
+
+static void starting_position(void) {
+    ChessGame *game = chess_game_new();
+    char buffer[256];
+    assert(chess_game_legal_move_count(game) == 20);
+    assert(chess_game_turn(game) == CHESS_COLOR_WHITE);
+    assert(chess_game_state(game) == CHESS_STATE_ONGOING);
+    chess_game_fen(game, buffer, sizeof buffer);
+    assert(strcmp(buffer, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1") == 0);
+    chess_game_free(game);
+}
+
+static void legal_moves_fit_the_buffer(void) {
+    ChessGame *game = chess_game_new();
+    char small[10];
+    size_t needed = chess_game_legal_moves(game, small, sizeof small);
+    /* 20 moves of 4 letters with a space between them */
+    assert(needed == 20 * 5 - 1);
+    assert(strlen(small) == sizeof small - 1);
+    char large[128];
+    assert(chess_game_legal_moves(game, large, sizeof large) == needed);
+    assert(strstr(large, "e2e4") != NULL);
+    chess_game_free(game);
+}
+
+static void moves_are_checked(void) {
+    ChessGame *game = chess_game_new();
+    assert(chess_game_make_move(game, "e2e5") == CHESS_ERROR_ILLEGAL_MOVE);
+    assert(chess_game_make_move(game, "f2f3") == CHESS_OK);
+    assert(chess_game_turn(game) == CHESS_COLOR_BLACK);
+    assert(chess_game_make_move(game, "e7e5") == CHESS_OK);
+    assert(chess_game_make_move(game, "g2g4") == CHESS_OK);
+    assert(chess_game_make_move(game, "d8h4") == CHESS_OK);
+    assert(chess_game_state(game) == CHESS_STATE_CHECKMATE);
+    assert(chess_game_legal_move_count(game) == 0);
+    chess_game_free(game);
+}
+
+static void fen_and_variants(void) {
+    ChessGame *game = chess_game_new();
+    assert(chess_game_load_fen(game, "not a fen") == CHESS_ERROR_FEN);
+    /* empty-square counts that overflow or run far past the edge of the board */
+    assert(chess_game_load_fen(game, "99999999999999999999/8/8/8/8/8/8/8 w - - 0 1") == CHESS_ERROR_FEN);
+    assert(chess_game_load_fen(game, "4000000000/8/8/8/8/8/8/8 w - - 0 1") == CHESS_ERROR_FEN);
+    assert(chess_game_load_fen(game, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1") == CHESS_ERROR_FEN);
+    /* the game is left as it was */
+    assert(chess_game_legal_move_count(game) == 20);
+    assert(chess_game_load_fen(game, "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1") == CHESS_OK);
+    assert(chess_game_state(game) == CHESS_STATE_STALEMATE);
+    assert(chess_game_load_fen(NULL, "8/8/8/8/8/8/8/8 w - - 0 1") == CHESS_ERROR_NULL);
+    chess_game_free(game);
+
+    assert(chess_game_new_variant("shogi") == NULL);
+    ChessGame *grand = chess_game_new_variant("grand");
+    assert(grand != NULL);
+    char buffer[128];
+    chess_game_fen(grand, buffer, sizeof buffer);
+    assert(strncmp(buffer, "r8r/", 4) == 0);
+    chess_game_free(grand);
+    chess_game_free(NULL);
+}
+
+int main(void) {
+    starting_position();
+    legal_moves_fit_the_buffer();
+    moves_are_checked();
+    fen_and_variants();
+    printf("all FFI tests passed\n");
+    return 0;
+}
@@ -0,0 +1,81 @@
+# exercises the Python module, install it with `maturin develop --release` and run this file, or
+# without maturin build the library and load it from the build directory, from the repository root:
+#   cargo rustc --release --lib --features python,pyo3/extension-module --crate-type cdylib
+#   cp target/release/libfinal_proj.so target/release/final_proj.so
+#   PYTHONPATH=target/release python3 tests/python/smoke_test.py
+import final_proj
+
+
+def starting_position():
+    game = final_proj.Game()
+    assert game.fen() == "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    assert len(game.legal_moves()) == 20
+    assert "Nf3" in game.legal_moves_san()
+    assert game.turn == "white"
+    assert game.state == "ongoing"
+    assert game.board.piece_at("e1") == "K"
+    assert game.board.piece_at("e4") is None
+    assert len(game.board.pieces()) == 32
+
+
+def moves_are_checked():
+    game = final_proj.Game()
+    assert game.push("e2e4") == "e4"
+    assert game.push("e5") == "e5"
+    assert game.is_legal("Nf3") and not game.is_legal("e2e4")
+    try:
+        game.push("Ke3")
+        raise AssertionError("an illegal move was played")
+    except ValueError:
+        pass
+    # the fool's mate
+    game = final_proj.Game()
+    for move in ["f3", "e5", "g4", "Qh4#"]:
+        game.push(move)
+    assert game.state == "checkmate"
+    assert game.is_over() and game.winner() == "black" and game.result() == "0-1"
+    assert game.history() == ["f2f3", "e7e5", "g2g4", "d8h4"]
+    assert game.history_san() == ["f3", "e5", "g4", "Qh4#"]
+
+
+def fen_and_pgn():
+    game = final_proj.Game.from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1")
+    assert game.push("O-O") == "O-O"
+    assert game.fen() == "4k3/8/8/8/8/8/8/5RK1 b - - 1 1"
+    copy = game.copy()
+    copy.push("Kd7")
+    assert len(game.history()) == 1
+    pgn = game.to_pgn({"White": "Smoke"})
+    assert '[White "Smoke"]' in pgn and '[FEN "4k3/8/8/8/8/8/8/4K2R w K - 0 1"]' in pgn
+    games = final_proj.read_pgn('[Event "Test"]\n\n1. e4 e5 2. Nf3 Nc6 1-0\n')
+    assert len(games) == 1
+    assert games[0].tag("Event") == "Test" and games[0].result == "1-0"
+    assert games[0].game("standard").history() == ["e2e4", "e7e5", "g1f3", "b8c6"]
+    assert final_proj.Game("capablanca").board.cols == 10
+    try:
+        final_proj.Game.from_fen("not a fen")
+        raise AssertionError("a bad FEN was accepted")
+    except ValueError:
+        pass
+
+
+def search():
+    # mate in one
+    game = final_proj.Game.from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")
+    result = final_proj.search(game, 3)
+    assert result["move"] == "a1a8" and result["mate"] == 1
+    engine = final_proj.Engine(hash_mb=1, threads=2)
+    result = engine.search(final_proj.Game(), 3)
+    assert result["move"] in final_proj.Game().legal_moves()
+    assert result["depth"] == 3 and result["nodes"] > 0 and result["pv"][0] == result["move"]
+    lines = engine.best_lines(final_proj.Game(), 2, 3)
+    assert len(lines) == 3 and len({line["pv"][0] for line in lines}) == 3
+    engine.clear()
+
+
+if __name__ == "__main__":
+    starting_position()
+    moves_are_checked()
+    fen_and_pgn()
+    search()
+    print("all Python tests passed")
@@ -0,0 +1,13 @@
+#!/bin/sh
+# downloads the official Syzygy tables for the endings under tests/syzygy into tests/syzygy/official,
+# where the official_tables_agree test compares the decoder against them
+# SYZYGY_MIRROR can point at another copy of the 3-, 4- and 5-piece tables
+set -e
+mirror=${SYZYGY_MIRROR:-https://tablebase.lichess.ovh/tables/standard/3-4-5}
+cd "$(dirname "$0")"
+mkdir -p official
+for name in KQvK KRvK KBvK KNvK KPvK KRRvK KQvKR; do
+    for extension in rtbw rtbz; do
+        curl -fsSL -o "official/$name.$extension" "$mirror/$name.$extension"
+    done
+done
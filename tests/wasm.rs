@@ -0,0 +1,43 @@
+// runs in a headless browser: `wasm-pack test --headless --firefox --features wasm`
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use final_proj::wasm::WasmGame;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn starting_position_has_twenty_moves() {
+    let game = WasmGame::new(None).unwrap();
+    assert_eq!(game.legal_moves().len(), 20);
+    assert_eq!(game.turn(), "white");
+    assert_eq!(game.fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+}
+
+#[wasm_bindgen_test]
+fn illegal_moves_are_refused() {
+    let mut game = WasmGame::new(None).unwrap();
+    assert!(!game.is_legal("e2e5"));
+    assert!(game.make_move("e2e5").is_err());
+    assert_eq!(game.history().len(), 0);
+}
+
+#[wasm_bindgen_test]
+fn fools_mate_ends_the_game() {
+    let mut game = WasmGame::new(None).unwrap();
+    for mv in ["f2f3", "e7e5", "g2g4"] {
+        game.make_move(mv).unwrap();
+    }
+    assert_eq!(game.make_move("Qh4").unwrap(), "Qh4");
+    assert_eq!(game.state(), "checkmate");
+    assert!(game.is_over());
+    assert_eq!(game.winner().as_deref(), Some("black"));
+}
+
+#[wasm_bindgen_test]
+fn fen_sets_up_the_position() {
+    let game = WasmGame::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1", None).unwrap();
+    assert!(game.is_legal("e1g1"));
+    assert!(WasmGame::from_fen("not a fen", None).is_err());
+    assert!(WasmGame::new(Some(String::from("grand"))).unwrap().fen().starts_with("r8r/"));
+}